@@ -0,0 +1,63 @@
+use anyhow::Context;
+use bitsgap_shared::{
+    interval::{DatabaseIntervals, ExchangeIntervals, IntervalsDict, database_intervals},
+    pair::{ExchangeSymbols, SymbolsDict},
+    utils::Has,
+};
+
+use crate::{
+    rest::intervals::exchange_intervals,
+    symbols::exchange_symbols,
+    ws::intervals::{WsKlinesChannels, all_ws_klines_channels, supported_ws_klines_channels},
+};
+
+pub struct BinanceContext {
+    exchange_intervals: IntervalsDict,
+    ws_klines_channels: IntervalsDict,
+    database_intervals: IntervalsDict,
+    exchange_symbols: SymbolsDict,
+}
+impl BinanceContext {
+    pub fn init(only_supported_klines: bool) -> anyhow::Result<Self> {
+        let database_intervals = database_intervals().context("database intervals")?;
+        Ok(Self {
+            exchange_intervals: exchange_intervals().context("exchange intervals")?,
+            ws_klines_channels: if only_supported_klines {
+                supported_ws_klines_channels(&database_intervals)
+            } else {
+                all_ws_klines_channels()
+            }
+            .context("klines channels intervals")?,
+            database_intervals,
+            exchange_symbols: exchange_symbols().context("exchange symbols")?,
+        })
+    }
+
+    pub fn set_exchange_symbols(&mut self, exchange_symbols: SymbolsDict) {
+        self.exchange_symbols = exchange_symbols;
+    }
+}
+
+impl Has<ExchangeIntervals> for BinanceContext {
+    fn give(&self, _label: ExchangeIntervals) -> &IntervalsDict {
+        &self.exchange_intervals
+    }
+}
+
+impl Has<DatabaseIntervals> for BinanceContext {
+    fn give(&self, _label: DatabaseIntervals) -> &IntervalsDict {
+        &self.database_intervals
+    }
+}
+
+impl Has<WsKlinesChannels> for BinanceContext {
+    fn give(&self, _label: WsKlinesChannels) -> &IntervalsDict {
+        &self.ws_klines_channels
+    }
+}
+
+impl Has<ExchangeSymbols> for BinanceContext {
+    fn give(&self, _label: ExchangeSymbols) -> &SymbolsDict {
+        &self.exchange_symbols
+    }
+}
@@ -0,0 +1,272 @@
+use anyhow::Context as _;
+use bitsgap_shared::{
+    Api, ApiRequester,
+    exchange::{Exchange, MarketEvent},
+    interval::Interval,
+    journal::JournalConfig,
+    pair::{ExchangeSymbols, Pair},
+    records::{kline::Kline, recent_trade::RecentTrade},
+    utils::{Has, time::UnixMillis},
+    ws::Uri,
+};
+
+use crate::{
+    context::BinanceContext,
+    rest::{klines::KlinesRequest, trades::TradesRequest},
+    symbols,
+    ws::{
+        channels::Channel,
+        market_data::{MarketDataStream, parse_events},
+    },
+};
+
+/// Public market data of Binance spot
+pub struct Binance {
+    api: Api<BinanceContext>,
+    ws_uri: Uri,
+}
+
+impl Binance {
+    /// `ws_uri` is usually `ws::PUBLIC_WS_URI`
+    pub fn new(requester: ApiRequester<BinanceContext>, ws_uri: Uri) -> Self {
+        Self {
+            api: Api::Rest(requester),
+            ws_uri,
+        }
+    }
+
+    /// Only normalizes messages and connects to stream, REST requests fail
+    pub fn offline(context: BinanceContext, ws_uri: Uri) -> Self {
+        Self {
+            api: Api::Offline(context),
+            ws_uri,
+        }
+    }
+
+    /// Replaces symbols of test task with every market from `/api/v3/exchangeInfo`,
+    /// so subscriptions can be changed to any pair at runtime
+    pub async fn load_symbols(&mut self) -> anyhow::Result<()> {
+        let requester = self.api.requester_mut()?;
+        let symbols = symbols::load_symbols(requester).await?;
+        requester.context_mut().set_exchange_symbols(symbols);
+        Ok(())
+    }
+}
+
+impl Exchange for Binance {
+    type Channel = Channel;
+    type Context = BinanceContext;
+    type Stream<'a> = MarketDataStream<'a>;
+
+    const KLINES_LIMIT: u16 = KlinesRequest::<Pair>::MAX_LIMIT;
+
+    fn name(&self) -> &'static str {
+        crate::EXCHANGE
+    }
+
+    fn context(&self) -> &BinanceContext {
+        self.api.context()
+    }
+
+    fn default_pairs(&self) -> Vec<Pair> {
+        let exchange_symbols = self.context().give(ExchangeSymbols);
+        crate::TEST_TASK_ASSETS
+            .iter()
+            .map(|(base, quote)| Pair::new(*base, *quote))
+            .filter(|pair| exchange_symbols.to_symbol(pair).is_ok())
+            .collect()
+    }
+
+    fn channels(&self) -> Vec<Channel> {
+        Channel::supported(self.context()).collect()
+    }
+
+    async fn klines(
+        &self,
+        pair: &Pair,
+        interval: Interval,
+        since: UnixMillis,
+        until: Option<UnixMillis>,
+        limit: u16,
+    ) -> anyhow::Result<Vec<Kline>> {
+        let requester = self.api.requester()?;
+        let req = KlinesRequest {
+            pair,
+            interval,
+            limit: Some(limit),
+            start_time: Some(since),
+            end_time: until,
+        };
+        let responses = requester
+            .get_response(&req)
+            .await
+            .context("get klines response from rest api")?;
+        let received_at = UnixMillis::now();
+        responses
+            .iter()
+            .map(|response| response.kline(&req, received_at, requester.context()))
+            .collect::<anyhow::Result<_>>()
+            .context("convert klines responses to klines")
+    }
+
+    async fn recent_trades(&self, pair: &Pair) -> anyhow::Result<Vec<RecentTrade>> {
+        let req = TradesRequest {
+            pair,
+            limit: Some(TradesRequest::<&Pair>::MAX_LIMIT),
+        };
+        let responses = self
+            .api
+            .requester()?
+            .get_response(&req)
+            .await
+            .context("get trades response from rest api")?;
+        // Binance sends oldest first
+        responses
+            .iter()
+            .rev()
+            .map(|response| response.recent_trade(&req))
+            .collect::<anyhow::Result<_>>()
+            .context("convert trades to recent trades")
+    }
+
+    async fn stream(&self, journal: Option<JournalConfig>) -> anyhow::Result<MarketDataStream<'_>> {
+        MarketDataStream::connect(self.context(), self.ws_uri.clone(), journal).await
+    }
+
+    fn parse_events(&self, raw: &str, channel: Option<Channel>) -> Vec<MarketEvent<Channel>> {
+        parse_events(raw, channel, self.context())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use axum::{Router, routing::get};
+    use bitsgap_shared::{
+        exchange::MarketStream, interval::IntervalKind, records::kline::KlineStatus,
+    };
+    use futures::{SinkExt, StreamExt};
+    use tokio::{net::TcpListener, time::timeout};
+    use tokio_tungstenite::tungstenite::Message;
+
+    use super::*;
+    use crate::tests::{binance_requester, fixture};
+
+    /// Serves REST fixtures over HTTP and replays WS fixtures on subscription, like Binance would
+    async fn stand_in_server() -> (String, Uri) {
+        let rest = Router::new()
+            .route(
+                "/api/v3/exchangeInfo",
+                get(|| async { fixture("exchange_info.json") }),
+            )
+            .route("/api/v3/klines", get(|| async { fixture("klines.json") }))
+            .route("/api/v3/trades", get(|| async { fixture("trades.json") }));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, rest).await.unwrap() });
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ws_uri = format!("ws://{}/ws", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            let mut streams = vec![];
+            while let Some(Ok(msg)) = ws.next().await {
+                let Message::Text(text) = msg else {
+                    continue;
+                };
+                let request: serde_json::Value = serde_json::from_str(&text).unwrap();
+                let id = &request["id"];
+                let reply = match request["method"].as_str().unwrap() {
+                    "SUBSCRIBE" => {
+                        streams.extend(request["params"].as_array().unwrap().iter().cloned());
+                        serde_json::json!({"result": null, "id": id})
+                    }
+                    "LIST_SUBSCRIPTIONS" => serde_json::json!({"result": streams, "id": id}),
+                    method => panic!("unexpected method {method}"),
+                };
+                ws.send(Message::Text(reply.to_string().into()))
+                    .await
+                    .unwrap();
+                if request["method"] == "SUBSCRIBE" {
+                    for name in ["ws_kline.json", "ws_trade.json"] {
+                        ws.send(Message::Text(fixture(name).into())).await.unwrap();
+                    }
+                }
+            }
+        });
+        (base_url, ws_uri.parse().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_stand_in_server() {
+        let (base_url, ws_uri) = stand_in_server().await;
+        let mut binance = Binance::new(binance_requester(&base_url), ws_uri);
+        let pair = Pair::new("BTC", "USDT");
+        // pair outside of test task is known once markets are loaded
+        let sol = Pair::new("SOL", "USDT");
+        assert!(!binance.listed_pairs().contains(&sol));
+        binance.load_symbols().await.unwrap();
+        assert_eq!(binance.listed_pairs(), [pair.clone(), sol.clone()]);
+        assert_eq!(binance.default_pairs(), std::slice::from_ref(&pair));
+        let minute = Interval {
+            kind: IntervalKind::Minute,
+            value: 1,
+        };
+
+        let klines = binance
+            .klines(&pair, minute, UnixMillis(1738700700000), None, 2)
+            .await
+            .unwrap();
+        assert_eq!(klines.len(), 2);
+        assert!(klines[0].utc_begin < klines[1].utc_begin);
+        assert_eq!(klines[0].status, KlineStatus::Closed);
+        assert_eq!(klines[0].time_frame, "1m");
+
+        let trades = binance.recent_trades(&pair).await.unwrap();
+        assert_eq!(trades.len(), 3);
+        assert!(trades[0].timestamp > trades[2].timestamp);
+
+        let mut stream = binance.stream(None).await.unwrap();
+        let channels = [Channel::Klines(minute), Channel::Trades];
+        let request = stream.subscribe(&channels, &[pair, sol]).await.unwrap();
+        let mut events = vec![];
+        while events.len() < 4 {
+            let update = timeout(Duration::from_secs(5), stream.next())
+                .await
+                .unwrap()
+                .unwrap();
+            events.extend(update.events);
+        }
+        assert!(matches!(
+            events[..],
+            [
+                MarketEvent::Subscribed {
+                    channel: Channel::Klines(_),
+                    request: Some(first),
+                },
+                MarketEvent::Subscribed {
+                    channel: Channel::Trades,
+                    request: Some(second),
+                },
+                MarketEvent::Kline {
+                    channel: Channel::Klines(_),
+                    ..
+                },
+                MarketEvent::Trade { .. },
+            ] if first == request && second == request
+        ));
+
+        stream.list_subscriptions().await.unwrap();
+        let update = timeout(Duration::from_secs(5), stream.next())
+            .await
+            .unwrap()
+            .unwrap();
+        match &update.events[..] {
+            [MarketEvent::Subscriptions(subscriptions)] => assert_eq!(subscriptions, &channels),
+            other => panic!("unexpected events {other:?}"),
+        }
+        stream.close().await;
+    }
+}
@@ -0,0 +1,50 @@
+pub mod context;
+pub mod exchange;
+pub mod rest;
+pub mod symbols;
+pub mod units;
+pub mod ws;
+
+/// Name of exchange in records, same as `Exchange::name`
+pub const EXCHANGE: &str = "binance";
+
+pub const TEST_TASK_ASSETS: &[(&str, &str)] = &[
+    ("BTC", "USDT"),
+    ("TRX", "USDT"),
+    ("ETH", "USDT"),
+    ("DOGE", "USDT"),
+    ("BCH", "USDT"),
+];
+
+#[cfg(test)]
+mod tests {
+    use bitsgap_shared::{ApiConfig, ApiFactory, ApiRequester, AuthMethod};
+    use context::BinanceContext;
+
+    use super::*;
+
+    pub(crate) fn binance_requester(base_url: &str) -> ApiRequester<BinanceContext> {
+        let context = BinanceContext::init(true).unwrap();
+        ApiFactory::init(Default::default())
+            .unwrap()
+            .make_requester(
+                ApiConfig {
+                    base_url: base_url.try_into().unwrap(),
+                    auth: AuthMethod::None,
+                },
+                context,
+            )
+    }
+
+    /// Responses and messages recorded from Binance
+    pub(crate) fn fixture(name: &str) -> &'static str {
+        match name {
+            "exchange_info.json" => include_str!("../fixtures/exchange_info.json"),
+            "klines.json" => include_str!("../fixtures/klines.json"),
+            "trades.json" => include_str!("../fixtures/trades.json"),
+            "ws_kline.json" => include_str!("../fixtures/ws_kline.json"),
+            "ws_trade.json" => include_str!("../fixtures/ws_trade.json"),
+            _ => panic!("unknown fixture {name}"),
+        }
+    }
+}
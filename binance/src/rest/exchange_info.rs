@@ -0,0 +1,34 @@
+use bitsgap_shared::{
+    Request,
+    utils::url::{BuildUrl, UrlBuilder},
+};
+
+/// Symbols of every market with their assets
+pub struct ExchangeInfoRequest;
+
+impl Request for ExchangeInfoRequest {
+    type Response = ExchangeInfoResponse;
+    const ENDPOINT: &'static str = "api/v3/exchangeInfo";
+}
+
+impl<C> BuildUrl<C> for ExchangeInfoRequest {
+    fn build_url(&self, url_builder: &mut UrlBuilder, _context: &C) -> anyhow::Result<()> {
+        url_builder.add_path_segments(&["api", "v3", "exchangeInfo"])
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ExchangeInfoResponse {
+    pub symbols: Vec<SymbolInfo>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SymbolInfo {
+    /// like "BTCUSDT"
+    pub symbol: String,
+    /// "TRADING" if market can be traded
+    pub status: String,
+    pub base_asset: String,
+    pub quote_asset: String,
+}
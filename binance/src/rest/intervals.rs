@@ -0,0 +1,25 @@
+use bitsgap_shared::interval::{IntervalKind, IntervalsDict};
+
+// TOOD: load from config
+pub fn exchange_intervals() -> anyhow::Result<IntervalsDict> {
+    IntervalsDict::default()
+        .with(IntervalKind::Second, [(1, "1s")])?
+        .with(
+            IntervalKind::Minute,
+            [(1, "1m"), (3, "3m"), (5, "5m"), (15, "15m"), (30, "30m")],
+        )?
+        .with(
+            IntervalKind::Hour,
+            [
+                (1, "1h"),
+                (2, "2h"),
+                (4, "4h"),
+                (6, "6h"),
+                (8, "8h"),
+                (12, "12h"),
+            ],
+        )?
+        .with(IntervalKind::Day, [(1, "1d"), (3, "3d")])?
+        .with(IntervalKind::Week, [(1, "1w")])?
+        .with(IntervalKind::Month, [(1, "1M")])
+}
@@ -0,0 +1,166 @@
+use std::borrow::Borrow;
+
+use anyhow::{Context, bail};
+use bitsgap_shared::{
+    Request,
+    interval::{DatabaseIntervals, ExchangeIntervals, Interval},
+    pair::{ExchangeSymbols, Pair},
+    records::kline::{Kline, KlineStatus, VBS},
+    utils::{
+        Has,
+        time::UnixMillis,
+        url::{BuildUrl, UrlBuilder},
+    },
+};
+
+use crate::units::{BnCount, BnPrice, BnTimestamp, BnUnits};
+
+/// Klines, oldest first
+pub struct KlinesRequest<P = Pair> {
+    /// our pair, converted to exchange symbol name
+    pub pair: P,
+    pub interval: Interval,
+    /// maximum number of records returned. The default value is 500 and the max value is 1000
+    pub limit: Option<u16>,
+    /// filters by open time
+    pub start_time: Option<BnTimestamp>,
+    /// filters by open time
+    pub end_time: Option<BnTimestamp>,
+}
+
+impl<P> KlinesRequest<P> {
+    pub const MAX_LIMIT: u16 = 1000;
+}
+
+impl<P> Request for KlinesRequest<P> {
+    type Response = Vec<KlinesResponse>;
+    const ENDPOINT: &'static str = "api/v3/klines";
+}
+
+impl<P: Borrow<Pair>, C: Has<ExchangeIntervals> + Has<ExchangeSymbols>> BuildUrl<C>
+    for KlinesRequest<P>
+{
+    fn build_url(&self, url_builder: &mut UrlBuilder, context: &C) -> anyhow::Result<()> {
+        let symbol = context
+            .give(ExchangeSymbols)
+            .to_symbol(self.pair.borrow())?;
+        url_builder.add_path_segments(&["api", "v3", "klines"])?;
+
+        let supported_intervals = context.give(ExchangeIntervals);
+        let Some(interval_alias) = supported_intervals.to_alias(self.interval) else {
+            bail!("unsupported interval")
+        };
+        let mut query_builder = url_builder.query_builder()?;
+
+        query_builder.add_pair("symbol", symbol);
+        query_builder.add_pair("interval", interval_alias);
+        if let Some(limit) = self.limit {
+            query_builder.display_pair("limit", &limit)?;
+        }
+        if let Some(start_time) = self.start_time {
+            query_builder.display_pair("startTime", &start_time.as_i64())?;
+        }
+        if let Some(end_time) = self.end_time {
+            query_builder.display_pair("endTime", &end_time.as_i64())?;
+        }
+        Ok(())
+    }
+}
+
+/// Kline is sent as array of its fields
+#[derive(Debug, serde::Deserialize)]
+pub struct KlinesResponse(
+    /// open time
+    pub BnTimestamp,
+    /// open price
+    pub BnPrice,
+    /// high price
+    pub BnPrice,
+    /// low price
+    pub BnPrice,
+    /// close price
+    pub BnPrice,
+    /// base units traded
+    pub BnUnits,
+    /// close time
+    pub BnTimestamp,
+    /// quote units traded
+    pub BnUnits,
+    /// count of trades
+    pub BnCount,
+    /// base units traded by market buy orders
+    pub BnUnits,
+    /// quote units traded by market buy orders
+    pub BnUnits,
+    /// unused field
+    pub serde::de::IgnoredAny,
+);
+
+impl KlinesResponse {
+    /// REST response has no record time, so time of receiving tells whether kline is final
+    pub fn kline<C: Has<DatabaseIntervals>, P: Borrow<Pair>>(
+        &self,
+        request: &KlinesRequest<P>,
+        received_at: UnixMillis,
+        context: &C,
+    ) -> anyhow::Result<Kline> {
+        let KlinesRequest {
+            ref pair, interval, ..
+        } = *request;
+        let Self(
+            open_time,
+            open,
+            high,
+            low,
+            close,
+            volume,
+            close_time,
+            quote_volume,
+            trade_count,
+            taker_buy_volume,
+            taker_buy_quote_volume,
+            _,
+        ) = self;
+
+        let time_frame = context
+            .give(DatabaseIntervals)
+            .to_alias(interval)
+            .context("convert interval to databse time frame format")?
+            .into();
+
+        // numbers are verified by `bitsgap_shared::validation`, so invalid data can be quarantined instead of lost here
+        let volume: f64 = volume.parse().context("parse volume")?;
+        let quote_volume: f64 = quote_volume.parse().context("parse quote volume")?;
+        let taker_buy_volume: f64 = taker_buy_volume.parse().context("parse taker buy volume")?;
+        let taker_buy_quote_volume: f64 = taker_buy_quote_volume
+            .parse()
+            .context("parse taker buy quote volume")?;
+
+        Ok(Kline {
+            schema: Kline::SCHEMA_VERSION,
+            exchange: crate::EXCHANGE.into(),
+            pair: pair.borrow().clone(),
+            time_frame,
+            o: open.parse().context("parse open price")?,
+            h: high.parse().context("parse high price")?,
+            l: low.parse().context("parse low price")?,
+            c: close.parse().context("parse close price")?,
+            utc_begin: *open_time,
+            utc_end: *close_time,
+            utc_record: received_at,
+            status: KlineStatus::from_record_time(received_at, *close_time),
+            trade_count: *trade_count,
+            vwap: if volume > 0.0 {
+                quote_volume / volume
+            } else {
+                0.0
+            },
+            volume_bs: VBS {
+                buy_base: taker_buy_volume,
+                sell_base: volume - taker_buy_volume,
+                buy_quote: taker_buy_quote_volume,
+                sell_quote: quote_volume - taker_buy_quote_volume,
+            },
+        })
+    }
+}
@@ -0,0 +1,94 @@
+pub mod exchange_info;
+pub mod intervals;
+pub mod klines;
+pub mod trades;
+
+#[cfg(test)]
+mod tests {
+    use bitsgap_shared::{
+        interval::{Interval, IntervalKind},
+        pair::Pair,
+        records::kline::KlineStatus,
+        utils::time::UnixMillis,
+    };
+
+    use super::{
+        klines::{KlinesRequest, KlinesResponse},
+        trades::{TradesRequest, TradesResponse},
+    };
+    use crate::{
+        context::BinanceContext,
+        tests::{binance_requester, fixture},
+    };
+
+    const MINUTE: Interval = Interval {
+        kind: IntervalKind::Minute,
+        value: 1,
+    };
+
+    #[test]
+    fn test_binance_klines_url() {
+        let url = binance_requester("https://api.binance.com")
+            .build_url(&KlinesRequest {
+                pair: Pair::new("BTC", "USDT"),
+                interval: MINUTE,
+                limit: Some(10),
+                start_time: Some(UnixMillis(1738700743 * 1000)),
+                end_time: Some(UnixMillis(1738770743 * 1000)),
+            })
+            .unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://api.binance.com/api/v3/klines?symbol=BTCUSDT&interval=1m&limit=10&startTime=1738700743000&endTime=1738770743000"
+        );
+    }
+
+    #[test]
+    fn test_binance_trades_url() {
+        let url = binance_requester("https://api.binance.com")
+            .build_url(&TradesRequest {
+                pair: Pair::new("BTC", "USDT"),
+                limit: Some(1000),
+            })
+            .unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://api.binance.com/api/v3/trades?symbol=BTCUSDT&limit=1000"
+        );
+    }
+
+    #[test]
+    fn test_fixtures_to_records() {
+        let context = BinanceContext::init(true).unwrap();
+        let req = KlinesRequest {
+            pair: Pair::new("BTC", "USDT"),
+            interval: MINUTE,
+            limit: None,
+            start_time: None,
+            end_time: None,
+        };
+        let responses: Vec<KlinesResponse> = serde_json::from_str(fixture("klines.json")).unwrap();
+        // received while the second kline is still in progress
+        let received_at = UnixMillis(1738700790000);
+        let klines: Vec<_> = responses
+            .iter()
+            .map(|response| response.kline(&req, received_at, &context).unwrap())
+            .collect();
+        assert_eq!(klines[0].status, KlineStatus::Closed);
+        assert_eq!(klines[1].status, KlineStatus::InProgress);
+        assert_eq!(klines[1].trade_count, 812);
+        assert_eq!(klines[1].vwap, 98140.0);
+        assert_eq!(klines[1].volume_bs.buy_base, 2.0);
+        assert_eq!(klines[1].volume_bs.sell_base, 3.0);
+
+        let req = TradesRequest {
+            pair: Pair::new("BTC", "USDT"),
+            limit: None,
+        };
+        let responses: Vec<TradesResponse> = serde_json::from_str(fixture("trades.json")).unwrap();
+        let recent_trade = responses[0].recent_trade(&req).unwrap();
+        assert_eq!(recent_trade.tid, "4512350001");
+        assert_eq!(recent_trade.side, "sell");
+        assert_eq!(recent_trade.amount, "0.00100000");
+    }
+}
@@ -0,0 +1,85 @@
+use std::borrow::Borrow;
+
+use anyhow::Context as _;
+use bitsgap_shared::{
+    Request,
+    pair::{ExchangeSymbols, Pair},
+    records::recent_trade::RecentTrade,
+    utils::{
+        Has,
+        url::{BuildUrl, UrlBuilder},
+    },
+};
+
+use crate::{
+    units::{BnPrice, BnTimestamp, BnTradeId, BnUnits},
+    ws::trades::taker_side,
+};
+
+/// Latest public trades, oldest first
+pub struct TradesRequest<P = Pair> {
+    /// our pair, converted to exchange symbol name
+    pub pair: P,
+    /// maximum number of records returned. The default value is 500 and the max value is 1000
+    pub limit: Option<u16>,
+}
+
+impl<P> TradesRequest<P> {
+    pub const MAX_LIMIT: u16 = 1000;
+}
+
+impl<P> Request for TradesRequest<P> {
+    type Response = Vec<TradesResponse>;
+    const ENDPOINT: &'static str = "api/v3/trades";
+}
+
+impl<P: Borrow<Pair>, C: Has<ExchangeSymbols>> BuildUrl<C> for TradesRequest<P> {
+    fn build_url(&self, url_builder: &mut UrlBuilder, context: &C) -> anyhow::Result<()> {
+        let symbol = context
+            .give(ExchangeSymbols)
+            .to_symbol(self.pair.borrow())?;
+        url_builder.add_path_segments(&["api", "v3", "trades"])?;
+        let mut query_builder = url_builder.query_builder()?;
+        query_builder.add_pair("symbol", symbol);
+        if let Some(limit) = self.limit {
+            query_builder.display_pair("limit", &limit)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TradesResponse {
+    /// trade id
+    pub id: BnTradeId,
+    /// trade price
+    pub price: BnPrice,
+    /// base units traded
+    pub qty: BnUnits,
+    /// quote units traded
+    pub quote_qty: BnUnits,
+    /// time of the trade
+    pub time: BnTimestamp,
+    /// buyer's order was in the book, i.e. taker sold
+    pub is_buyer_maker: bool,
+}
+
+impl TradesResponse {
+    // REST response doesn't carry symbol, so we take pair from request
+    pub fn recent_trade<P: Borrow<Pair>>(
+        &self,
+        request: &TradesRequest<P>,
+    ) -> anyhow::Result<RecentTrade> {
+        Ok(RecentTrade {
+            schema: RecentTrade::SCHEMA_VERSION,
+            tid: self.id.to_string(),
+            exchange: crate::EXCHANGE.into(),
+            pair: request.pair.borrow().clone(),
+            price: self.price.clone(),
+            amount: self.qty.clone(),
+            side: taker_side(self.is_buyer_maker).into(),
+            timestamp: self.time.to_nanos().context("convert trade time")?,
+        })
+    }
+}
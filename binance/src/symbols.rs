@@ -0,0 +1,54 @@
+use anyhow::Context as _;
+use bitsgap_shared::{ApiRequester, pair::SymbolsDict};
+
+use crate::{
+    context::BinanceContext,
+    rest::exchange_info::{ExchangeInfoRequest, SymbolInfo},
+};
+
+/// Symbols of test task, until they're loaded from `/api/v3/exchangeInfo` by `Binance::load_symbols`
+pub fn exchange_symbols() -> anyhow::Result<SymbolsDict> {
+    // Binance symbols have no separator, like "BTCUSDT", so assets are given explicitly
+    let mut symbols = SymbolsDict::default();
+    for (base, quote) in crate::TEST_TASK_ASSETS {
+        symbols.add(format!("{base}{quote}"), base, quote)?;
+    }
+    Ok(symbols)
+}
+
+/// Every market from `/api/v3/exchangeInfo`, it's public, so requester needs no keys
+pub async fn load_symbols(requester: &ApiRequester<BinanceContext>) -> anyhow::Result<SymbolsDict> {
+    let response = requester
+        .get_response(&ExchangeInfoRequest)
+        .await
+        .context("get exchange info")?;
+    log::info!("Loaded {} markets of binance", response.symbols.len());
+    exchange_info_symbols(&response.symbols).context("symbols of markets")
+}
+
+/// Every market of Binance, whatever its status is
+pub fn exchange_info_symbols(infos: &[SymbolInfo]) -> anyhow::Result<SymbolsDict> {
+    let mut symbols = SymbolsDict::default();
+    for info in infos {
+        symbols
+            .add(info.symbol.clone(), &info.base_asset, &info.quote_asset)
+            .with_context(|| format!("add symbol of market {}", info.symbol))?;
+    }
+    Ok(symbols)
+}
+
+#[cfg(test)]
+mod tests {
+    use bitsgap_shared::pair::Pair;
+
+    use super::*;
+
+    #[test]
+    fn test_symbols_round_trip() {
+        let symbols = exchange_symbols().unwrap();
+        let pair = symbols.to_pair("BTCUSDT").unwrap();
+        assert_eq!(pair, &Pair::new("BTC", "USDT"));
+        assert_eq!(symbols.to_symbol(pair).unwrap(), "BTCUSDT");
+        assert!(symbols.to_pair("BTC_USDT").is_err());
+    }
+}
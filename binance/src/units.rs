@@ -0,0 +1,10 @@
+use bitsgap_shared::utils::time::UnixMillis;
+
+// Binance-specific basic units
+pub type BnSymbol = String;
+pub type BnTimestamp = UnixMillis;
+pub type BnPrice = String;
+pub type BnUnits = String;
+pub type BnCount = u32;
+pub type BnInterval = String;
+pub type BnTradeId = u64;
@@ -0,0 +1,88 @@
+use std::sync::LazyLock;
+
+use anyhow::Context as _;
+use bitsgap_shared::{
+    channel::{self, ChannelNames},
+    interval::IntervalsDict,
+};
+
+use super::{
+    intervals::{WsKlinesChannels, all_ws_klines_channels},
+    klines::KlineMessage,
+    trades::TradeMessage,
+};
+
+// names of all klines channels, to convert channel without context
+static KLINES_CHANNELS: LazyLock<IntervalsDict> =
+    LazyLock::new(|| all_ws_klines_channels().expect("valid klines channels"));
+
+const STREAM_SEPARATOR: char = '@';
+
+/// Names of Binance stream types, like "kline_1m" or "trade"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BinanceChannels;
+
+impl ChannelNames for BinanceChannels {
+    type Supported = WsKlinesChannels;
+
+    const EXCHANGE: &'static str = crate::EXCHANGE;
+    const TRADES: &'static str = "trade";
+    const SUPPORTED: WsKlinesChannels = WsKlinesChannels;
+
+    fn klines() -> &'static IntervalsDict {
+        &KLINES_CHANNELS
+    }
+}
+
+/// Public channel, (de)serialized as Binance stream type.
+/// Stream of channel is named after symbol too, like "btcusdt@trade".
+pub type Channel = channel::Channel<BinanceChannels>;
+
+/// Stream of channel of symbol, like "btcusdt@kline_1m"
+pub fn stream_name(channel: Channel, symbol: &str) -> anyhow::Result<String> {
+    Ok(format!(
+        "{}{STREAM_SEPARATOR}{}",
+        symbol.to_lowercase(),
+        channel.name()?
+    ))
+}
+
+/// Channel of stream name, like "btcusdt@kline_1m"
+pub fn stream_channel(stream: &str) -> anyhow::Result<Channel> {
+    let (_, channel) = stream
+        .split_once(STREAM_SEPARATOR)
+        .with_context(|| format!("stream {stream:?} has no separator"))?;
+    channel.parse()
+}
+
+/// Event of stream, tagged with its type
+#[derive(Debug, serde::Deserialize)]
+#[serde(tag = "e")]
+pub enum StreamEvent {
+    #[serde(rename = "kline")]
+    Klines(KlineMessage),
+    #[serde(rename = "trade")]
+    Trades(TradeMessage),
+}
+
+#[cfg(test)]
+mod tests {
+    use bitsgap_shared::interval::{Interval, IntervalKind};
+
+    use super::*;
+
+    #[test]
+    fn test_channel_names() {
+        let minute = Channel::Klines(Interval {
+            kind: IntervalKind::Minute,
+            value: 1,
+        });
+        for (channel, name) in [(minute, "kline_1m"), (Channel::Trades, "trade")] {
+            assert_eq!(channel.to_string(), name);
+            assert_eq!(name.parse::<Channel>().unwrap(), channel);
+        }
+        assert_eq!(stream_name(minute, "BTCUSDT").unwrap(), "btcusdt@kline_1m");
+        assert_eq!(stream_channel("btcusdt@trade").unwrap(), Channel::Trades);
+        assert!("kline_2m".parse::<Channel>().is_err());
+    }
+}
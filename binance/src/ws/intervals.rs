@@ -0,0 +1,30 @@
+use bitsgap_shared::{interval::IntervalsDict, utils::ValueLabel};
+
+use crate::rest::intervals::exchange_intervals;
+
+pub struct WsKlinesChannels;
+impl ValueLabel for WsKlinesChannels {
+    type Value = IntervalsDict;
+}
+
+// kline streams are named after REST intervals, like "kline_1m"
+pub fn all_ws_klines_channels() -> anyhow::Result<IntervalsDict> {
+    let mut res = IntervalsDict::default();
+    for (interval, alias) in exchange_intervals()?.iter() {
+        res.add(interval, format!("kline_{alias}"))?;
+    }
+    Ok(res)
+}
+
+pub fn supported_ws_klines_channels(
+    supported_intervals: &IntervalsDict,
+) -> anyhow::Result<IntervalsDict> {
+    let all = all_ws_klines_channels()?;
+    let mut res = IntervalsDict::default();
+    for (interval, alias) in all.iter() {
+        if supported_intervals.to_alias(interval).is_some() {
+            res.add(interval, alias.to_string())?;
+        }
+    }
+    Ok(res)
+}
@@ -0,0 +1,151 @@
+use anyhow::Context;
+use bitsgap_shared::{
+    interval::{DatabaseIntervals, ExchangeIntervals, Interval},
+    pair::ExchangeSymbols,
+    records::kline::{Kline, KlineStatus, VBS},
+    utils::Has,
+};
+
+use crate::units::{BnCount, BnInterval, BnPrice, BnSymbol, BnTimestamp, BnUnits};
+
+/// Kline stream event, pushed on every trade of the bucket
+#[derive(Debug, serde::Deserialize)]
+pub struct KlineMessage {
+    /// time the event was pushed
+    #[serde(rename = "E")]
+    pub event_time: BnTimestamp,
+    /// symbol name
+    #[serde(rename = "s")]
+    pub symbol: BnSymbol,
+    #[serde(rename = "k")]
+    pub kline: KlineData,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct KlineData {
+    /// start time of interval
+    #[serde(rename = "t")]
+    pub start_time: BnTimestamp,
+    /// close time of interval
+    #[serde(rename = "T")]
+    pub close_time: BnTimestamp,
+    #[serde(rename = "i")]
+    pub interval: BnInterval,
+    /// price at the start time
+    #[serde(rename = "o")]
+    pub open: BnPrice,
+    /// latest price
+    #[serde(rename = "c")]
+    pub close: BnPrice,
+    /// highest price over the interval
+    #[serde(rename = "h")]
+    pub high: BnPrice,
+    /// lowest price over the interval
+    #[serde(rename = "l")]
+    pub low: BnPrice,
+    /// base units traded over the interval
+    #[serde(rename = "v")]
+    pub volume: BnUnits,
+    /// count of trades
+    #[serde(rename = "n")]
+    pub trade_count: BnCount,
+    /// is this kline final
+    #[serde(rename = "x")]
+    pub is_closed: bool,
+    /// quote units traded over the interval
+    #[serde(rename = "q")]
+    pub quote_volume: BnUnits,
+    /// base units traded by market buy orders
+    #[serde(rename = "V")]
+    pub taker_buy_volume: BnUnits,
+    /// quote units traded by market buy orders
+    #[serde(rename = "Q")]
+    pub taker_buy_quote_volume: BnUnits,
+}
+
+impl KlineMessage {
+    pub fn interval<C: Has<ExchangeIntervals>>(&self, context: &C) -> anyhow::Result<Interval> {
+        context
+            .give(ExchangeIntervals)
+            .to_interval(&self.kline.interval)
+            .with_context(|| format!("interval {:?} is unknown", self.kline.interval))
+    }
+
+    pub fn kline<C: Has<DatabaseIntervals> + Has<ExchangeSymbols>>(
+        &self,
+        interval: Interval,
+        context: &C,
+    ) -> anyhow::Result<Kline> {
+        let Self {
+            event_time,
+            symbol,
+            kline:
+                KlineData {
+                    start_time,
+                    close_time,
+                    open,
+                    close,
+                    high,
+                    low,
+                    volume,
+                    trade_count,
+                    is_closed,
+                    quote_volume,
+                    taker_buy_volume,
+                    taker_buy_quote_volume,
+                    ..
+                },
+        } = self;
+
+        let time_frame = context
+            .give(DatabaseIntervals)
+            .to_alias(interval)
+            .context("convert interval to databse time frame format")?
+            .into();
+
+        let pair = context
+            .give(ExchangeSymbols)
+            .to_pair(symbol)
+            .context("convert symbol to pair")?
+            .clone();
+
+        let volume: f64 = volume.parse().context("parse volume")?;
+        let quote_volume: f64 = quote_volume.parse().context("parse quote volume")?;
+        let taker_buy_volume: f64 = taker_buy_volume.parse().context("parse taker buy volume")?;
+        let taker_buy_quote_volume: f64 = taker_buy_quote_volume
+            .parse()
+            .context("parse taker buy quote volume")?;
+
+        Ok(Kline {
+            schema: Kline::SCHEMA_VERSION,
+            exchange: crate::EXCHANGE.into(),
+            pair,
+            time_frame,
+            o: open.parse().context("parse open price")?,
+            h: high.parse().context("parse high price")?,
+            l: low.parse().context("parse low price")?,
+            c: close.parse().context("parse close price")?,
+            utc_begin: *start_time,
+            utc_end: *close_time,
+            utc_record: *event_time,
+            // unlike Poloniex, stream tells when kline is final
+            status: if *is_closed {
+                KlineStatus::Closed
+            } else {
+                KlineStatus::InProgress
+            },
+            trade_count: *trade_count,
+            vwap: if volume > 0.0 {
+                quote_volume / volume
+            } else {
+                0.0
+            },
+            volume_bs: VBS {
+                buy_base: taker_buy_volume,
+                sell_base: volume - taker_buy_volume,
+                buy_quote: taker_buy_quote_volume,
+                sell_quote: quote_volume - taker_buy_quote_volume,
+            },
+        })
+    }
+}
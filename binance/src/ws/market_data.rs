@@ -0,0 +1,218 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Context as _;
+use bitsgap_shared::{
+    exchange,
+    journal::JournalingCodec,
+    ws::{
+        Parsed, SimpleJsonCodec, Unparsed, Uri, WsClient,
+        market_data::{self, MarketProtocol},
+    },
+};
+
+use super::{
+    channels::{Channel, StreamEvent, stream_channel},
+    protocol::{ClientMsg, ErrorReply, EventData, Method, Reply, RequestId, ServerMsg},
+    public_ws_with_codec,
+};
+use crate::context::BinanceContext;
+
+/// Normalized event of public market data
+pub type MarketEvent = exchange::MarketEvent<Channel>;
+
+/// Events of one server message
+pub type MarketUpdate = exchange::MarketUpdate<Channel>;
+
+/// Requests waiting for reply. Server replies by id only, so channels are kept here.
+#[derive(Debug, Default)]
+pub struct Requests {
+    last_id: RequestId,
+    pending: BTreeMap<RequestId, (Method, Vec<Channel>)>,
+}
+
+impl Requests {
+    pub fn start(&mut self, method: Method, channels: Vec<Channel>) -> RequestId {
+        self.last_id += 1;
+        self.pending.insert(self.last_id, (method, channels));
+        self.last_id
+    }
+
+    fn reply(&mut self, Reply { result, id }: Reply) -> Vec<MarketEvent> {
+        let Some((method, channels)) = self.pending.remove(&id) else {
+            // e.g. request of previous connection
+            log::debug!("WS server replied to unknown request {id}");
+            return vec![];
+        };
+        match method {
+            Method::Subscribe => channels
+                .into_iter()
+                .map(|channel| MarketEvent::Subscribed {
+                    channel,
+                    request: Some(id),
+                })
+                .collect(),
+            Method::Unsubscribe => channels
+                .into_iter()
+                .map(|channel| MarketEvent::Unsubscribed {
+                    channel,
+                    request: Some(id),
+                })
+                .collect(),
+            Method::ListSubscriptions => {
+                let channels: BTreeSet<Channel> = result
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|stream| match stream_channel(stream) {
+                        Ok(channel) => Some(channel),
+                        Err(err) => {
+                            log::warn!("WS server has subscription of unknown stream: {err:#}");
+                            None
+                        }
+                    })
+                    .collect();
+                vec![MarketEvent::Subscriptions(channels.into_iter().collect())]
+            }
+        }
+    }
+}
+
+/// Normalizes server message, replies are matched with requests
+pub fn market_events(
+    msg: Parsed<ServerMsg>,
+    context: &BinanceContext,
+    requests: &mut Requests,
+) -> Vec<MarketEvent> {
+    let msg = match msg {
+        Parsed::Msg(msg) => msg,
+        Parsed::Unparsed(Unparsed { raw, error }) => {
+            return vec![MarketEvent::Invalid {
+                raw,
+                channel: None,
+                error,
+            }];
+        }
+    };
+    match msg {
+        ServerMsg::Event(event) => vec![stream_event(&event, context)],
+        ServerMsg::Reply(reply) => requests.reply(reply),
+        ServerMsg::Error(ErrorReply { error, id }) => {
+            if let Some(id) = id {
+                requests.pending.remove(&id);
+            }
+            vec![MarketEvent::Error {
+                message: format!("{error:?}"),
+                harmless: false,
+            }]
+        }
+    }
+}
+
+fn stream_event(event: &EventData, context: &BinanceContext) -> MarketEvent {
+    let res = event.event().and_then(|event| match event {
+        StreamEvent::Klines(msg) => {
+            let interval = msg.interval(context)?;
+            msg.kline(interval, context)
+                .context("convert kline message to kline")
+                .map(|kline| MarketEvent::Kline {
+                    channel: Channel::Klines(interval),
+                    interval,
+                    kline,
+                })
+        }
+        StreamEvent::Trades(msg) => msg
+            .recent_trade(context)
+            .context("convert trade to recent trade")
+            .map(|recent_trade| MarketEvent::Trade {
+                channel: Channel::Trades,
+                recent_trade,
+            }),
+    });
+    res.unwrap_or_else(|error| MarketEvent::Invalid {
+        raw: event.0.to_string(),
+        channel: event.channel(),
+        error,
+    })
+}
+
+/// Normalizes raw message. Events carry their own type, so known channel changes nothing.
+pub fn parse_events(
+    raw: &str,
+    channel: Option<Channel>,
+    context: &BinanceContext,
+) -> Vec<MarketEvent> {
+    match serde_json::from_str(raw) {
+        // replies without requests mean nothing
+        Ok(msg) => market_events(Parsed::Msg(msg), context, &mut Requests::default()),
+        Err(error) => vec![MarketEvent::Invalid {
+            raw: raw.into(),
+            channel,
+            error: anyhow::Error::new(error).context("deserialize JSON message"),
+        }],
+    }
+}
+
+/// Public server of Binance, keeps requests until they're replied
+#[derive(Debug, Default)]
+pub struct PublicProtocol {
+    requests: Requests,
+}
+
+impl MarketProtocol for PublicProtocol {
+    type Channel = Channel;
+    type Context = BinanceContext;
+    type ClientMsg = ClientMsg;
+    type ServerMsg = ServerMsg;
+
+    const JOURNAL_PREFIX: &'static str = "binance-public";
+
+    async fn connect(
+        uri: Uri,
+        codec: JournalingCodec<SimpleJsonCodec>,
+    ) -> anyhow::Result<WsClient<ClientMsg, Parsed<ServerMsg>>> {
+        public_ws_with_codec(uri, codec)
+            .await
+            .context("connect to binance public WebSocket server")
+    }
+
+    fn request(
+        &mut self,
+        subscribe: bool,
+        channels: &[Channel],
+        symbols: Vec<String>,
+    ) -> anyhow::Result<(RequestId, ClientMsg)> {
+        let method = if subscribe {
+            Method::Subscribe
+        } else {
+            Method::Unsubscribe
+        };
+        let params = ClientMsg::streams(channels, symbols.iter().map(String::as_str))?;
+        let id = self.requests.start(method, channels.to_vec());
+        Ok((id, ClientMsg { method, params, id }))
+    }
+
+    fn list_subscriptions(&mut self) -> ClientMsg {
+        let method = Method::ListSubscriptions;
+        let id = self.requests.start(method, vec![]);
+        ClientMsg {
+            method,
+            params: vec![],
+            id,
+        }
+    }
+
+    /// Subscriptions end with connection
+    fn unsubscribe_all(&self) -> Option<ClientMsg> {
+        None
+    }
+
+    fn events(&mut self, msg: Parsed<ServerMsg>, context: &BinanceContext) -> Vec<MarketEvent> {
+        market_events(msg, context, &mut self.requests)
+    }
+
+    fn forget_requests(&mut self) {
+        self.requests.pending.clear();
+    }
+}
+
+/// Public market data of Binance, `uri` of `connect` is usually `ws::PUBLIC_WS_URI`
+pub type MarketDataStream<'a> = market_data::MarketDataStream<'a, PublicProtocol>;
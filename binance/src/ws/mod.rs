@@ -0,0 +1,43 @@
+use std::time::Duration;
+
+use bitsgap_shared::{
+    utils::Strict,
+    ws::{CodecIn, CodecOut, Message, SimpleJsonCodec, Uri, WsClient, WsConfig},
+};
+use protocol::ClientMsg;
+
+pub mod channels;
+pub mod intervals;
+pub mod klines;
+pub mod market_data;
+pub mod protocol;
+pub mod trades;
+
+// TODO: move to config
+pub const PUBLIC_WS_URI: &str = "wss://stream.binance.com:9443/ws";
+
+/// `RX` is usually `ServerMsg`, or `Parsed<ServerMsg>` to receive unparsable messages too
+pub async fn public_ws<RX: Strict>(uri: Uri) -> anyhow::Result<WsClient<ClientMsg, RX>>
+where
+    SimpleJsonCodec: CodecIn<RX>,
+{
+    public_ws_with_codec(uri, SimpleJsonCodec).await
+}
+
+/// Same as `public_ws`, but with custom codec, e.g. `JournalingCodec`
+pub async fn public_ws_with_codec<C, RX: Strict>(
+    uri: Uri,
+    codec: C,
+) -> anyhow::Result<WsClient<ClientMsg, RX>>
+where
+    C: Strict + CodecOut<ClientMsg> + CodecIn<RX>,
+{
+    let config = WsConfig {
+        // server pings us every 20 seconds too, tungstenite replies to them
+        ping: Message::Ping(Default::default()),
+        ping_interval: Duration::from_secs(60),
+        uri,
+        codec,
+    };
+    config.start().await
+}
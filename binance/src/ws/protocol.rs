@@ -0,0 +1,133 @@
+use anyhow::Context as _;
+use serde::Deserialize as _;
+use serde_json::Value;
+
+use super::channels::{Channel, StreamEvent, stream_name};
+
+pub use bitsgap_shared::exchange::RequestId;
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Method {
+    Subscribe,
+    Unsubscribe,
+    ListSubscriptions,
+}
+
+/// Request to server, which replies with the same id
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct ClientMsg {
+    pub method: Method,
+    /// stream names, like "btcusdt@trade"
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub params: Vec<String>,
+    pub id: RequestId,
+}
+
+impl ClientMsg {
+    /// Streams of every channel of every symbol
+    pub fn streams<'s>(
+        channels: &[Channel],
+        symbols: impl IntoIterator<Item = &'s str>,
+    ) -> anyhow::Result<Vec<String>> {
+        let mut streams = vec![];
+        for symbol in symbols {
+            for channel in channels {
+                streams.push(stream_name(*channel, symbol)?);
+            }
+        }
+        Ok(streams)
+    }
+}
+
+/// Untagged, so variants are tried in order: stream event is anything which isn't a reply
+#[derive(Debug, serde::Deserialize)]
+#[serde(untagged)]
+pub enum ServerMsg {
+    Error(ErrorReply),
+    Reply(Reply),
+    Event(EventData),
+}
+
+/// `result` is null for (un)subscription and list of streams for `ListSubscriptions`
+#[derive(Debug, PartialEq, serde::Deserialize)]
+pub struct Reply {
+    pub result: Option<Vec<String>>,
+    pub id: RequestId,
+}
+
+#[derive(Debug, PartialEq, serde::Deserialize)]
+pub struct ErrorReply {
+    pub error: ServerError,
+    /// absent if request itself couldn't be parsed
+    pub id: Option<RequestId>,
+}
+
+#[derive(Debug, PartialEq, serde::Deserialize)]
+pub struct ServerError {
+    pub code: i64,
+    pub msg: String,
+}
+
+/// Stream event, kept untyped until it's converted, so invalid one can be sent to dead letters
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+pub struct EventData(pub Value);
+
+impl EventData {
+    /// Channel, guessed from event type and interval, even if the rest is invalid
+    pub fn channel(&self) -> Option<Channel> {
+        match self.0.get("e")?.as_str()? {
+            "trade" => Some(Channel::Trades),
+            "kline" => {
+                let interval = self.0.get("k")?.get("i")?.as_str()?;
+                format!("kline_{interval}").parse().ok()
+            }
+            _ => None,
+        }
+    }
+
+    pub fn event(&self) -> anyhow::Result<StreamEvent> {
+        StreamEvent::deserialize(&self.0).context("deserialize stream event")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_protocol() {
+        let msg = ClientMsg {
+            method: Method::Subscribe,
+            params: ClientMsg::streams(&[Channel::Trades], ["BTCUSDT"]).unwrap(),
+            id: 1,
+        };
+        assert_eq!(
+            serde_json::to_string(&msg).unwrap(),
+            r#"{"method":"SUBSCRIBE","params":["btcusdt@trade"],"id":1}"#
+        );
+
+        let msg: ServerMsg = serde_json::from_str(r#"{"result": null, "id": 1}"#).unwrap();
+        assert!(matches!(
+            msg,
+            ServerMsg::Reply(Reply {
+                result: None,
+                id: 1
+            })
+        ));
+        let msg: ServerMsg =
+            serde_json::from_str(r#"{"error": {"code": 2, "msg": "Invalid request"}, "id": 2}"#)
+                .unwrap();
+        assert!(matches!(
+            msg,
+            ServerMsg::Error(ErrorReply { id: Some(2), .. })
+        ));
+        let msg: ServerMsg =
+            serde_json::from_str(r#"{"e": "kline", "k": {"i": "1m", "renamed": 0}}"#).unwrap();
+        let ServerMsg::Event(event) = msg else {
+            panic!("unexpected message {msg:?}");
+        };
+        assert!(matches!(event.channel(), Some(Channel::Klines(_))));
+        assert!(event.event().is_err());
+    }
+}
@@ -0,0 +1,57 @@
+use anyhow::Context as _;
+use bitsgap_shared::{pair::ExchangeSymbols, records::recent_trade::RecentTrade, utils::Has};
+
+use crate::units::{BnPrice, BnSymbol, BnTimestamp, BnTradeId, BnUnits};
+
+#[derive(Debug, serde::Deserialize)]
+pub struct TradeMessage {
+    /// time the event was pushed
+    #[serde(rename = "E")]
+    pub event_time: BnTimestamp,
+    /// symbol name
+    #[serde(rename = "s")]
+    pub symbol: BnSymbol,
+    /// trade id
+    #[serde(rename = "t")]
+    pub id: BnTradeId,
+    /// trade price
+    #[serde(rename = "p")]
+    pub price: BnPrice,
+    /// base units traded
+    #[serde(rename = "q")]
+    pub quantity: BnUnits,
+    /// time of the trade
+    #[serde(rename = "T")]
+    pub trade_time: BnTimestamp,
+    /// buyer's order was in the book, i.e. taker sold
+    #[serde(rename = "m")]
+    pub is_buyer_maker: bool,
+}
+
+/// Binance tells which side was maker, we store taker's side
+pub fn taker_side(is_buyer_maker: bool) -> &'static str {
+    if is_buyer_maker { "sell" } else { "buy" }
+}
+
+impl TradeMessage {
+    pub fn recent_trade<C: Has<ExchangeSymbols>>(
+        &self,
+        context: &C,
+    ) -> anyhow::Result<RecentTrade> {
+        let pair = context
+            .give(ExchangeSymbols)
+            .to_pair(&self.symbol)
+            .context("convert symbol to pair")?
+            .clone();
+        Ok(RecentTrade {
+            schema: RecentTrade::SCHEMA_VERSION,
+            tid: self.id.to_string(),
+            exchange: crate::EXCHANGE.into(),
+            pair,
+            price: self.price.clone(),
+            amount: self.quantity.clone(),
+            side: taker_side(self.is_buyer_maker).into(),
+            timestamp: self.trade_time.to_nanos().context("convert trade time")?,
+        })
+    }
+}
@@ -1,11 +1,13 @@
 use anyhow::Context;
 use bitsgap_shared::{
     interval::{DatabaseIntervals, ExchangeIntervals, IntervalsDict, database_intervals},
+    pair::{ExchangeSymbols, SymbolsDict},
     utils::Has,
 };
 
 use crate::{
     rest::intervals::exchange_intervals,
+    symbols::exchange_symbols,
     ws::intervals::{WsCandlesChannels, all_ws_candles_channels, supported_ws_candles_channels},
 };
 
@@ -13,6 +15,7 @@ pub struct PoloniexContext {
     exchange_intervals: IntervalsDict,
     ws_candles_channels: IntervalsDict,
     database_intervals: IntervalsDict,
+    exchange_symbols: SymbolsDict,
 }
 impl PoloniexContext {
     pub fn init(only_supported_candles: bool) -> anyhow::Result<Self> {
@@ -26,8 +29,13 @@ impl PoloniexContext {
             }
             .context("candles channels intervals")?,
             database_intervals,
+            exchange_symbols: exchange_symbols().context("exchange symbols")?,
         })
     }
+
+    pub fn set_exchange_symbols(&mut self, exchange_symbols: SymbolsDict) {
+        self.exchange_symbols = exchange_symbols;
+    }
 }
 
 impl Has<ExchangeIntervals> for PoloniexContext {
@@ -47,3 +55,9 @@ impl Has<WsCandlesChannels> for PoloniexContext {
         &self.ws_candles_channels
     }
 }
+
+impl Has<ExchangeSymbols> for PoloniexContext {
+    fn give(&self, _label: ExchangeSymbols) -> &SymbolsDict {
+        &self.exchange_symbols
+    }
+}
@@ -0,0 +1,201 @@
+use anyhow::Context as _;
+use bitsgap_shared::{
+    Api, ApiRequester,
+    exchange::{Exchange, MarketEvent},
+    interval::Interval,
+    journal::JournalConfig,
+    pair::{ExchangeSymbols, Pair},
+    records::{kline::Kline, recent_trade::RecentTrade},
+    utils::{Has, time::UnixMillis},
+    ws::Uri,
+};
+
+use crate::{
+    context::PoloniexContext,
+    rest::{candles::CandlesRequest, trades::TradesRequest},
+    symbols,
+    ws::{
+        channels::Channel,
+        market_data::{MarketDataStream, parse_events},
+    },
+};
+
+/// Public market data of Poloniex
+pub struct Poloniex {
+    api: Api<PoloniexContext>,
+    ws_uri: Uri,
+}
+
+impl Poloniex {
+    /// `ws_uri` is usually `ws::PUBLIC_WS_URI`
+    pub fn new(requester: ApiRequester<PoloniexContext>, ws_uri: Uri) -> Self {
+        Self {
+            api: Api::Rest(requester),
+            ws_uri,
+        }
+    }
+
+    /// Only normalizes messages and connects to stream, REST requests fail
+    pub fn offline(context: PoloniexContext, ws_uri: Uri) -> Self {
+        Self {
+            api: Api::Offline(context),
+            ws_uri,
+        }
+    }
+
+    /// Replaces symbols of test task with every market from `/markets`,
+    /// so subscriptions can be changed to any pair at runtime
+    pub async fn load_symbols(&mut self) -> anyhow::Result<()> {
+        let requester = self.api.requester_mut()?;
+        let symbols = symbols::load_symbols(requester).await?;
+        requester.context_mut().set_exchange_symbols(symbols);
+        Ok(())
+    }
+}
+
+impl Exchange for Poloniex {
+    type Channel = Channel;
+    type Context = PoloniexContext;
+    type Stream<'a> = MarketDataStream<'a>;
+
+    // max value of candles request
+    const KLINES_LIMIT: u16 = 500;
+
+    fn name(&self) -> &'static str {
+        crate::EXCHANGE
+    }
+
+    fn context(&self) -> &PoloniexContext {
+        self.api.context()
+    }
+
+    fn default_pairs(&self) -> Vec<Pair> {
+        let exchange_symbols = self.context().give(ExchangeSymbols);
+        crate::TEST_TASK_SYMBOLS
+            .iter()
+            .filter_map(|symbol| exchange_symbols.to_pair(symbol).ok())
+            .filter(|pair| !exchange_symbols.is_delisted(pair))
+            .cloned()
+            .collect()
+    }
+
+    fn channels(&self) -> Vec<Channel> {
+        Channel::supported(self.context()).collect()
+    }
+
+    async fn klines(
+        &self,
+        pair: &Pair,
+        interval: Interval,
+        since: UnixMillis,
+        until: Option<UnixMillis>,
+        limit: u16,
+    ) -> anyhow::Result<Vec<Kline>> {
+        let requester = self.api.requester()?;
+        let req = CandlesRequest {
+            pair,
+            interval,
+            limit: Some(limit),
+            start_time: Some(since),
+            end_time: until,
+        };
+        let responses = requester
+            .get_response(&req)
+            .await
+            .context("get candles response from rest api")?;
+        responses
+            .iter()
+            .map(|response| response.kline(&req, requester.context()))
+            .collect::<anyhow::Result<_>>()
+            .context("convert candles to klines")
+    }
+
+    async fn recent_trades(&self, pair: &Pair) -> anyhow::Result<Vec<RecentTrade>> {
+        // there is no cursor for public trades, so only the latest ones are available
+        let req = TradesRequest {
+            pair,
+            limit: Some(TradesRequest::<&Pair>::MAX_LIMIT),
+        };
+        let responses = self
+            .api
+            .requester()?
+            .get_response(&req)
+            .await
+            .context("get trades response from rest api")?;
+        responses
+            .iter()
+            .map(|response| response.recent_trade(&req))
+            .collect::<anyhow::Result<_>>()
+            .context("convert trades to recent trades")
+    }
+
+    async fn stream(&self, journal: Option<JournalConfig>) -> anyhow::Result<MarketDataStream<'_>> {
+        MarketDataStream::connect(self.context(), self.ws_uri.clone(), journal).await
+    }
+
+    fn parse_events(&self, raw: &str, channel: Option<Channel>) -> Vec<MarketEvent<Channel>> {
+        parse_events(raw, channel, self.context())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Json, Router, routing::get};
+    use serde_json::{Value, json};
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::tests::stand_in_requester;
+
+    fn market(symbol: &str, base: &str, quote: &str) -> Value {
+        json!({
+            "symbol": symbol,
+            "baseCurrencyName": base,
+            "quoteCurrencyName": quote,
+            "displayName": format!("{base}/{quote}"),
+            "state": "NORMAL",
+            "symbolTradeLimit": {
+                "symbol": symbol,
+                "priceScale": 2,
+                "quantityScale": 6,
+                "amountScale": 2,
+                "minQuantity": "0.000001",
+                "minAmount": "1"
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_load_symbols() {
+        let markets = json!([
+            market("BTC_USDT", "BTC", "USDT"),
+            market("SOL_USDT", "SOL", "USDT"),
+        ]);
+        let rest = Router::new().route("/markets", get(|| async { Json(markets) }));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, rest).await.unwrap() });
+
+        let ws_uri: Uri = crate::ws::PUBLIC_WS_URI.parse().unwrap();
+        let mut poloniex = Poloniex::new(stand_in_requester(&base_url), ws_uri.clone());
+        let sol = Pair::new("SOL", "USDT");
+        assert!(!poloniex.listed_pairs().contains(&sol));
+        poloniex.load_symbols().await.unwrap();
+
+        // pair outside of test task can be requested and subscribed
+        assert_eq!(
+            poloniex.listed_pairs(),
+            [Pair::new("BTC", "USDT"), sol.clone()]
+        );
+        let exchange_symbols = poloniex.context().give(ExchangeSymbols);
+        assert_eq!(exchange_symbols.to_symbol(&sol).unwrap(), "SOL_USDT");
+        assert_eq!(poloniex.default_pairs(), [Pair::new("BTC", "USDT")]);
+
+        assert!(
+            Poloniex::offline(PoloniexContext::init(false).unwrap(), ws_uri)
+                .load_symbols()
+                .await
+                .is_err()
+        );
+    }
+}
@@ -1,8 +1,14 @@
 pub mod context;
+pub mod exchange;
 pub mod rest;
+pub mod symbols;
+pub mod trading;
 pub mod units;
 pub mod ws;
 
+/// Name of exchange in records, same as `Exchange::name`
+pub const EXCHANGE: &str = "poloniex";
+
 pub const TEST_TASK_SYMBOLS: &[&str] =
     &["BTC_USDT", "TRX_USDT", "ETH_USDT", "DOGE_USDT", "BCH_USDT"];
 
@@ -42,4 +48,17 @@ mod tests {
                 context,
             )
     }
+
+    /// Requester without keys, for local stand-in servers and URLs
+    pub(crate) fn stand_in_requester(base_url: &str) -> ApiRequester<PoloniexContext> {
+        ApiFactory::init(Default::default())
+            .unwrap()
+            .make_requester(
+                ApiConfig {
+                    base_url: base_url.try_into().unwrap(),
+                    auth: AuthMethod::None,
+                },
+                PoloniexContext::init(false).unwrap(),
+            )
+    }
 }
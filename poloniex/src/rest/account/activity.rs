@@ -0,0 +1,75 @@
+use bitsgap_shared::{
+    Request,
+    utils::url::{BuildUrl, UrlBuilder},
+};
+
+use crate::{
+    rest::orders::query::Direction,
+    units::{PxTimestamp, PxUnits},
+};
+
+/// Account activity other than trading, like deposits, withdrawals and transfers, newest first
+#[derive(Default)]
+pub struct ActivityRequest {
+    /// activity type code as in Poloniex docs, all types if not set
+    pub activity_type: Option<u16>,
+    pub currency: Option<String>,
+    /// activity id to page from
+    pub from: Option<String>,
+    pub direction: Option<Direction>,
+    /// maximum number of records returned. The default value is 50 and the max value is 100
+    pub limit: Option<u16>,
+    pub start_time: Option<PxTimestamp>,
+    pub end_time: Option<PxTimestamp>,
+}
+
+impl ActivityRequest {
+    pub const MAX_LIMIT: u16 = 100;
+}
+
+impl Request for ActivityRequest {
+    type Response = Vec<ActivityResponse>;
+    const ENDPOINT: &'static str = "accounts/activity";
+}
+
+impl<C> BuildUrl<C> for ActivityRequest {
+    fn build_url(&self, url_builder: &mut UrlBuilder, _context: &C) -> anyhow::Result<()> {
+        url_builder.add_path_segments(&["accounts", "activity"])?;
+        let mut query_builder = url_builder.query_builder()?;
+        if let Some(start_time) = self.start_time {
+            query_builder.display_pair("startTime", &start_time.as_i64())?;
+        }
+        if let Some(end_time) = self.end_time {
+            query_builder.display_pair("endTime", &end_time.as_i64())?;
+        }
+        if let Some(activity_type) = self.activity_type {
+            query_builder.display_pair("activityType", &activity_type)?;
+        }
+        if let Some(limit) = self.limit {
+            query_builder.display_pair("limit", &limit)?;
+        }
+        if let Some(from) = &self.from {
+            query_builder.add_pair("from", from);
+        }
+        if let Some(direction) = self.direction {
+            query_builder.add_pair("direction", direction.as_str());
+        }
+        if let Some(currency) = &self.currency {
+            query_builder.add_pair("currency", currency);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityResponse {
+    pub id: String,
+    pub currency: String,
+    /// negative if currency left the account
+    pub amount: PxUnits,
+    pub state: String,
+    pub create_time: PxTimestamp,
+    pub description: String,
+    pub activity_type: u16,
+}
@@ -0,0 +1,58 @@
+use bitsgap_shared::{
+    Request,
+    records::balance::Balance,
+    utils::{
+        time::UnixMillis,
+        url::{BuildUrl, UrlBuilder},
+    },
+};
+
+use crate::units::PxUnits;
+
+/// Balances of spot account
+pub struct BalancesRequest;
+
+impl Request for BalancesRequest {
+    type Response = Vec<AccountBalancesResponse>;
+    const ENDPOINT: &'static str = "accounts/balances";
+}
+
+impl<C> BuildUrl<C> for BalancesRequest {
+    fn build_url(&self, url_builder: &mut UrlBuilder, _context: &C) -> anyhow::Result<()> {
+        url_builder.add_path_segments(&["accounts", "balances"])?;
+        url_builder.query_builder()?.add_pair("accountType", "SPOT");
+        Ok(())
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountBalancesResponse {
+    pub account_id: String,
+    pub account_type: String,
+    pub balances: Vec<CurrencyBalanceResponse>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CurrencyBalanceResponse {
+    pub currency_id: String,
+    /// currency name, same as in symbols
+    pub currency: String,
+    pub available: PxUnits,
+    /// locked by open orders
+    pub hold: PxUnits,
+}
+
+impl AccountBalancesResponse {
+    // response has no time, so time of request is given
+    pub fn balances(&self, fetched_at: UnixMillis) -> impl Iterator<Item = Balance> + '_ {
+        self.balances.iter().map(move |balance| Balance {
+            exchange: crate::EXCHANGE.into(),
+            currency: balance.currency.clone(),
+            available: balance.available.clone(),
+            hold: balance.hold.clone(),
+            timestamp: fetched_at,
+        })
+    }
+}
@@ -0,0 +1,47 @@
+use bitsgap_shared::{
+    Request,
+    records::fee_tier::FeeTier,
+    utils::{
+        time::UnixMillis,
+        url::{BuildUrl, UrlBuilder},
+    },
+};
+
+/// Fee rates of account
+pub struct FeeInfoRequest;
+
+impl Request for FeeInfoRequest {
+    type Response = FeeInfoResponse;
+    const ENDPOINT: &'static str = "feeinfo";
+}
+
+impl<C> BuildUrl<C> for FeeInfoRequest {
+    fn build_url(&self, url_builder: &mut UrlBuilder, _context: &C) -> anyhow::Result<()> {
+        url_builder.add_path_segments(&["feeinfo"])
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeInfoResponse {
+    /// fees are paid with TRX at discount
+    pub trx_discount: bool,
+    pub maker_rate: String,
+    pub taker_rate: String,
+    /// 30 days trading volume in USD
+    #[serde(rename = "volume30D")]
+    pub volume_30d: String,
+}
+
+impl FeeInfoResponse {
+    // response has no time, so time of request is given
+    pub fn fee_tier(&self, fetched_at: UnixMillis) -> FeeTier {
+        FeeTier {
+            exchange: crate::EXCHANGE.into(),
+            maker_rate: self.maker_rate.clone(),
+            taker_rate: self.taker_rate.clone(),
+            volume_30d: self.volume_30d.clone(),
+            timestamp: fetched_at,
+        }
+    }
+}
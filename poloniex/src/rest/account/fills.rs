@@ -0,0 +1,177 @@
+use std::borrow::Borrow;
+
+use anyhow::Context as _;
+use bitsgap_shared::{
+    Request,
+    pair::{ExchangeSymbols, Pair},
+    records::fill::Fill,
+    utils::{
+        Has,
+        url::{BuildUrl, UrlBuilder},
+    },
+};
+use let_clone::let_clone;
+
+use crate::{
+    rest::orders::{OrderSide, OrderType, query::Direction},
+    units::{PxClientOrderId, PxOrderId, PxPrice, PxSymbol, PxTimestamp, PxUnits},
+};
+
+/// Fills of our orders, newest first.
+/// Poloniex keeps fills of the last 3 months, 7 days at most can be queried at once.
+pub struct FillsRequest<P = Pair> {
+    /// fills of all pairs if empty
+    pub pairs: Vec<P>,
+    /// `page_id` to page from
+    pub from: Option<String>,
+    pub direction: Option<Direction>,
+    /// maximum number of records returned. The default value is 100 and the max value is 1000
+    pub limit: Option<u16>,
+    pub start_time: Option<PxTimestamp>,
+    pub end_time: Option<PxTimestamp>,
+}
+
+impl<P> FillsRequest<P> {
+    pub const MAX_LIMIT: u16 = 1000;
+}
+
+impl<P> Default for FillsRequest<P> {
+    fn default() -> Self {
+        Self {
+            pairs: vec![],
+            from: None,
+            direction: None,
+            limit: None,
+            start_time: None,
+            end_time: None,
+        }
+    }
+}
+
+impl<P> Request for FillsRequest<P> {
+    type Response = Vec<FillResponse>;
+    const ENDPOINT: &'static str = "trades";
+}
+
+impl<P: Borrow<Pair>, C: Has<ExchangeSymbols>> BuildUrl<C> for FillsRequest<P> {
+    fn build_url(&self, url_builder: &mut UrlBuilder, context: &C) -> anyhow::Result<()> {
+        url_builder.add_path_segments(&["trades"])?;
+        let mut query_builder = url_builder.query_builder()?;
+        if !self.pairs.is_empty() {
+            let exchange_symbols = context.give(ExchangeSymbols);
+            let symbols = self
+                .pairs
+                .iter()
+                .map(|pair| exchange_symbols.to_symbol(pair.borrow()))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            query_builder.add_pair("symbols", symbols.join(","));
+        }
+        if let Some(limit) = self.limit {
+            query_builder.display_pair("limit", &limit)?;
+        }
+        if let Some(start_time) = self.start_time {
+            query_builder.display_pair("startTime", &start_time.as_i64())?;
+        }
+        if let Some(end_time) = self.end_time {
+            query_builder.display_pair("endTime", &end_time.as_i64())?;
+        }
+        if let Some(from) = &self.from {
+            query_builder.add_pair("from", from);
+        }
+        if let Some(direction) = self.direction {
+            query_builder.add_pair("direction", direction.as_str());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MatchRole {
+    Maker,
+    Taker,
+}
+
+impl MatchRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Maker => "maker",
+            Self::Taker => "taker",
+        }
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FillResponse {
+    /// fill id
+    pub id: String,
+    pub symbol: PxSymbol,
+    pub order_id: PxOrderId,
+    /// empty if order was placed without one
+    pub client_order_id: PxClientOrderId,
+    pub side: OrderSide,
+    #[serde(rename = "type")]
+    pub order_type: OrderType,
+    pub match_role: MatchRole,
+    pub price: PxPrice,
+    /// base units filled
+    pub quantity: PxUnits,
+    /// quote units filled
+    pub amount: PxUnits,
+    pub fee_currency: String,
+    pub fee_amount: PxUnits,
+    /// cursor for `FillsRequest::from`
+    pub page_id: String,
+    pub create_time: PxTimestamp,
+}
+
+impl FillResponse {
+    pub fn fill<C: Has<ExchangeSymbols>>(&self, context: &C) -> anyhow::Result<Fill> {
+        let Self {
+            id,
+            symbol,
+            order_id,
+            client_order_id,
+            side,
+            match_role,
+            price,
+            quantity,
+            amount,
+            fee_currency,
+            fee_amount,
+            create_time,
+            ..
+        } = self;
+        let_clone!(
+            id,
+            order_id,
+            client_order_id,
+            price,
+            // before quantity takes its name
+            amount: quote_amount,
+            quantity: amount,
+            fee_amount: fee,
+            fee_currency,
+        );
+        let side = match side {
+            OrderSide::Buy => "buy",
+            OrderSide::Sell => "sell",
+        };
+        Ok(Fill {
+            id,
+            exchange: crate::EXCHANGE.into(),
+            order_id,
+            client_order_id,
+            pair: context.give(ExchangeSymbols).to_pair(symbol)?.clone(),
+            side: side.into(),
+            role: match_role.as_str().into(),
+            price,
+            amount,
+            quote_amount,
+            fee,
+            fee_currency,
+            timestamp: create_time.to_nanos().context("convert create time")?,
+        })
+    }
+}
@@ -0,0 +1,118 @@
+//! Spot account: balances, fees, fills and other activity, every request needs HMAC authentication
+
+pub mod activity;
+pub mod balances;
+pub mod fees;
+pub mod fills;
+
+#[cfg(test)]
+mod tests {
+    use bitsgap_shared::{pair::Pair, utils::time::UnixMillis};
+
+    use super::{
+        activity::ActivityRequest,
+        balances::{AccountBalancesResponse, BalancesRequest},
+        fees::FeeInfoResponse,
+        fills::{FillResponse, FillsRequest},
+    };
+    use crate::{
+        context::PoloniexContext, rest::orders::query::Direction, tests::stand_in_requester,
+    };
+
+    #[test]
+    fn test_poloniex_account_urls() {
+        let requester = stand_in_requester("https://api.poloniex.com");
+        let url = requester.build_url(&BalancesRequest).unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://api.poloniex.com/accounts/balances?accountType=SPOT"
+        );
+
+        let url = requester
+            .build_url(&FillsRequest {
+                pairs: vec![Pair::new("BTC", "USDT"), Pair::new("ETH", "USDT")],
+                limit: Some(FillsRequest::<Pair>::MAX_LIMIT),
+                start_time: Some(UnixMillis(1738700743000)),
+                from: Some("32164924331503616".into()),
+                direction: Some(Direction::Pre),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://api.poloniex.com/trades?symbols=BTC_USDT%2CETH_USDT&limit=1000&startTime=1738700743000&from=32164924331503616&direction=PRE"
+        );
+
+        let url = requester
+            .build_url(&ActivityRequest {
+                activity_type: Some(200),
+                currency: Some("USDT".into()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://api.poloniex.com/accounts/activity?activityType=200&currency=USDT"
+        );
+    }
+
+    #[test]
+    fn test_poloniex_account_records() {
+        let context = PoloniexContext::init(false).unwrap();
+        let fetched_at = UnixMillis(1738700750000);
+
+        let accounts: Vec<AccountBalancesResponse> = serde_json::from_str(
+            r#"[{
+                "accountId": "1234",
+                "accountType": "SPOT",
+                "balances": [
+                    {"currencyId": "214", "currency": "USDT", "available": "120.5", "hold": "24.0002"},
+                    {"currencyId": "28", "currency": "BTC", "available": "0.0004", "hold": "0"}
+                ]
+            }]"#,
+        )
+        .unwrap();
+        let balances: Vec<_> = accounts[0].balances(fetched_at).collect();
+        assert_eq!(balances.len(), 2);
+        assert_eq!(balances[0].currency, "USDT");
+        assert_eq!(balances[0].hold, "24.0002");
+        assert_eq!(balances[1].exchange, "poloniex");
+
+        let fee_info: FeeInfoResponse = serde_json::from_str(
+            r#"{"trxDiscount": false, "makerRate": "0.0009", "takerRate": "0.0009", "volume30D": "0.00"}"#,
+        )
+        .unwrap();
+        let fee_tier = fee_info.fee_tier(fetched_at);
+        assert_eq!(fee_tier.maker_rate, "0.0009");
+        assert_eq!(fee_tier.volume_30d, "0.00");
+
+        let fill: FillResponse = serde_json::from_str(
+            r#"{
+                "id": "62561238",
+                "symbol": "BTC_USDT",
+                "accountType": "SPOT",
+                "orderId": "24993088082542592",
+                "side": "BUY",
+                "type": "LIMIT",
+                "matchRole": "MAKER",
+                "createTime": 1738700750000,
+                "price": "60000.5",
+                "quantity": "0.0004",
+                "amount": "24.0002",
+                "feeCurrency": "BTC",
+                "feeAmount": "0.00000036",
+                "pageId": "32164924331503616",
+                "clientOrderId": "grid-1"
+            }"#,
+        )
+        .unwrap();
+        let fill = fill.fill(&context).unwrap();
+        assert_eq!(fill.pair, Pair::new("BTC", "USDT"));
+        assert_eq!(fill.side, "buy");
+        assert_eq!(fill.role, "maker");
+        assert_eq!(fill.amount, "0.0004");
+        assert_eq!(fill.quote_amount, "24.0002");
+        assert_eq!(fill.fee, "0.00000036");
+        assert_eq!(fill.timestamp.as_i64(), 1738700750000 * 1_000_000);
+    }
+}
@@ -1,8 +1,11 @@
+use std::borrow::Borrow;
+
 use anyhow::{Context, bail};
 use bitsgap_shared::{
     Request,
     interval::{DatabaseIntervals, ExchangeIntervals, Interval},
-    records::kline::{Kline, VBS},
+    pair::{ExchangeSymbols, Pair},
+    records::kline::{Kline, KlineStatus, VBS},
     utils::{
         Has,
         url::{BuildUrl, UrlBuilder},
@@ -11,28 +14,34 @@ use bitsgap_shared::{
 
 use crate::units::{PxCount, PxInterval, PxPrice, PxTimestamp, PxUnits};
 
-pub struct CandlesRequest<S> {
-    /// symbol name
-    pub symbol: S,
+pub struct CandlesRequest<P = Pair> {
+    /// our pair, converted to exchange symbol name
+    pub pair: P,
     /// the unit of time to aggregate data by
     pub interval: Interval,
     /// maximum number of records returned. The default value is 100 and the max value is 500
     pub limit: Option<u16>,
     /// filters by time
     /// the default value is 0
-    pub start_time: Option<u64>,
+    pub start_time: Option<PxTimestamp>,
     /// filters by time
     /// the default value is current time
-    pub end_time: Option<u64>,
+    pub end_time: Option<PxTimestamp>,
 }
 
-impl<S> Request for CandlesRequest<S> {
+impl<P> Request for CandlesRequest<P> {
     type Response = Vec<CandlesResponse>;
+    const ENDPOINT: &'static str = "markets/{symbol}/candles";
 }
 
-impl<S: AsRef<str>, C: Has<ExchangeIntervals>> BuildUrl<C> for CandlesRequest<S> {
+impl<P: Borrow<Pair>, C: Has<ExchangeIntervals> + Has<ExchangeSymbols>> BuildUrl<C>
+    for CandlesRequest<P>
+{
     fn build_url(&self, url_builder: &mut UrlBuilder, context: &C) -> anyhow::Result<()> {
-        url_builder.add_path_segments(&["markets", self.symbol.as_ref(), "candles"])?;
+        let symbol = context
+            .give(ExchangeSymbols)
+            .to_symbol(self.pair.borrow())?;
+        url_builder.add_path_segments(&["markets", symbol, "candles"])?;
 
         let supported_intervals = context.give(ExchangeIntervals);
         let Some(interval_alias) = supported_intervals.to_alias(self.interval) else {
@@ -45,10 +54,10 @@ impl<S: AsRef<str>, C: Has<ExchangeIntervals>> BuildUrl<C> for CandlesRequest<S>
             query_builder.display_pair("limit", &limit)?;
         }
         if let Some(start_time) = self.start_time {
-            query_builder.display_pair("startTime", &start_time)?;
+            query_builder.display_pair("startTime", &start_time.as_i64())?;
         }
         if let Some(end_time) = self.end_time {
-            query_builder.display_pair("endTime", &end_time)?;
+            query_builder.display_pair("endTime", &end_time.as_i64())?;
         }
         Ok(())
     }
@@ -90,16 +99,14 @@ pub struct CandlesResponse {
 
 impl CandlesResponse {
     // TODO: refactor into trait, something like `ToInternal`
-    pub fn kline<C: Has<DatabaseIntervals>, S: AsRef<str>>(
+    pub fn kline<C: Has<DatabaseIntervals>, P: Borrow<Pair>>(
         &self,
-        request: &CandlesRequest<S>,
+        request: &CandlesRequest<P>,
         context: &C,
     ) -> anyhow::Result<Kline> {
         // TODO: check if intervals in request and response match?
         let CandlesRequest {
-            ref symbol,
-            interval,
-            ..
+            ref pair, interval, ..
         } = *request;
         let CandlesResponse {
             low,
@@ -110,12 +117,15 @@ impl CandlesResponse {
             quantity,
             buy_taker_amount,
             buy_taker_quantity,
+            trade_count,
+            weighted_average,
             start_time,
+            close_time,
+            record_time,
             ..
         } = self;
 
-        // Right now, it does match to database format from test task, but we need think about converting it in the futures
-        let pair = symbol.as_ref().into();
+        let pair = pair.borrow().clone();
 
         let time_frame = context
             .give(DatabaseIntervals)
@@ -124,7 +134,7 @@ impl CandlesResponse {
             .into();
 
         // TODO: make type which deserializes as String, but stores in-memory as number, use it in CandlesResponse
-        // TODO: verify numbers to be positive and finite
+        // numbers are verified by `bitsgap_shared::validation`, so invalid data can be quarantined instead of lost here
         let amount: f64 = amount.parse().context("parse amount")?;
         let quantity: f64 = quantity.parse().context("parse quantity")?;
         let buy_taker_amount: f64 = buy_taker_amount.parse().context("parse buy taker amount")?;
@@ -138,21 +148,22 @@ impl CandlesResponse {
             buy_quote: buy_taker_amount,
             sell_quote: amount - buy_taker_amount,
         };
-        if volume_bs.sell_base < 0.0 {
-            bail!("sell base volume is negative");
-        }
-        if volume_bs.sell_quote < 0.0 {
-            bail!("sell quote volume is negative");
-        }
 
         Ok(Kline {
+            schema: Kline::SCHEMA_VERSION,
+            exchange: crate::EXCHANGE.into(),
             pair,
             time_frame,
             o: open.parse().context("parse open price")?,
             h: high.parse().context("parse open price")?,
             l: low.parse().context("parse open price")?,
             c: close.parse().context("parse open price")?,
-            utc_begin: (*start_time).try_into().context("convert start time")?,
+            utc_begin: *start_time,
+            utc_end: *close_time,
+            utc_record: *record_time,
+            status: KlineStatus::from_record_time(*record_time, *close_time),
+            trade_count: *trade_count,
+            vwap: weighted_average.parse().context("parse weighted average")?,
             volume_bs,
         })
     }
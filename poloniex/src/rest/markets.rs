@@ -0,0 +1,97 @@
+use std::borrow::Borrow;
+
+use bitsgap_shared::{
+    Request,
+    pair::{ExchangeSymbols, Pair},
+    utils::{
+        Has,
+        url::{BuildUrl, UrlBuilder},
+    },
+};
+
+use crate::units::{PxPrice, PxSymbol, PxTimestamp, PxUnits};
+
+/// Markets with their trading rules, of a single pair or of every one
+pub struct MarketsRequest<P = Pair> {
+    pub pair: Option<P>,
+}
+
+impl<P> Request for MarketsRequest<P> {
+    type Response = Vec<MarketResponse>;
+    const ENDPOINT: &'static str = "markets";
+}
+
+impl<P: Borrow<Pair>, C: Has<ExchangeSymbols>> BuildUrl<C> for MarketsRequest<P> {
+    fn build_url(&self, url_builder: &mut UrlBuilder, context: &C) -> anyhow::Result<()> {
+        match &self.pair {
+            Some(pair) => {
+                let symbol = context.give(ExchangeSymbols).to_symbol(pair.borrow())?;
+                url_builder.add_path_segments(&["markets", symbol])
+            }
+            None => url_builder.add_path_segments(&["markets"]),
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarketResponse {
+    pub symbol: PxSymbol,
+    pub base_currency_name: String,
+    pub quote_currency_name: String,
+    /// "NORMAL" if market can be traded
+    pub state: String,
+    pub symbol_trade_limit: SymbolTradeLimit,
+}
+
+impl MarketResponse {
+    pub const NORMAL_STATE: &'static str = "NORMAL";
+}
+
+/// Precision and minimal size of orders
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SymbolTradeLimit {
+    /// decimal places of price
+    pub price_scale: u32,
+    /// decimal places of base units
+    pub quantity_scale: u32,
+    /// decimal places of quote units
+    pub amount_scale: u32,
+    /// minimal base units of order
+    pub min_quantity: PxUnits,
+    /// minimal quote units of order
+    pub min_amount: PxUnits,
+}
+
+/// Latest trade price of pair
+pub struct MarketPriceRequest<P = Pair> {
+    pub pair: P,
+}
+
+impl<P> Request for MarketPriceRequest<P> {
+    type Response = MarketPriceResponse;
+    const ENDPOINT: &'static str = "markets/{symbol}/price";
+}
+
+impl<P: Borrow<Pair>, C: Has<ExchangeSymbols>> BuildUrl<C> for MarketPriceRequest<P> {
+    fn build_url(&self, url_builder: &mut UrlBuilder, context: &C) -> anyhow::Result<()> {
+        let symbol = context
+            .give(ExchangeSymbols)
+            .to_symbol(self.pair.borrow())?;
+        url_builder.add_path_segments(&["markets", symbol, "price"])
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarketPriceResponse {
+    pub symbol: PxSymbol,
+    /// price of the latest trade
+    pub price: PxPrice,
+    /// time the price was updated
+    pub time: PxTimestamp,
+    /// time the record was pushed
+    #[serde(rename = "ts")]
+    pub record_time: PxTimestamp,
+}
@@ -1,15 +1,27 @@
+pub mod account;
 pub mod candles;
 pub mod intervals;
+pub mod markets;
+pub mod orders;
+pub mod trades;
 
 #[cfg(test)]
 mod tests {
     use bitsgap_shared::{
         interval::{Interval, IntervalKind},
-        utils::url::BuildUrl,
+        pair::{ExchangeSymbols, Pair},
+        utils::{Has, time::UnixMillis, url::BuildUrl},
     };
 
-    use super::candles::CandlesRequest;
-    use crate::{context::PoloniexContext, tests::poloniex_requester};
+    use super::{
+        candles::CandlesRequest,
+        markets::{MarketPriceRequest, MarketsRequest},
+        trades::TradesRequest,
+    };
+    use crate::{
+        context::PoloniexContext,
+        tests::{poloniex_requester, stand_in_requester},
+    };
 
     async fn poloniex_get_and_print_json<B: BuildUrl<PoloniexContext>>(path: &B) {
         let value: serde_json::Value = poloniex_requester().get_json(path).await.unwrap();
@@ -23,22 +35,34 @@ mod tests {
 
     #[tokio::test]
     async fn get_poloniex_trades() {
-        poloniex_get_and_print_json(&&["markets", "BTC_USDT", "trades"]).await;
+        let requester = poloniex_requester();
+        for pair in requester.context().give(ExchangeSymbols).listed() {
+            let req = TradesRequest {
+                pair,
+                limit: Some(3),
+            };
+            let responses = requester.get_response(&req).await.unwrap();
+            assert!(responses.len() <= 3);
+            for response in responses {
+                let recent_trade = response.recent_trade(&req).unwrap();
+                println!("{}", serde_json::to_string(&recent_trade).unwrap());
+            }
+        }
     }
 
     #[tokio::test]
     async fn get_poloniex_candles() {
         let requester = poloniex_requester();
-        for symbol in crate::TEST_TASK_SYMBOLS {
+        for pair in requester.context().give(ExchangeSymbols).listed() {
             let req = CandlesRequest {
-                symbol,
+                pair,
                 interval: Interval {
                     kind: IntervalKind::Minute,
                     value: 1,
                 },
                 limit: Some(1),
-                start_time: Some(1738700743 * 1000),
-                end_time: Some(1738770743 * 1000),
+                start_time: Some(UnixMillis(1738700743 * 1000)),
+                end_time: Some(UnixMillis(1738770743 * 1000)),
             };
             let responses = requester.get_response(&req).await.unwrap();
             for response in responses {
@@ -52,14 +76,14 @@ mod tests {
     fn test_poliniex_candles_url() {
         let url = poloniex_requester()
             .build_url(&CandlesRequest {
-                symbol: "BTC_USDT",
+                pair: Pair::new("BTC", "USDT"),
                 interval: Interval {
                     kind: IntervalKind::Minute,
                     value: 1,
                 },
                 limit: Some(10),
-                start_time: Some(1738700743 * 1000),
-                end_time: Some(1738770743 * 1000),
+                start_time: Some(UnixMillis(1738700743 * 1000)),
+                end_time: Some(UnixMillis(1738770743 * 1000)),
             })
             .unwrap();
         assert_eq!(
@@ -67,4 +91,36 @@ mod tests {
             "https://api.poloniex.com/markets/BTC_USDT/candles?interval=MINUTE_1&limit=10&startTime=1738700743000&endTime=1738770743000"
         );
     }
+
+    #[test]
+    fn test_poliniex_trades_url() {
+        let url = poloniex_requester()
+            .build_url(&TradesRequest {
+                pair: Pair::new("BTC", "USDT"),
+                limit: Some(1000),
+            })
+            .unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://api.poloniex.com/markets/BTC_USDT/trades?limit=1000"
+        );
+    }
+
+    #[test]
+    fn test_poloniex_markets_urls() {
+        let requester = stand_in_requester("https://api.poloniex.com");
+        let url = requester
+            .build_url(&MarketsRequest::<Pair> { pair: None })
+            .unwrap();
+        assert_eq!(url.as_str(), "https://api.poloniex.com/markets");
+        let url = requester
+            .build_url(&MarketPriceRequest {
+                pair: Pair::new("BTC", "USDT"),
+            })
+            .unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://api.poloniex.com/markets/BTC_USDT/price"
+        );
+    }
 }
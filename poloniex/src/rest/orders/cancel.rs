@@ -0,0 +1,128 @@
+use std::borrow::Borrow;
+
+use anyhow::Context as _;
+use bitsgap_shared::{
+    BuildBody, Method, Request,
+    pair::{ExchangeSymbols, Pair},
+    utils::{
+        Has,
+        url::{BuildUrl, UrlBuilder},
+    },
+};
+
+use super::{OrderRef, OrderState};
+use crate::units::{PxClientOrderId, PxOrderId};
+
+/// Cancels a single order
+pub struct CancelOrderRequest {
+    pub order: OrderRef,
+}
+
+impl Request for CancelOrderRequest {
+    type Response = CancelOrderResponse;
+    const ENDPOINT: &'static str = "orders/{id}";
+    const METHOD: Method = Method::DELETE;
+}
+
+impl<C> BuildUrl<C> for CancelOrderRequest {
+    fn build_url(&self, url_builder: &mut UrlBuilder, _context: &C) -> anyhow::Result<()> {
+        url_builder.add_path_segments(&["orders", &self.order.path_segment()])
+    }
+}
+
+impl<C> BuildBody<C> for CancelOrderRequest {}
+
+/// Cancels orders by ids, client order ids or both
+pub struct CancelOrdersRequest {
+    pub order_ids: Vec<PxOrderId>,
+    pub client_order_ids: Vec<PxClientOrderId>,
+}
+
+impl Request for CancelOrdersRequest {
+    type Response = Vec<CancelOrderResponse>;
+    const ENDPOINT: &'static str = "orders/cancelByIds";
+    const METHOD: Method = Method::DELETE;
+}
+
+impl<C> BuildUrl<C> for CancelOrdersRequest {
+    fn build_url(&self, url_builder: &mut UrlBuilder, _context: &C) -> anyhow::Result<()> {
+        url_builder.add_path_segments(&["orders", "cancelByIds"])
+    }
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CancelByIdsBody<'a> {
+    order_ids: &'a [PxOrderId],
+    client_order_ids: &'a [PxClientOrderId],
+}
+
+impl<C> BuildBody<C> for CancelOrdersRequest {
+    fn build_body(&self, _context: &C) -> anyhow::Result<Option<String>> {
+        let body = CancelByIdsBody {
+            order_ids: &self.order_ids,
+            client_order_ids: &self.client_order_ids,
+        };
+        serde_json::to_string(&body)
+            .map(Some)
+            .context("serialize order ids")
+    }
+}
+
+/// Cancels all open orders of pairs, or of every pair if there are none
+pub struct CancelAllOrdersRequest<P = Pair> {
+    pub pairs: Vec<P>,
+}
+
+impl<P> Request for CancelAllOrdersRequest<P> {
+    type Response = Vec<CancelOrderResponse>;
+    const ENDPOINT: &'static str = "orders";
+    const METHOD: Method = Method::DELETE;
+}
+
+impl<P, C> BuildUrl<C> for CancelAllOrdersRequest<P> {
+    fn build_url(&self, url_builder: &mut UrlBuilder, _context: &C) -> anyhow::Result<()> {
+        url_builder.add_path_segments(&["orders"])
+    }
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CancelAllBody<'a> {
+    symbols: Vec<&'a str>,
+    account_types: [&'static str; 1],
+}
+
+impl<P: Borrow<Pair>, C: Has<ExchangeSymbols>> BuildBody<C> for CancelAllOrdersRequest<P> {
+    fn build_body(&self, context: &C) -> anyhow::Result<Option<String>> {
+        let exchange_symbols = context.give(ExchangeSymbols);
+        let symbols = self
+            .pairs
+            .iter()
+            .map(|pair| exchange_symbols.to_symbol(pair.borrow()))
+            .collect::<anyhow::Result<_>>()?;
+        let body = CancelAllBody {
+            symbols,
+            account_types: ["SPOT"],
+        };
+        serde_json::to_string(&body)
+            .map(Some)
+            .context("serialize symbols")
+    }
+}
+
+/// Cancellation is accepted, order is cancelled once its state is CANCELED
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelOrderResponse {
+    pub order_id: PxOrderId,
+    pub client_order_id: PxClientOrderId,
+    pub state: OrderState,
+    /// 200 if cancellation is accepted
+    pub code: i64,
+    pub message: String,
+}
+
+impl CancelOrderResponse {
+    pub const OK_CODE: i64 = 200;
+}
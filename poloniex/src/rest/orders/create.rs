@@ -0,0 +1,172 @@
+use std::borrow::Borrow;
+
+use anyhow::{Context as _, bail};
+use bitsgap_shared::{
+    BuildBody, Method, Request,
+    pair::{ExchangeSymbols, Pair},
+    utils::{
+        Has,
+        url::{BuildUrl, UrlBuilder},
+    },
+};
+
+use super::{OrderSide, OrderType, PlacedOrderResponse, StpMode, TimeInForce};
+use crate::units::{PxClientOrderId, PxOrderId, PxPrice, PxUnits};
+
+/// Places an order, reply only means that Poloniex accepted it
+pub struct CreateOrderRequest<P = Pair> {
+    /// our pair, converted to exchange symbol name
+    pub pair: P,
+    pub side: OrderSide,
+    pub order_type: OrderType,
+    /// GTC by default
+    pub time_in_force: Option<TimeInForce>,
+    /// required for limit orders
+    pub price: Option<PxPrice>,
+    /// base units, required for limit orders and market sell
+    pub quantity: Option<PxUnits>,
+    /// quote units, required for market buy
+    pub amount: Option<PxUnits>,
+    /// our own id, unique among open orders, up to 64 characters
+    pub client_order_id: Option<PxClientOrderId>,
+    /// EXPIRE_TAKER by default
+    pub stp_mode: Option<StpMode>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OrderBody<'a> {
+    symbol: &'a str,
+    side: OrderSide,
+    #[serde(rename = "type")]
+    order_type: OrderType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    time_in_force: Option<TimeInForce>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    price: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    quantity: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    amount: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_order_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stp_mode: Option<StpMode>,
+}
+
+impl<P: Borrow<Pair>> CreateOrderRequest<P> {
+    fn body<'a, C: Has<ExchangeSymbols>>(
+        &'a self,
+        context: &'a C,
+    ) -> anyhow::Result<OrderBody<'a>> {
+        let Self {
+            pair,
+            side,
+            order_type,
+            time_in_force,
+            price,
+            quantity,
+            amount,
+            client_order_id,
+            stp_mode,
+        } = self;
+        match order_type {
+            OrderType::Limit | OrderType::LimitMaker if price.is_none() || quantity.is_none() => {
+                bail!("limit order needs price and quantity")
+            }
+            OrderType::Market if price.is_some() => bail!("market order can't have price"),
+            _ => {}
+        }
+        Ok(OrderBody {
+            symbol: context.give(ExchangeSymbols).to_symbol(pair.borrow())?,
+            side: *side,
+            order_type: *order_type,
+            time_in_force: *time_in_force,
+            price: price.as_deref(),
+            quantity: quantity.as_deref(),
+            amount: amount.as_deref(),
+            client_order_id: client_order_id.as_deref(),
+            stp_mode: *stp_mode,
+        })
+    }
+}
+
+impl<P> Request for CreateOrderRequest<P> {
+    type Response = PlacedOrderResponse;
+    const ENDPOINT: &'static str = "orders";
+    const METHOD: Method = Method::POST;
+}
+
+impl<P, C> BuildUrl<C> for CreateOrderRequest<P> {
+    fn build_url(&self, url_builder: &mut UrlBuilder, _context: &C) -> anyhow::Result<()> {
+        url_builder.add_path_segments(&["orders"])
+    }
+}
+
+impl<P: Borrow<Pair>, C: Has<ExchangeSymbols>> BuildBody<C> for CreateOrderRequest<P> {
+    fn build_body(&self, context: &C) -> anyhow::Result<Option<String>> {
+        let body = self.body(context)?;
+        serde_json::to_string(&body)
+            .map(Some)
+            .context("serialize order")
+    }
+}
+
+/// Places up to `MAX_ORDERS` orders at once, each of them succeeds or fails on its own
+pub struct CreateOrdersRequest<P = Pair> {
+    pub orders: Vec<CreateOrderRequest<P>>,
+}
+
+impl<P> CreateOrdersRequest<P> {
+    pub const MAX_ORDERS: usize = 20;
+}
+
+impl<P> Request for CreateOrdersRequest<P> {
+    type Response = Vec<BatchOrderResponse>;
+    const ENDPOINT: &'static str = "orders/batch";
+    const METHOD: Method = Method::POST;
+}
+
+impl<P, C> BuildUrl<C> for CreateOrdersRequest<P> {
+    fn build_url(&self, url_builder: &mut UrlBuilder, _context: &C) -> anyhow::Result<()> {
+        url_builder.add_path_segments(&["orders", "batch"])
+    }
+}
+
+impl<P: Borrow<Pair>, C: Has<ExchangeSymbols>> BuildBody<C> for CreateOrdersRequest<P> {
+    fn build_body(&self, context: &C) -> anyhow::Result<Option<String>> {
+        if self.orders.len() > Self::MAX_ORDERS {
+            bail!("at most {} orders can be placed at once", Self::MAX_ORDERS);
+        }
+        let orders = self
+            .orders
+            .iter()
+            .map(|order| order.body(context))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        serde_json::to_string(&orders)
+            .map(Some)
+            .context("serialize orders")
+    }
+}
+
+/// Placed order, or why it wasn't placed. In the same order as requested.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchOrderResponse {
+    pub id: Option<PxOrderId>,
+    pub client_order_id: Option<PxClientOrderId>,
+    pub code: Option<i64>,
+    pub message: Option<String>,
+}
+
+impl BatchOrderResponse {
+    pub fn placed(&self) -> anyhow::Result<&PxOrderId> {
+        match (&self.id, self.code) {
+            (Some(id), None) => Ok(id),
+            (_, code) => bail!(
+                "order isn't placed, code: {code:?}, message: {}",
+                self.message.as_deref().unwrap_or_default()
+            ),
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use anyhow::{Context as _, bail};
+use bitsgap_shared::{
+    BuildBody, Method, Request,
+    utils::url::{BuildUrl, UrlBuilder},
+};
+
+use crate::units::PxTimestamp;
+
+/// Sets timer which cancels all orders when it expires, every request restarts it.
+/// Without `timeout` timer is disabled.
+pub struct KillSwitchRequest {
+    pub timeout: Option<Duration>,
+}
+
+impl KillSwitchRequest {
+    pub const MIN_TIMEOUT: Duration = Duration::from_secs(10);
+    pub const MAX_TIMEOUT: Duration = Duration::from_secs(600);
+}
+
+impl Request for KillSwitchRequest {
+    type Response = KillSwitchResponse;
+    const ENDPOINT: &'static str = "orders/killSwitch";
+    const METHOD: Method = Method::POST;
+}
+
+impl<C> BuildUrl<C> for KillSwitchRequest {
+    fn build_url(&self, url_builder: &mut UrlBuilder, _context: &C) -> anyhow::Result<()> {
+        url_builder.add_path_segments(&["orders", "killSwitch"])
+    }
+}
+
+#[derive(serde::Serialize)]
+struct KillSwitchBody {
+    /// seconds, or -1 to disable
+    timeout: String,
+}
+
+impl<C> BuildBody<C> for KillSwitchRequest {
+    fn build_body(&self, _context: &C) -> anyhow::Result<Option<String>> {
+        let timeout = match self.timeout {
+            Some(timeout) if (Self::MIN_TIMEOUT..=Self::MAX_TIMEOUT).contains(&timeout) => {
+                timeout.as_secs().to_string()
+            }
+            Some(timeout) => bail!(
+                "kill switch timeout {timeout:?} is out of {:?}..={:?}",
+                Self::MIN_TIMEOUT,
+                Self::MAX_TIMEOUT
+            ),
+            None => "-1".into(),
+        };
+        serde_json::to_string(&KillSwitchBody { timeout })
+            .map(Some)
+            .context("serialize kill switch timeout")
+    }
+}
+
+/// Current timer, if it's set
+pub struct KillSwitchStatusRequest;
+
+impl Request for KillSwitchStatusRequest {
+    type Response = KillSwitchResponse;
+    const ENDPOINT: &'static str = "orders/killSwitchStatus";
+}
+
+impl<C> BuildUrl<C> for KillSwitchStatusRequest {
+    fn build_url(&self, url_builder: &mut UrlBuilder, _context: &C) -> anyhow::Result<()> {
+        url_builder.add_path_segments(&["orders", "killSwitchStatus"])
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KillSwitchResponse {
+    /// time timer was (re)started
+    pub start_time: PxTimestamp,
+    /// time orders will be cancelled unless timer is restarted
+    pub cancellation_time: PxTimestamp,
+}
@@ -0,0 +1,296 @@
+//! Spot order management, every request needs HMAC authentication
+
+use std::borrow::Cow;
+
+use bitsgap_shared::{
+    pair::{ExchangeSymbols, Pair},
+    utils::Has,
+};
+
+use crate::units::{PxClientOrderId, PxOrderId, PxPrice, PxSymbol, PxTimestamp, PxUnits};
+
+pub mod cancel;
+pub mod create;
+pub mod kill_switch;
+pub mod query;
+pub mod replace;
+
+/// Order is referred to by id assigned by Poloniex or by our own client order id
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OrderRef {
+    Id(PxOrderId),
+    ClientId(PxClientOrderId),
+}
+
+impl OrderRef {
+    fn path_segment(&self) -> Cow<'_, str> {
+        match self {
+            Self::Id(id) => id.into(),
+            Self::ClientId(client_id) => format!("cid:{client_id}").into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OrderSide {
+    Buy,
+    Sell,
+}
+
+impl OrderSide {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Buy => "BUY",
+            Self::Sell => "SELL",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OrderType {
+    /// `amount` of quote to buy or `quantity` of base to sell at any price
+    Market,
+    Limit,
+    /// limit order which is rejected instead of taking liquidity
+    LimitMaker,
+}
+
+impl OrderType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Market => "MARKET",
+            Self::Limit => "LIMIT",
+            Self::LimitMaker => "LIMIT_MAKER",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TimeInForce {
+    /// good till cancelled
+    Gtc,
+    /// immediate or cancel
+    Ioc,
+    /// fill or kill
+    Fok,
+}
+
+/// Self-trade prevention: what happens when our order would match our own one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum StpMode {
+    None,
+    ExpireTaker,
+    ExpireMaker,
+    ExpireBoth,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OrderState {
+    New,
+    PartiallyFilled,
+    Filled,
+    PendingCancel,
+    PartiallyCanceled,
+    Canceled,
+    Failed,
+}
+
+impl OrderState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::New => "NEW",
+            Self::PartiallyFilled => "PARTIALLY_FILLED",
+            Self::Filled => "FILLED",
+            Self::PendingCancel => "PENDING_CANCEL",
+            Self::PartiallyCanceled => "PARTIALLY_CANCELED",
+            Self::Canceled => "CANCELED",
+            Self::Failed => "FAILED",
+        }
+    }
+
+    /// Order won't change anymore
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            Self::Filled | Self::PartiallyCanceled | Self::Canceled | Self::Failed
+        )
+    }
+}
+
+/// Order as Poloniex reports it, both open and historical
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderResponse {
+    pub id: PxOrderId,
+    /// empty if order was placed without one
+    pub client_order_id: PxClientOrderId,
+    pub symbol: PxSymbol,
+    pub state: OrderState,
+    pub side: OrderSide,
+    #[serde(rename = "type")]
+    pub order_type: OrderType,
+    pub time_in_force: TimeInForce,
+    /// base units ordered, "0" for market buy
+    pub quantity: PxUnits,
+    /// "0" for market orders
+    pub price: PxPrice,
+    /// average price of fills
+    pub avg_price: PxPrice,
+    /// quote units ordered, "0" for limit orders
+    pub amount: PxUnits,
+    /// base units filled
+    pub filled_quantity: PxUnits,
+    /// quote units filled
+    pub filled_amount: PxUnits,
+    pub create_time: PxTimestamp,
+    pub update_time: PxTimestamp,
+}
+
+impl OrderResponse {
+    pub fn pair<'c, C: Has<ExchangeSymbols>>(&self, context: &'c C) -> anyhow::Result<&'c Pair> {
+        context.give(ExchangeSymbols).to_pair(&self.symbol)
+    }
+}
+
+/// Reply to order placement or replacement
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlacedOrderResponse {
+    pub id: PxOrderId,
+    pub client_order_id: PxClientOrderId,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bitsgap_shared::{BuildBody, pair::Pair, utils::time::UnixMillis};
+
+    use super::{
+        cancel::{CancelAllOrdersRequest, CancelOrderRequest, CancelOrdersRequest},
+        create::CreateOrderRequest,
+        kill_switch::KillSwitchRequest,
+        query::{Direction, OrderHistoryRequest},
+        replace::ReplaceOrderRequest,
+        *,
+    };
+    use crate::{context::PoloniexContext, tests::stand_in_requester};
+
+    fn limit_buy() -> CreateOrderRequest {
+        CreateOrderRequest {
+            pair: Pair::new("BTC", "USDT"),
+            side: OrderSide::Buy,
+            order_type: OrderType::Limit,
+            time_in_force: Some(TimeInForce::Gtc),
+            price: Some("60000.5".into()),
+            quantity: Some("0.001".into()),
+            amount: None,
+            client_order_id: Some("grid-1".into()),
+            stp_mode: Some(StpMode::ExpireTaker),
+        }
+    }
+
+    #[test]
+    fn test_poloniex_orders_bodies() {
+        let context = PoloniexContext::init(false).unwrap();
+        assert_eq!(
+            limit_buy().build_body(&context).unwrap().unwrap(),
+            r#"{"symbol":"BTC_USDT","side":"BUY","type":"LIMIT","timeInForce":"GTC","price":"60000.5","quantity":"0.001","clientOrderId":"grid-1","stpMode":"EXPIRE_TAKER"}"#
+        );
+        let replace = ReplaceOrderRequest {
+            client_order_id: Some("grid-2".into()),
+            price: Some("60001".into()),
+            ..ReplaceOrderRequest::new(OrderRef::ClientId("grid-1".into()))
+        };
+        assert_eq!(
+            replace.build_body(&context).unwrap().unwrap(),
+            r#"{"clientOrderId":"grid-2","price":"60001"}"#
+        );
+        let cancel = CancelOrdersRequest {
+            order_ids: vec!["21934611974062080".into()],
+            client_order_ids: vec![],
+        };
+        assert_eq!(
+            cancel.build_body(&context).unwrap().unwrap(),
+            r#"{"orderIds":["21934611974062080"],"clientOrderIds":[]}"#
+        );
+        let cancel_all = CancelAllOrdersRequest {
+            pairs: vec![Pair::new("ETH", "USDT")],
+        };
+        assert_eq!(
+            cancel_all.build_body(&context).unwrap().unwrap(),
+            r#"{"symbols":["ETH_USDT"],"accountTypes":["SPOT"]}"#
+        );
+
+        let kill_switch = |timeout| KillSwitchRequest { timeout }.build_body(&context);
+        assert_eq!(
+            kill_switch(Some(Duration::from_secs(60))).unwrap().unwrap(),
+            r#"{"timeout":"60"}"#
+        );
+        assert_eq!(kill_switch(None).unwrap().unwrap(), r#"{"timeout":"-1"}"#);
+        assert!(kill_switch(Some(Duration::from_secs(5))).is_err());
+    }
+
+    #[test]
+    fn test_poloniex_orders_urls() {
+        let requester = stand_in_requester("https://api.poloniex.com");
+        let url = requester
+            .build_url(&CancelOrderRequest {
+                order: OrderRef::ClientId("grid-1".into()),
+            })
+            .unwrap();
+        assert_eq!(url.as_str(), "https://api.poloniex.com/orders/cid:grid-1");
+
+        let url = requester
+            .build_url(&OrderHistoryRequest {
+                pair: Some(Pair::new("BTC", "USDT")),
+                states: vec![OrderState::Filled, OrderState::Canceled],
+                direction: Some(Direction::Next),
+                limit: Some(50),
+                start_time: Some(UnixMillis(1738700743000)),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://api.poloniex.com/orders/history?symbol=BTC_USDT&states=FILLED%2CCANCELED&direction=NEXT&limit=50&startTime=1738700743000"
+        );
+    }
+
+    #[test]
+    fn test_poloniex_order_response() {
+        let context = PoloniexContext::init(false).unwrap();
+        let order: OrderResponse = serde_json::from_str(
+            r#"{
+                "id": "24993088082542592",
+                "clientOrderId": "grid-1",
+                "symbol": "BTC_USDT",
+                "state": "PARTIALLY_FILLED",
+                "accountType": "SPOT",
+                "side": "BUY",
+                "type": "LIMIT",
+                "timeInForce": "GTC",
+                "quantity": "0.001",
+                "price": "60000.5",
+                "avgPrice": "60000.5",
+                "amount": "0",
+                "filledQuantity": "0.0004",
+                "filledAmount": "24.0002",
+                "createTime": 1738700743000,
+                "updateTime": 1738700750000,
+                "orderSource": "API",
+                "loan": false
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(order.state, OrderState::PartiallyFilled);
+        assert!(!order.state.is_final());
+        assert_eq!(order.order_type, OrderType::Limit);
+        assert_eq!(order.pair(&context).unwrap(), &Pair::new("BTC", "USDT"));
+    }
+}
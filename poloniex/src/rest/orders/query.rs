@@ -0,0 +1,190 @@
+use std::borrow::Borrow;
+
+use bitsgap_shared::{
+    Request,
+    pair::{ExchangeSymbols, Pair},
+    utils::{
+        Has,
+        url::{BuildUrl, UrlBuilder},
+    },
+};
+
+use super::{OrderRef, OrderResponse, OrderSide, OrderState, OrderType};
+use crate::units::{PxOrderId, PxTimestamp};
+
+/// Direction of paging from `from` order id
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// older orders
+    Pre,
+    /// newer orders
+    Next,
+}
+
+impl Direction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pre => "PRE",
+            Self::Next => "NEXT",
+        }
+    }
+}
+
+/// Open orders, newest first
+pub struct OpenOrdersRequest<P = Pair> {
+    /// orders of all pairs if not set
+    pub pair: Option<P>,
+    pub side: Option<OrderSide>,
+    /// order id to page from
+    pub from: Option<PxOrderId>,
+    pub direction: Option<Direction>,
+    /// maximum number of records returned. The default value is 500 and the max value is 2000
+    pub limit: Option<u16>,
+}
+
+impl<P> OpenOrdersRequest<P> {
+    pub const MAX_LIMIT: u16 = 2000;
+}
+
+impl<P> Default for OpenOrdersRequest<P> {
+    fn default() -> Self {
+        Self {
+            pair: None,
+            side: None,
+            from: None,
+            direction: None,
+            limit: None,
+        }
+    }
+}
+
+impl<P> Request for OpenOrdersRequest<P> {
+    type Response = Vec<OrderResponse>;
+    const ENDPOINT: &'static str = "orders";
+}
+
+impl<P: Borrow<Pair>, C: Has<ExchangeSymbols>> BuildUrl<C> for OpenOrdersRequest<P> {
+    fn build_url(&self, url_builder: &mut UrlBuilder, context: &C) -> anyhow::Result<()> {
+        url_builder.add_path_segments(&["orders"])?;
+        let mut query_builder = url_builder.query_builder()?;
+        if let Some(pair) = &self.pair {
+            let symbol = context.give(ExchangeSymbols).to_symbol(pair.borrow())?;
+            query_builder.add_pair("symbol", symbol);
+        }
+        if let Some(side) = self.side {
+            query_builder.add_pair("side", side.as_str());
+        }
+        if let Some(from) = &self.from {
+            query_builder.add_pair("from", from);
+        }
+        if let Some(direction) = self.direction {
+            query_builder.add_pair("direction", direction.as_str());
+        }
+        if let Some(limit) = self.limit {
+            query_builder.display_pair("limit", &limit)?;
+        }
+        Ok(())
+    }
+}
+
+/// Single order, open or not
+pub struct OrderRequest {
+    pub order: OrderRef,
+}
+
+impl Request for OrderRequest {
+    type Response = OrderResponse;
+    const ENDPOINT: &'static str = "orders/{id}";
+}
+
+impl<C> BuildUrl<C> for OrderRequest {
+    fn build_url(&self, url_builder: &mut UrlBuilder, _context: &C) -> anyhow::Result<()> {
+        url_builder.add_path_segments(&["orders", &self.order.path_segment()])
+    }
+}
+
+/// Orders which are not open anymore, newest first.
+/// Poloniex keeps history of the last 3 months, 7 days at most can be queried at once.
+pub struct OrderHistoryRequest<P = Pair> {
+    /// orders of all pairs if not set
+    pub pair: Option<P>,
+    pub side: Option<OrderSide>,
+    pub order_type: Option<OrderType>,
+    /// all final states if empty
+    pub states: Vec<OrderState>,
+    /// order id to page from
+    pub from: Option<PxOrderId>,
+    pub direction: Option<Direction>,
+    /// maximum number of records returned. The default value is 100 and the max value is 1000
+    pub limit: Option<u16>,
+    /// skip cancelled orders without fills
+    pub hide_cancel: Option<bool>,
+    pub start_time: Option<PxTimestamp>,
+    pub end_time: Option<PxTimestamp>,
+}
+
+impl<P> OrderHistoryRequest<P> {
+    pub const MAX_LIMIT: u16 = 1000;
+}
+
+impl<P> Default for OrderHistoryRequest<P> {
+    fn default() -> Self {
+        Self {
+            pair: None,
+            side: None,
+            order_type: None,
+            states: vec![],
+            from: None,
+            direction: None,
+            limit: None,
+            hide_cancel: None,
+            start_time: None,
+            end_time: None,
+        }
+    }
+}
+
+impl<P> Request for OrderHistoryRequest<P> {
+    type Response = Vec<OrderResponse>;
+    const ENDPOINT: &'static str = "orders/history";
+}
+
+impl<P: Borrow<Pair>, C: Has<ExchangeSymbols>> BuildUrl<C> for OrderHistoryRequest<P> {
+    fn build_url(&self, url_builder: &mut UrlBuilder, context: &C) -> anyhow::Result<()> {
+        url_builder.add_path_segments(&["orders", "history"])?;
+        let mut query_builder = url_builder.query_builder()?;
+        if let Some(pair) = &self.pair {
+            let symbol = context.give(ExchangeSymbols).to_symbol(pair.borrow())?;
+            query_builder.add_pair("symbol", symbol);
+        }
+        if let Some(side) = self.side {
+            query_builder.add_pair("side", side.as_str());
+        }
+        if let Some(order_type) = self.order_type {
+            query_builder.add_pair("type", order_type.as_str());
+        }
+        if !self.states.is_empty() {
+            let states: Vec<_> = self.states.iter().map(OrderState::as_str).collect();
+            query_builder.add_pair("states", states.join(","));
+        }
+        if let Some(from) = &self.from {
+            query_builder.add_pair("from", from);
+        }
+        if let Some(direction) = self.direction {
+            query_builder.add_pair("direction", direction.as_str());
+        }
+        if let Some(limit) = self.limit {
+            query_builder.display_pair("limit", &limit)?;
+        }
+        if let Some(hide_cancel) = self.hide_cancel {
+            query_builder.display_pair("hideCancel", &hide_cancel)?;
+        }
+        if let Some(start_time) = self.start_time {
+            query_builder.display_pair("startTime", &start_time.as_i64())?;
+        }
+        if let Some(end_time) = self.end_time {
+            query_builder.display_pair("endTime", &end_time.as_i64())?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,87 @@
+use anyhow::Context as _;
+use bitsgap_shared::{
+    BuildBody, Method, Request,
+    utils::url::{BuildUrl, UrlBuilder},
+};
+
+use super::{OrderRef, OrderType, PlacedOrderResponse, TimeInForce};
+use crate::units::{PxClientOrderId, PxPrice, PxUnits};
+
+/// Cancels order and places a new one of the same symbol and side, unset fields are kept
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplaceOrderRequest {
+    pub order: OrderRef,
+    /// client order id of the new order
+    pub client_order_id: Option<PxClientOrderId>,
+    pub price: Option<PxPrice>,
+    pub quantity: Option<PxUnits>,
+    pub amount: Option<PxUnits>,
+    pub order_type: Option<OrderType>,
+    pub time_in_force: Option<TimeInForce>,
+    /// place the new order even if the old one couldn't be cancelled
+    pub proceed_on_failure: Option<bool>,
+}
+
+impl ReplaceOrderRequest {
+    /// Replacement which keeps everything, fields to change are set afterwards
+    pub fn new(order: OrderRef) -> Self {
+        Self {
+            order,
+            client_order_id: None,
+            price: None,
+            quantity: None,
+            amount: None,
+            order_type: None,
+            time_in_force: None,
+            proceed_on_failure: None,
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReplaceBody<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_order_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    price: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    quantity: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    amount: Option<&'a str>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    order_type: Option<OrderType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    time_in_force: Option<TimeInForce>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    proceed_on_failure: Option<bool>,
+}
+
+impl Request for ReplaceOrderRequest {
+    type Response = PlacedOrderResponse;
+    const ENDPOINT: &'static str = "orders/{id}";
+    const METHOD: Method = Method::PUT;
+}
+
+impl<C> BuildUrl<C> for ReplaceOrderRequest {
+    fn build_url(&self, url_builder: &mut UrlBuilder, _context: &C) -> anyhow::Result<()> {
+        url_builder.add_path_segments(&["orders", &self.order.path_segment()])
+    }
+}
+
+impl<C> BuildBody<C> for ReplaceOrderRequest {
+    fn build_body(&self, _context: &C) -> anyhow::Result<Option<String>> {
+        let body = ReplaceBody {
+            client_order_id: self.client_order_id.as_deref(),
+            price: self.price.as_deref(),
+            quantity: self.quantity.as_deref(),
+            amount: self.amount.as_deref(),
+            order_type: self.order_type,
+            time_in_force: self.time_in_force,
+            proceed_on_failure: self.proceed_on_failure,
+        };
+        serde_json::to_string(&body)
+            .map(Some)
+            .context("serialize order replacement")
+    }
+}
@@ -0,0 +1,97 @@
+use std::borrow::Borrow;
+
+use anyhow::Context as _;
+use bitsgap_shared::{
+    Request,
+    pair::{ExchangeSymbols, Pair},
+    records::recent_trade::RecentTrade,
+    utils::{
+        Has,
+        url::{BuildUrl, UrlBuilder},
+    },
+};
+use let_clone::let_clone;
+
+use crate::{
+    units::{PxPrice, PxTimestamp, PxUnits},
+    ws::trades::TakerSide,
+};
+
+/// Public market trades, newest first.
+/// Poloniex exposes only the latest trades of a market here, there is no time or id cursor to page further back.
+pub struct TradesRequest<P = Pair> {
+    /// our pair, converted to exchange symbol name
+    pub pair: P,
+    /// maximum number of records returned. The default value is 500 and the max value is 1000
+    pub limit: Option<u16>,
+}
+
+impl<P> TradesRequest<P> {
+    pub const MAX_LIMIT: u16 = 1000;
+}
+
+impl<P> Request for TradesRequest<P> {
+    type Response = Vec<TradesResponse>;
+    const ENDPOINT: &'static str = "markets/{symbol}/trades";
+}
+
+impl<P: Borrow<Pair>, C: Has<ExchangeSymbols>> BuildUrl<C> for TradesRequest<P> {
+    fn build_url(&self, url_builder: &mut UrlBuilder, context: &C) -> anyhow::Result<()> {
+        let symbol = context
+            .give(ExchangeSymbols)
+            .to_symbol(self.pair.borrow())?;
+        url_builder.add_path_segments(&["markets", symbol, "trades"])?;
+        if let Some(limit) = self.limit {
+            url_builder.query_builder()?.display_pair("limit", &limit)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TradesResponse {
+    /// trade id
+    pub id: String,
+    /// trade price
+    pub price: PxPrice,
+    /// base units traded
+    pub quantity: PxUnits,
+    /// quote units traded
+    pub amount: PxUnits,
+    /// taker's trade side (buy, sell)
+    pub taker_side: TakerSide,
+    /// time the trade was created
+    pub create_time: PxTimestamp,
+    /// time the record was pushed
+    #[serde(rename = "ts")]
+    pub record_time: PxTimestamp,
+}
+
+impl TradesResponse {
+    // REST response doesn't carry symbol, so we take pair from request
+    pub fn recent_trade<P: Borrow<Pair>>(
+        &self,
+        request: &TradesRequest<P>,
+    ) -> anyhow::Result<RecentTrade> {
+        let Self {
+            id,
+            price,
+            quantity,
+            taker_side,
+            create_time,
+            ..
+        } = self;
+        let_clone!(price, quantity: amount, id: tid);
+        Ok(RecentTrade {
+            schema: RecentTrade::SCHEMA_VERSION,
+            tid,
+            exchange: crate::EXCHANGE.into(),
+            pair: request.pair.borrow().clone(),
+            price,
+            amount,
+            side: taker_side.as_str().into(),
+            timestamp: create_time.to_nanos().context("convert create time")?,
+        })
+    }
+}
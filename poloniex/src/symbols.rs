@@ -0,0 +1,76 @@
+use anyhow::Context as _;
+use bitsgap_shared::{
+    ApiRequester,
+    pair::{Pair, SymbolsDict},
+};
+
+use crate::{
+    context::PoloniexContext,
+    rest::markets::{MarketResponse, MarketsRequest},
+};
+
+pub const SYMBOL_SEPARATOR: char = '_';
+
+/// Symbols of test task, until they're loaded from `/markets` by `Poloniex::load_symbols`
+pub fn exchange_symbols() -> anyhow::Result<SymbolsDict> {
+    SymbolsDict::default()
+        .with_separated(SYMBOL_SEPARATOR, crate::TEST_TASK_SYMBOLS.iter().copied())
+}
+
+/// Every market from `/markets`, it's public, so requester needs no keys
+pub async fn load_symbols(
+    requester: &ApiRequester<PoloniexContext>,
+) -> anyhow::Result<SymbolsDict> {
+    let responses = requester
+        .get_response(&MarketsRequest::<Pair> { pair: None })
+        .await
+        .context("get markets")?;
+    log::info!("Loaded {} markets of poloniex", responses.len());
+    markets_symbols(&responses).context("symbols of markets")
+}
+
+/// Every market of Poloniex, whatever its state is
+pub fn markets_symbols(markets: &[MarketResponse]) -> anyhow::Result<SymbolsDict> {
+    let mut symbols = SymbolsDict::default();
+    for market in markets {
+        symbols
+            .add(
+                market.symbol.clone(),
+                &market.base_currency_name,
+                &market.quote_currency_name,
+            )
+            .with_context(|| format!("add symbol of market {}", market.symbol))?;
+    }
+    Ok(symbols)
+}
+
+#[cfg(test)]
+mod tests {
+    use bitsgap_shared::pair::Pair;
+
+    use super::*;
+
+    #[test]
+    fn test_symbols_round_trip() {
+        let symbols = exchange_symbols().unwrap();
+        let pair = symbols.to_pair("BTC_USDT").unwrap();
+        assert_eq!(pair, &Pair::new("BTC", "USDT"));
+        assert_eq!(pair.to_string(), "BTC/USDT");
+        assert_eq!(symbols.to_symbol(pair).unwrap(), "BTC_USDT");
+        assert!(symbols.to_pair("BTCUSDT").is_err());
+    }
+
+    #[test]
+    fn test_renamed_and_delisted_assets() {
+        let symbols = SymbolsDict::default()
+            .with_renamed_assets([("BCHSV", "BSV")])
+            .unwrap()
+            .with_separated(SYMBOL_SEPARATOR, ["BSV_USDT", "BCHSV_USDT"])
+            .unwrap()
+            .with_delisted([Pair::new("BSV", "USDT")]);
+        let pair = symbols.to_pair("BCHSV_USDT").unwrap();
+        assert_eq!(pair, symbols.to_pair("BSV_USDT").unwrap());
+        assert!(symbols.to_symbol(pair).is_err());
+        assert_eq!(symbols.listed().count(), 0);
+    }
+}
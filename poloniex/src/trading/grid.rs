@@ -0,0 +1,589 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Context as _, bail, ensure};
+use bitsgap_shared::{ApiRequester, pair::Pair, utils::time::UnixMillis};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use super::{
+    risk::{MarketRules, RiskGuard},
+    tracker::{OrderChange, OrderStatus, OrderTracker, TrackedOrder},
+};
+use crate::{
+    context::PoloniexContext,
+    rest::{
+        markets::MarketPriceRequest,
+        orders::{
+            OrderSide, OrderType, TimeInForce,
+            cancel::{CancelOrderResponse, CancelOrdersRequest},
+            create::CreateOrderRequest,
+        },
+    },
+    units::{PxOrderId, PxPrice, PxUnits},
+};
+
+/// What grid trades
+#[derive(Debug, Clone)]
+pub struct GridConfig {
+    /// prefix of client order ids, tells orders of different bots apart
+    pub name: String,
+    pub pair: Pair,
+    pub lower_price: f64,
+    pub upper_price: f64,
+    /// prices between lower and upper ones inclusive, evenly spaced
+    pub levels: usize,
+    /// quote units spent if every buy order is filled
+    pub investment: f64,
+}
+
+/// Level prices and quantity of every order, with market precision
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grid {
+    /// ascending
+    pub prices: Vec<PxPrice>,
+    pub quantity: PxUnits,
+}
+
+impl Grid {
+    pub fn new(config: &GridConfig, rules: &MarketRules) -> anyhow::Result<Self> {
+        let GridConfig {
+            lower_price,
+            upper_price,
+            levels,
+            investment,
+            ..
+        } = *config;
+        ensure!(levels >= 2, "grid needs at least 2 levels");
+        ensure!(
+            0.0 < lower_price && lower_price < upper_price,
+            "grid range {lower_price}..{upper_price} is invalid"
+        );
+        let step = (upper_price - lower_price) / (levels - 1) as f64;
+        let scale = rules.price_scale as usize;
+        let prices: Vec<PxPrice> = (0..levels)
+            .map(|level| format!("{:.scale$}", lower_price + step * level as f64))
+            .collect();
+        if prices.windows(2).any(|pair| pair[0] == pair[1]) {
+            bail!("grid levels are closer than price precision");
+        }
+        // every level but the top one can hold a buy order
+        let buy_levels: f64 = prices[..levels - 1]
+            .iter()
+            .map(|price| price.parse::<f64>())
+            .sum::<Result<_, _>>()
+            .context("parse level price")?;
+        let factor = 10f64.powi(rules.quantity_scale as i32);
+        // rounded down, so investment isn't exceeded
+        let quantity = (investment / buy_levels * factor).floor() / factor;
+        ensure!(
+            quantity > 0.0,
+            "investment {investment} is too small for grid"
+        );
+        Ok(Self {
+            prices,
+            quantity: format!("{quantity:.0$}", rules.quantity_scale as usize),
+        })
+    }
+}
+
+// order of grid at some level
+#[derive(Debug, Clone)]
+struct Slot {
+    level: usize,
+    side: OrderSide,
+    /// price base units of sell order were bought at
+    basis: f64,
+}
+
+/// Grid bot: buys below and sells above the current price, every filled order is replaced
+/// with the opposite one at the neighbouring level. Sell orders need base units on the account.
+pub struct GridBot<'a> {
+    config: GridConfig,
+    grid: Grid,
+    requester: &'a ApiRequester<PoloniexContext>,
+    guard: &'a RiskGuard,
+    tracker: &'a OrderTracker,
+    changes: broadcast::Receiver<OrderChange>,
+    slots: BTreeMap<PxOrderId, Slot>,
+    /// quote units, fees aren't subtracted
+    realized_profit: f64,
+    /// starts at start time in milliseconds, so ids aren't reused after restart
+    next_client_id: i64,
+}
+
+impl<'a> GridBot<'a> {
+    /// Places the initial ladder around the latest trade price.
+    /// If an order can't be placed, already placed ones are cancelled.
+    pub async fn start(
+        config: GridConfig,
+        requester: &'a ApiRequester<PoloniexContext>,
+        guard: &'a RiskGuard,
+        tracker: &'a OrderTracker,
+    ) -> anyhow::Result<Self> {
+        let rules = guard
+            .rules(&config.pair)
+            .with_context(|| format!("no trading rules of {}", config.pair))?;
+        let grid = Grid::new(&config, rules)?;
+        let price = requester
+            .get_response(&MarketPriceRequest { pair: &config.pair })
+            .await
+            .context("get latest trade price")?;
+        let start_price: f64 = price.price.parse().context("parse latest trade price")?;
+        let mut bot = Self {
+            config,
+            grid,
+            requester,
+            guard,
+            tracker,
+            // before placement, so no change is missed
+            changes: tracker.subscribe(),
+            slots: BTreeMap::new(),
+            realized_profit: 0.0,
+            next_client_id: UnixMillis::now().as_i64(),
+        };
+        if let Err(err) = bot.place_ladder(start_price).await {
+            if let Err(stop_err) = bot.stop().await {
+                log::error!(
+                    "Grid {} orders placed before failure: {stop_err:#}",
+                    bot.config.name
+                );
+            }
+            return Err(err);
+        }
+        log::info!(
+            "Grid {} started at {start_price} with {} orders",
+            bot.config.name,
+            bot.slots.len()
+        );
+        Ok(bot)
+    }
+
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    /// Profit of filled sell orders, in quote units, before fees
+    pub fn realized_profit(&self) -> f64 {
+        self.realized_profit
+    }
+
+    /// Open orders of grid by level
+    pub fn orders(&self) -> BTreeMap<usize, (PxOrderId, OrderSide)> {
+        self.slots
+            .iter()
+            .map(|(id, slot)| (slot.level, (id.clone(), slot.side)))
+            .collect()
+    }
+
+    async fn place_ladder(&mut self, start_price: f64) -> anyhow::Result<()> {
+        // level nearest to the current price stays empty
+        let nearest = (0..self.grid.prices.len())
+            .min_by(|a, b| {
+                let distance = |level| (self.level_price(level) - start_price).abs();
+                distance(*a).total_cmp(&distance(*b))
+            })
+            .expect("grid has levels");
+        for level in 0..self.grid.prices.len() {
+            match level.cmp(&nearest) {
+                std::cmp::Ordering::Less => self.place(level, OrderSide::Buy, 0.0).await?,
+                std::cmp::Ordering::Equal => {}
+                // base units are valued at the price they're held at
+                std::cmp::Ordering::Greater => {
+                    self.place(level, OrderSide::Sell, start_price).await?
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn level_price(&self, level: usize) -> f64 {
+        // prices are formatted from floats
+        self.grid.prices[level].parse().unwrap_or_default()
+    }
+
+    async fn place(&mut self, level: usize, side: OrderSide, basis: f64) -> anyhow::Result<()> {
+        self.next_client_id += 1;
+        let order = CreateOrderRequest {
+            pair: &self.config.pair,
+            side,
+            order_type: OrderType::Limit,
+            time_in_force: Some(TimeInForce::Gtc),
+            price: Some(self.grid.prices[level].clone()),
+            quantity: Some(self.grid.quantity.clone()),
+            amount: None,
+            client_order_id: Some(format!("{}-{}", self.config.name, self.next_client_id)),
+            stp_mode: None,
+        };
+        let placed = self
+            .guard
+            .place(self.requester, self.tracker, &order)
+            .await
+            .with_context(|| format!("place {side:?} order at level {level}"))?;
+        self.slots.insert(placed.id, Slot { level, side, basis });
+        Ok(())
+    }
+
+    /// Replaces filled order with the opposite one, forgets cancelled or rejected one
+    pub async fn handle(&mut self, order: &TrackedOrder) -> anyhow::Result<()> {
+        if !order.status.is_final() {
+            return Ok(());
+        }
+        let Some(slot) = self.slots.remove(&order.id) else {
+            return Ok(());
+        };
+        if order.status != OrderStatus::Filled {
+            log::warn!(
+                "Grid {} order {} at level {} is {:?}, level stays empty",
+                self.config.name,
+                order.id,
+                slot.level,
+                order.status
+            );
+            return Ok(());
+        }
+        match slot.side {
+            OrderSide::Buy => {
+                let price = self.level_price(slot.level);
+                let level = slot.level + 1;
+                self.place(level, OrderSide::Sell, price).await
+            }
+            OrderSide::Sell => {
+                let quantity: f64 = order
+                    .filled_quantity
+                    .parse()
+                    .context("parse filled quantity")?;
+                let amount: f64 = order.filled_amount.parse().context("parse filled amount")?;
+                self.realized_profit += amount - quantity * slot.basis;
+                log::info!(
+                    "Grid {} sold at level {}, realized profit {}",
+                    self.config.name,
+                    slot.level,
+                    self.realized_profit
+                );
+                let level = slot.level - 1;
+                self.place(level, OrderSide::Buy, 0.0).await
+            }
+        }
+    }
+
+    // orders whose changes were missed are taken from tracker
+    async fn catch_up(&mut self) -> anyhow::Result<()> {
+        let ids: Vec<_> = self.slots.keys().cloned().collect();
+        for id in ids {
+            if let Some(order) = self.tracker.order(&id) {
+                self.handle(&order).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Waits for the next change of orders and handles it
+    pub async fn step(&mut self) -> anyhow::Result<()> {
+        match self.changes.recv().await {
+            Ok(change) => self.handle(&change.order).await,
+            Err(RecvError::Lagged(missed)) => {
+                log::warn!("Grid {} missed {missed} order changes", self.config.name);
+                self.catch_up().await
+            }
+            Err(RecvError::Closed) => bail!("order tracker is gone"),
+        }
+    }
+
+    /// Keeps grid running until an order can't be placed
+    pub async fn run(&mut self) -> anyhow::Result<()> {
+        loop {
+            self.step().await?;
+        }
+    }
+
+    /// Cancels every open order of grid
+    pub async fn stop(&mut self) -> anyhow::Result<()> {
+        if self.slots.is_empty() {
+            return Ok(());
+        }
+        let order_ids: Vec<_> = self.slots.keys().cloned().collect();
+        let responses = self
+            .requester
+            .send(&CancelOrdersRequest {
+                order_ids,
+                client_order_ids: vec![],
+            })
+            .await
+            .context("cancel grid orders")?;
+        for response in responses {
+            if response.code != CancelOrderResponse::OK_CODE {
+                log::error!(
+                    "Grid {} order {} isn't cancelled: {}",
+                    self.config.name,
+                    response.order_id,
+                    response.message
+                );
+            }
+        }
+        self.slots.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::{
+        Json, Router,
+        extract::State,
+        http::StatusCode,
+        routing::{delete, get, post},
+    };
+    use serde_json::{Value, json};
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::{
+        rest::orders::OrderResponse,
+        tests::stand_in_requester,
+        trading::{risk::PairLimits, tracker::OrderUpdate},
+    };
+
+    #[derive(Default)]
+    struct Exchange {
+        placed: Vec<Value>,
+        cancelled: Vec<Value>,
+        /// orders after this many placed ones are rejected
+        capacity: Option<usize>,
+    }
+
+    type SharedExchange = Arc<Mutex<Exchange>>;
+
+    /// Accepts orders and cancellations like Poloniex order endpoints would, latest price is 100
+    async fn stand_in_server() -> (String, SharedExchange) {
+        let exchange = SharedExchange::default();
+        let rest = Router::new()
+            .route(
+                "/markets/{symbol}/price",
+                get(|| async {
+                    Json(json!({"symbol": "BTC_USDT", "price": "100", "time": 1738700743000i64, "ts": 1738700743001i64}))
+                }),
+            )
+            .route(
+                "/orders",
+                post(
+                    |State(exchange): State<SharedExchange>, Json(order): Json<Value>| async move {
+                        let mut exchange = exchange.lock().unwrap();
+                        if exchange.capacity == Some(exchange.placed.len()) {
+                            return Err(StatusCode::BAD_REQUEST);
+                        }
+                        let id = (exchange.placed.len() + 1).to_string();
+                        let client_order_id = order["clientOrderId"].clone();
+                        exchange.placed.push(order);
+                        Ok(Json(json!({"id": id, "clientOrderId": client_order_id})))
+                    },
+                ),
+            )
+            .route(
+                "/orders/cancelByIds",
+                delete(
+                    |State(exchange): State<SharedExchange>, Json(body): Json<Value>| async move {
+                        let ids = body["orderIds"].as_array().unwrap().clone();
+                        exchange.lock().unwrap().cancelled.extend(ids.iter().cloned());
+                        let responses: Vec<_> = ids
+                            .iter()
+                            .map(|id| json!({"orderId": id, "clientOrderId": "", "state": "PENDING_CANCEL", "code": 200, "message": ""}))
+                            .collect();
+                        Json(responses)
+                    },
+                ),
+            )
+            .with_state(exchange.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, rest).await.unwrap() });
+        (base_url, exchange)
+    }
+
+    fn rules() -> MarketRules {
+        MarketRules {
+            state: "NORMAL".into(),
+            price_scale: 2,
+            quantity_scale: 6,
+            amount_scale: 2,
+            min_quantity: 0.000001,
+            min_amount: 1.0,
+        }
+    }
+
+    // order as exchange reports it once it's filled
+    fn filled(id: &str, order: &Value) -> OrderUpdate {
+        let price: f64 = order["price"].as_str().unwrap().parse().unwrap();
+        let quantity: f64 = order["quantity"].as_str().unwrap().parse().unwrap();
+        let response: OrderResponse = serde_json::from_value(json!({
+            "id": id,
+            "clientOrderId": order["clientOrderId"],
+            "symbol": "BTC_USDT",
+            "state": "FILLED",
+            "side": order["side"],
+            "type": "LIMIT",
+            "timeInForce": "GTC",
+            "quantity": order["quantity"],
+            "price": order["price"],
+            "avgPrice": order["price"],
+            "amount": "0",
+            "filledQuantity": order["quantity"],
+            "filledAmount": (price * quantity).to_string(),
+            "createTime": 1738700743000i64,
+            "updateTime": 1738700750000i64
+        }))
+        .unwrap();
+        OrderUpdate::from_response(&response, &PoloniexContext::init(false).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_grid_levels() {
+        let config = GridConfig {
+            name: "grid".into(),
+            pair: Pair::new("BTC", "USDT"),
+            lower_price: 90.0,
+            upper_price: 110.0,
+            levels: 3,
+            investment: 100.0,
+        };
+        let grid = Grid::new(&config, &rules()).unwrap();
+        assert_eq!(grid.prices, ["90.00", "100.00", "110.00"]);
+        // 100 / (90 + 100), rounded down
+        assert_eq!(grid.quantity, "0.526315");
+
+        let dense = GridConfig {
+            levels: 5000,
+            ..config
+        };
+        assert!(Grid::new(&dense, &rules()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_grid_against_stand_in_server() {
+        let (base_url, exchange) = stand_in_server().await;
+        let requester = stand_in_requester(&base_url);
+        let pair = Pair::new("BTC", "USDT");
+        let limits = PairLimits {
+            max_notional: 1000.0,
+            max_open_orders: 10,
+            price_band: 0.2,
+        };
+        let guard = RiskGuard::new(
+            [(pair.clone(), rules())].into(),
+            [(pair.clone(), limits)].into(),
+        );
+        let tracker = OrderTracker::default();
+        let config = GridConfig {
+            name: "grid".into(),
+            pair,
+            lower_price: 90.0,
+            upper_price: 110.0,
+            levels: 5,
+            investment: 390.0,
+        };
+        let mut bot = GridBot::start(config, &requester, &guard, &tracker)
+            .await
+            .unwrap();
+        assert_eq!(bot.grid().quantity, "1.000000");
+
+        // buys below 100, sells above, nothing at 100
+        let placed = exchange.lock().unwrap().placed.clone();
+        let ladder: Vec<_> = placed
+            .iter()
+            .map(|order| {
+                (
+                    order["side"].as_str().unwrap(),
+                    order["price"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            ladder,
+            [
+                ("BUY", "90.00"),
+                ("BUY", "95.00"),
+                ("SELL", "105.00"),
+                ("SELL", "110.00")
+            ]
+        );
+        // ids of the previous run aren't reused
+        let client_id = |order: &Value| -> i64 {
+            let id = order["clientOrderId"].as_str().unwrap();
+            id.strip_prefix("grid-").unwrap().parse().unwrap()
+        };
+        assert!(client_id(&placed[0]) > 1738700743000);
+        assert!(
+            placed
+                .windows(2)
+                .all(|pair| client_id(&pair[0]) < client_id(&pair[1]))
+        );
+
+        let last_placed = || exchange.lock().unwrap().placed.last().cloned().unwrap();
+
+        // buy at 95 is filled, sell at 100 replaces it
+        tracker.apply(filled("2", &placed[1])).unwrap();
+        bot.step().await.unwrap();
+        let sell = last_placed();
+        assert_eq!(
+            (&sell["side"], &sell["price"]),
+            (&json!("SELL"), &json!("100.00"))
+        );
+        assert_eq!(bot.realized_profit(), 0.0);
+
+        // and it's filled too, profit is one grid step
+        tracker.apply(filled("5", &sell)).unwrap();
+        bot.step().await.unwrap();
+        let buy = last_placed();
+        assert_eq!(
+            (&buy["side"], &buy["price"]),
+            (&json!("BUY"), &json!("95.00"))
+        );
+        assert_eq!(bot.realized_profit(), 5.0);
+
+        // initial sell is valued at the start price
+        tracker.apply(filled("3", &placed[2])).unwrap();
+        bot.step().await.unwrap();
+        assert_eq!(last_placed()["price"], "100.00");
+        assert_eq!(bot.realized_profit(), 10.0);
+
+        let open: Vec<_> = bot.orders().into_values().map(|(id, _)| id).collect();
+        assert_eq!(open.len(), 4);
+        bot.stop().await.unwrap();
+        let mut cancelled: Vec<_> = exchange.lock().unwrap().cancelled.clone();
+        cancelled.sort_by_key(|id| id.as_str().unwrap().to_string());
+        let mut open: Vec<Value> = open.into_iter().map(Value::from).collect();
+        open.sort_by_key(|id| id.as_str().unwrap().to_string());
+        assert_eq!(cancelled, open);
+        assert!(bot.orders().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_grid_start_cancels_placed_orders_on_failure() {
+        let (base_url, exchange) = stand_in_server().await;
+        exchange.lock().unwrap().capacity = Some(2);
+        let requester = stand_in_requester(&base_url);
+        let pair = Pair::new("BTC", "USDT");
+        let limits = PairLimits {
+            max_notional: 1000.0,
+            max_open_orders: 10,
+            price_band: 0.2,
+        };
+        let guard = RiskGuard::new(
+            [(pair.clone(), rules())].into(),
+            [(pair.clone(), limits)].into(),
+        );
+        let tracker = OrderTracker::default();
+        let config = GridConfig {
+            name: "grid".into(),
+            pair,
+            lower_price: 90.0,
+            upper_price: 110.0,
+            levels: 5,
+            investment: 390.0,
+        };
+        let res = GridBot::start(config, &requester, &guard, &tracker).await;
+        assert!(res.is_err());
+        // the third order is rejected, the first two are cancelled
+        assert_eq!(exchange.lock().unwrap().cancelled, [json!("1"), json!("2")]);
+    }
+}
@@ -0,0 +1,189 @@
+use std::time::Duration;
+
+use anyhow::{Context as _, bail};
+use bitsgap_shared::ApiRequester;
+
+use crate::{
+    context::PoloniexContext,
+    rest::orders::kill_switch::{KillSwitchRequest, KillSwitchResponse},
+};
+
+/// Dead man's switch: keeps Poloniex kill switch from firing while process is healthy.
+/// If process hangs, loses connectivity or reports itself unhealthy, all orders are cancelled after `timeout`.
+#[derive(Debug, Clone)]
+pub struct KillSwitchKeeper {
+    /// orders are cancelled if switch isn't refreshed for this long
+    timeout: Duration,
+    /// several refreshes fit into timeout, so a single failed one doesn't fire switch
+    refresh_interval: Duration,
+}
+
+impl KillSwitchKeeper {
+    pub fn new(timeout: Duration, refresh_interval: Duration) -> anyhow::Result<Self> {
+        if !(KillSwitchRequest::MIN_TIMEOUT..=KillSwitchRequest::MAX_TIMEOUT).contains(&timeout) {
+            bail!(
+                "kill switch timeout {timeout:?} is out of {:?}..={:?}",
+                KillSwitchRequest::MIN_TIMEOUT,
+                KillSwitchRequest::MAX_TIMEOUT
+            );
+        }
+        if refresh_interval.is_zero() || refresh_interval >= timeout {
+            bail!(
+                "kill switch refresh interval {refresh_interval:?} has to be less than timeout {timeout:?}"
+            );
+        }
+        Ok(Self {
+            timeout,
+            refresh_interval,
+        })
+    }
+
+    /// Refreshes switch once
+    pub async fn refresh(
+        &self,
+        requester: &ApiRequester<PoloniexContext>,
+    ) -> anyhow::Result<KillSwitchResponse> {
+        requester
+            .send(&KillSwitchRequest {
+                timeout: Some(self.timeout),
+            })
+            .await
+            .context("refresh kill switch")
+    }
+
+    /// Arms switch and refreshes it every interval while `healthy` returns true. Never returns after arming.
+    /// Failed refreshes are retried on the next tick, so switch fires only if they fail for the whole timeout.
+    /// Dropping the future leaves switch armed, call `disarm` to stop gracefully without cancelling orders.
+    pub async fn run(
+        &self,
+        requester: &ApiRequester<PoloniexContext>,
+        healthy: impl Fn() -> bool,
+    ) -> anyhow::Result<()> {
+        let armed = self.refresh(requester).await.context("arm kill switch")?;
+        log::info!(
+            "Kill switch is armed, orders are cancelled at {} unless refreshed",
+            armed.cancellation_time.display()
+        );
+        let mut interval = tokio::time::interval(self.refresh_interval);
+        // the first tick completes immediately
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            if !healthy() {
+                log::warn!("Process is unhealthy, kill switch isn't refreshed");
+                continue;
+            }
+            if let Err(err) = self.refresh(requester).await {
+                log::error!("{err:#}");
+            }
+        }
+    }
+
+    /// Disables switch, orders stay open
+    pub async fn disarm(&self, requester: &ApiRequester<PoloniexContext>) -> anyhow::Result<()> {
+        requester
+            .send(&KillSwitchRequest { timeout: None })
+            .await
+            .context("disarm kill switch")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    };
+
+    use axum::{Json, Router, extract::State, http::StatusCode, routing::post};
+    use serde_json::{Value, json};
+    use tokio::{
+        net::TcpListener,
+        sync::mpsc::{self, UnboundedReceiver, UnboundedSender, error::TryRecvError},
+        time::timeout,
+    };
+
+    use super::*;
+    use crate::tests::stand_in_requester;
+
+    #[test]
+    fn test_kill_switch_keeper_config() {
+        let minute = Duration::from_secs(60);
+        assert!(KillSwitchKeeper::new(minute, Duration::from_secs(15)).is_ok());
+        assert!(KillSwitchKeeper::new(minute, minute).is_err());
+        assert!(KillSwitchKeeper::new(Duration::from_secs(5), Duration::from_secs(1)).is_err());
+        assert!(KillSwitchKeeper::new(Duration::from_secs(601), minute).is_err());
+    }
+
+    /// Timeout of every kill switch request is sent to receiver before reply, the second one fails
+    async fn stand_in_server() -> (ApiRequester<PoloniexContext>, UnboundedReceiver<String>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let requests = Arc::new(AtomicUsize::new(0));
+        let rest = Router::new()
+            .route(
+                "/orders/killSwitch",
+                post(
+                    |State((tx, requests)): State<(UnboundedSender<String>, Arc<AtomicUsize>)>,
+                     Json(body): Json<Value>| async move {
+                        tx.send(body["timeout"].as_str().unwrap().into()).unwrap();
+                        if requests.fetch_add(1, Ordering::SeqCst) == 1 {
+                            return Err(StatusCode::BAD_REQUEST);
+                        }
+                        Ok(Json(json!({"startTime": 1738700743000i64, "cancellationTime": 1738700803000i64})))
+                    },
+                ),
+            )
+            .with_state((tx, requests));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, rest).await.unwrap() });
+        (stand_in_requester(&base_url), rx)
+    }
+
+    #[tokio::test]
+    async fn test_kill_switch_keeper_run() {
+        let (requester, mut timeouts) = stand_in_server().await;
+        let keeper =
+            KillSwitchKeeper::new(Duration::from_secs(60), Duration::from_millis(10)).unwrap();
+        let healthy = AtomicBool::new(true);
+        // every health check is reported, so test waits for keeper instead of clock
+        let (checks_tx, mut checks) = mpsc::unbounded_channel();
+        let is_healthy = || {
+            let ok = healthy.load(Ordering::SeqCst);
+            checks_tx.send(ok).unwrap();
+            ok
+        };
+
+        let steps = async {
+            // armed, then the second refresh fails and the third one is made anyway
+            for _ in 0..3 {
+                assert_eq!(timeouts.recv().await.unwrap(), "60");
+            }
+
+            healthy.store(false, Ordering::SeqCst);
+            while checks.recv().await.unwrap() {}
+            // refreshes are sequential, so those before the unhealthy check are done
+            while timeouts.try_recv().is_ok() {}
+            for _ in 0..3 {
+                assert!(!checks.recv().await.unwrap());
+            }
+            assert_eq!(timeouts.try_recv(), Err(TryRecvError::Empty));
+
+            healthy.store(true, Ordering::SeqCst);
+            assert_eq!(timeouts.recv().await.unwrap(), "60");
+        };
+        tokio::select! {
+            res = keeper.run(&requester, is_healthy) => panic!("keeper stopped: {res:?}"),
+            res = timeout(Duration::from_secs(10), steps) => res.unwrap(),
+        }
+
+        keeper.disarm(&requester).await.unwrap();
+        // besides refresh which was in progress when run was dropped
+        let mut sent = vec![];
+        while let Ok(timeout) = timeouts.try_recv() {
+            sent.push(timeout);
+        }
+        assert!(sent.contains(&"-1".to_string()), "{sent:?}");
+    }
+}
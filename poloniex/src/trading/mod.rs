@@ -0,0 +1,6 @@
+//! Building blocks of trading bots on top of private REST API and WebSocket
+
+pub mod grid;
+pub mod kill_switch;
+pub mod risk;
+pub mod tracker;
@@ -0,0 +1,844 @@
+use std::{
+    borrow::Borrow,
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    sync::{
+        Mutex, MutexGuard,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use anyhow::{Context as _, bail};
+use bitsgap_shared::{
+    ApiRequester,
+    pair::{ExchangeSymbols, Pair},
+    utils::{Has, time::UnixMillis},
+};
+
+use super::tracker::{FINAL_ORDERS_RETENTION, OrderTracker};
+use crate::{
+    context::PoloniexContext,
+    rest::{
+        markets::{MarketPriceRequest, MarketResponse, MarketsRequest},
+        orders::{
+            OrderRef, OrderType, PlacedOrderResponse,
+            create::{BatchOrderResponse, CreateOrderRequest, CreateOrdersRequest},
+            replace::ReplaceOrderRequest,
+        },
+    },
+    units::PxOrderId,
+};
+
+/// Our limits of one pair
+#[derive(Debug, Clone)]
+pub struct PairLimits {
+    /// max price * quantity of a single order, in quote units
+    pub max_notional: f64,
+    /// max open orders of pair, including the new one
+    pub max_open_orders: usize,
+    /// max relative distance of limit price from the latest trade price, 0.05 is 5%
+    pub price_band: f64,
+}
+
+/// Trading rules of Poloniex for pair, from `/markets`
+#[derive(Debug, Clone)]
+pub struct MarketRules {
+    /// market state, orders are accepted in "NORMAL" one only
+    pub state: String,
+    /// decimal places of price
+    pub price_scale: u32,
+    /// decimal places of base units
+    pub quantity_scale: u32,
+    /// decimal places of quote units
+    pub amount_scale: u32,
+    /// minimal base units of order
+    pub min_quantity: f64,
+    /// minimal quote units of order
+    pub min_amount: f64,
+}
+
+impl MarketRules {
+    pub fn from_response(response: &MarketResponse) -> anyhow::Result<Self> {
+        let limit = &response.symbol_trade_limit;
+        Ok(Self {
+            state: response.state.clone(),
+            price_scale: limit.price_scale,
+            quantity_scale: limit.quantity_scale,
+            amount_scale: limit.amount_scale,
+            min_quantity: limit.min_quantity.parse().context("parse min quantity")?,
+            min_amount: limit.min_amount.parse().context("parse min amount")?,
+        })
+    }
+}
+
+/// Why order isn't sent to exchange
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rejection {
+    KillSwitch,
+    /// there are no trading rules of pair
+    UnknownMarket(Pair),
+    NotTradable {
+        pair: Pair,
+        state: String,
+    },
+    /// there are no our limits of pair
+    NoLimits(Pair),
+    TooManyOpenOrders {
+        open: usize,
+        max: usize,
+    },
+    /// field required by order type isn't set
+    Missing(&'static str),
+    /// not a positive decimal number
+    InvalidNumber {
+        field: &'static str,
+        value: String,
+    },
+    /// more decimal places than exchange accepts
+    Precision {
+        field: &'static str,
+        value: String,
+        scale: u32,
+    },
+    BelowMinQuantity {
+        quantity: f64,
+        min: f64,
+    },
+    BelowMinAmount {
+        notional: f64,
+        min: f64,
+    },
+    NotionalTooLarge {
+        notional: f64,
+        max: f64,
+    },
+    /// latest trade price is needed, but unknown
+    NoReferencePrice,
+    PriceOutOfBand {
+        price: f64,
+        reference: f64,
+        band: f64,
+    },
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::KillSwitch => write!(f, "kill switch is engaged"),
+            Self::UnknownMarket(pair) => write!(f, "no trading rules of {pair}"),
+            Self::NotTradable { pair, state } => write!(f, "{pair} isn't tradable, state {state}"),
+            Self::NoLimits(pair) => write!(f, "no risk limits of {pair}"),
+            Self::TooManyOpenOrders { open, max } => {
+                write!(f, "{open} orders are open already, max {max}")
+            }
+            Self::Missing(field) => write!(f, "{field} is required"),
+            Self::InvalidNumber { field, value } => {
+                write!(f, "{field} {value:?} isn't a positive decimal number")
+            }
+            Self::Precision {
+                field,
+                value,
+                scale,
+            } => write!(f, "{field} {value} has more than {scale} decimal places"),
+            Self::BelowMinQuantity { quantity, min } => {
+                write!(f, "quantity {quantity} is below minimum {min}")
+            }
+            Self::BelowMinAmount { notional, min } => {
+                write!(f, "notional {notional} is below minimum {min}")
+            }
+            Self::NotionalTooLarge { notional, max } => {
+                write!(f, "notional {notional} is above limit {max}")
+            }
+            Self::NoReferencePrice => write!(f, "latest trade price is unknown"),
+            Self::PriceOutOfBand {
+                price,
+                reference,
+                band,
+            } => write!(f, "price {price} is further than {band} from {reference}"),
+        }
+    }
+}
+
+impl std::error::Error for Rejection {}
+
+/// Order to check, as it would be placed
+#[derive(Debug, Clone, Copy)]
+pub struct OrderIntent<'a> {
+    pub pair: &'a Pair,
+    pub order_type: OrderType,
+    pub price: Option<&'a str>,
+    pub quantity: Option<&'a str>,
+    pub amount: Option<&'a str>,
+}
+
+impl<'a, P: Borrow<Pair>> From<&'a CreateOrderRequest<P>> for OrderIntent<'a> {
+    fn from(order: &'a CreateOrderRequest<P>) -> Self {
+        Self {
+            pair: order.pair.borrow(),
+            order_type: order.order_type,
+            price: order.price.as_deref(),
+            quantity: order.quantity.as_deref(),
+            amount: order.amount.as_deref(),
+        }
+    }
+}
+
+// positive decimal number of at most `scale` decimal places
+fn number(field: &'static str, value: &str, scale: u32) -> Result<f64, Rejection> {
+    let invalid = || Rejection::InvalidNumber {
+        field,
+        value: value.into(),
+    };
+    let (int, fraction) = value.split_once('.').unwrap_or((value, ""));
+    let digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    if int.is_empty() || !digits(int) || !digits(fraction) {
+        return Err(invalid());
+    }
+    if fraction.trim_end_matches('0').len() > scale as usize {
+        return Err(Rejection::Precision {
+            field,
+            value: value.into(),
+            scale,
+        });
+    }
+    match value.parse::<f64>() {
+        Ok(number) if number > 0.0 => Ok(number),
+        _ => Err(invalid()),
+    }
+}
+
+/// Orders sent through guard, which tracker may not know yet
+#[derive(Debug, Default)]
+struct Exposure {
+    /// orders being sent, by pair
+    in_flight: BTreeMap<Pair, usize>,
+    /// orders accepted by exchange which aren't known to be done, with time they were accepted
+    accepted: BTreeMap<PxOrderId, (Pair, UnixMillis)>,
+}
+
+impl Exposure {
+    /// Forgets accepted orders which tracker reports done.
+    /// Orders tracker never learned about are forgotten after `FINAL_ORDERS_RETENTION`.
+    fn release_final(&mut self, tracker: &OrderTracker, now: UnixMillis) {
+        let expired = now.saturating_sub(FINAL_ORDERS_RETENTION);
+        self.accepted
+            .retain(|id, (_, accepted_at)| match tracker.order(id) {
+                Some(order) => !order.status.is_final(),
+                None => *accepted_at >= expired,
+            });
+    }
+
+    /// Open orders of pair, as tracker and guard know them, besides the `replaced` one
+    fn open_orders(
+        &self,
+        tracker: &OrderTracker,
+        pair: &Pair,
+        replaced: Option<&PxOrderId>,
+    ) -> usize {
+        let mut open: BTreeSet<PxOrderId> = tracker
+            .open_orders(Some(pair))
+            .into_iter()
+            .map(|order| order.id)
+            .collect();
+        open.extend(
+            self.accepted
+                .iter()
+                .filter(|(_, (accepted_pair, _))| accepted_pair == pair)
+                .map(|(id, _)| id.clone()),
+        );
+        if let Some(replaced) = replaced {
+            open.remove(replaced);
+        }
+        open.len() + self.in_flight.get(pair).copied().unwrap_or_default()
+    }
+}
+
+/// Order counted as open while it's sent, forgotten on drop unless it's accepted
+struct Reservation<'a> {
+    guard: &'a RiskGuard,
+    pair: Pair,
+}
+
+impl Reservation<'_> {
+    fn accept(self, id: PxOrderId) {
+        let accepted = (self.pair.clone(), UnixMillis::now());
+        self.guard.exposure().accepted.insert(id, accepted);
+    }
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        let mut exposure = self.guard.exposure();
+        if let Some(count) = exposure.in_flight.get_mut(&self.pair) {
+            *count -= 1;
+            if *count == 0 {
+                exposure.in_flight.remove(&self.pair);
+            }
+        }
+    }
+}
+
+/// Pre-trade checks between strategy code and exchange.
+/// Orders it sends count as open until tracker reports them done, so concurrent callers
+/// can't exceed `max_open_orders` before tracker catches up.
+pub struct RiskGuard {
+    rules: BTreeMap<Pair, MarketRules>,
+    limits: BTreeMap<Pair, PairLimits>,
+    killed: AtomicBool,
+    exposure: Mutex<Exposure>,
+}
+
+impl RiskGuard {
+    pub fn new(rules: BTreeMap<Pair, MarketRules>, limits: BTreeMap<Pair, PairLimits>) -> Self {
+        Self {
+            rules,
+            limits,
+            killed: AtomicBool::new(false),
+            exposure: Mutex::default(),
+        }
+    }
+
+    /// Fetches trading rules of pairs known to context
+    pub async fn load(
+        requester: &ApiRequester<PoloniexContext>,
+        limits: BTreeMap<Pair, PairLimits>,
+    ) -> anyhow::Result<Self> {
+        let responses = requester
+            .get_response(&MarketsRequest::<Pair> { pair: None })
+            .await
+            .context("get markets")?;
+        let exchange_symbols = requester.context().give(ExchangeSymbols);
+        let mut rules = BTreeMap::new();
+        for response in &responses {
+            // markets we don't know are never traded
+            let Ok(pair) = exchange_symbols.to_pair(&response.symbol) else {
+                continue;
+            };
+            let market_rules = MarketRules::from_response(response)
+                .with_context(|| format!("trading rules of {}", response.symbol))?;
+            rules.insert(pair.clone(), market_rules);
+        }
+        Ok(Self::new(rules, limits))
+    }
+
+    pub fn rules(&self, pair: &Pair) -> Option<&MarketRules> {
+        self.rules.get(pair)
+    }
+
+    fn exposure(&self) -> MutexGuard<'_, Exposure> {
+        // exposure is consistent after every statement
+        self.exposure.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Open orders of pair, including ones which are being sent or tracker doesn't know yet
+    pub fn open_orders(&self, tracker: &OrderTracker, pair: &Pair) -> usize {
+        let mut exposure = self.exposure();
+        exposure.release_final(tracker, UnixMillis::now());
+        exposure.open_orders(tracker, pair, None)
+    }
+
+    /// Checks order against open orders and counts it as open until reservation is dropped
+    fn reserve(
+        &self,
+        tracker: &OrderTracker,
+        order: OrderIntent<'_>,
+        replaced: Option<&PxOrderId>,
+        last_price: Option<f64>,
+    ) -> Result<Reservation<'_>, Rejection> {
+        // tracker is locked under exposure lock, never the other way round
+        let mut exposure = self.exposure();
+        exposure.release_final(tracker, UnixMillis::now());
+        let open_orders = exposure.open_orders(tracker, order.pair, replaced);
+        self.check(order, open_orders, last_price)?;
+        *exposure.in_flight.entry(order.pair.clone()).or_default() += 1;
+        Ok(Reservation {
+            guard: self,
+            pair: order.pair.clone(),
+        })
+    }
+
+    /// Every order is rejected until switch is released
+    pub fn engage_kill_switch(&self) {
+        log::warn!("Kill switch is engaged, orders are rejected");
+        self.killed.store(true, Ordering::SeqCst);
+    }
+
+    pub fn release_kill_switch(&self) {
+        log::warn!("Kill switch is released");
+        self.killed.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_killed(&self) -> bool {
+        self.killed.load(Ordering::SeqCst)
+    }
+
+    /// `open_orders` of pair besides the checked one, `last_price` of pair's latest trade
+    pub fn check(
+        &self,
+        order: OrderIntent<'_>,
+        open_orders: usize,
+        last_price: Option<f64>,
+    ) -> Result<(), Rejection> {
+        if self.is_killed() {
+            return Err(Rejection::KillSwitch);
+        }
+        let pair = order.pair;
+        let rules = self
+            .rules
+            .get(pair)
+            .ok_or_else(|| Rejection::UnknownMarket(pair.clone()))?;
+        if rules.state != MarketResponse::NORMAL_STATE {
+            return Err(Rejection::NotTradable {
+                pair: pair.clone(),
+                state: rules.state.clone(),
+            });
+        }
+        let limits = self
+            .limits
+            .get(pair)
+            .ok_or_else(|| Rejection::NoLimits(pair.clone()))?;
+        if open_orders >= limits.max_open_orders {
+            return Err(Rejection::TooManyOpenOrders {
+                open: open_orders,
+                max: limits.max_open_orders,
+            });
+        }
+
+        let price = order
+            .price
+            .map(|price| number("price", price, rules.price_scale))
+            .transpose()?;
+        let quantity = order
+            .quantity
+            .map(|quantity| number("quantity", quantity, rules.quantity_scale))
+            .transpose()?;
+        let amount = order
+            .amount
+            .map(|amount| number("amount", amount, rules.amount_scale))
+            .transpose()?;
+        let reference = || last_price.ok_or(Rejection::NoReferencePrice);
+        let notional = match order.order_type {
+            OrderType::Limit | OrderType::LimitMaker => {
+                let price = price.ok_or(Rejection::Missing("price"))?;
+                let quantity = quantity.ok_or(Rejection::Missing("quantity"))?;
+                let reference = reference()?;
+                if (price - reference).abs() > reference * limits.price_band {
+                    return Err(Rejection::PriceOutOfBand {
+                        price,
+                        reference,
+                        band: limits.price_band,
+                    });
+                }
+                price * quantity
+            }
+            OrderType::Market => match (quantity, amount) {
+                (_, Some(amount)) => amount,
+                (Some(quantity), None) => quantity * reference()?,
+                (None, None) => return Err(Rejection::Missing("quantity or amount")),
+            },
+        };
+        if let Some(quantity) = quantity {
+            if quantity < rules.min_quantity {
+                return Err(Rejection::BelowMinQuantity {
+                    quantity,
+                    min: rules.min_quantity,
+                });
+            }
+        }
+        if notional < rules.min_amount {
+            return Err(Rejection::BelowMinAmount {
+                notional,
+                min: rules.min_amount,
+            });
+        }
+        if notional > limits.max_notional {
+            return Err(Rejection::NotionalTooLarge {
+                notional,
+                max: limits.max_notional,
+            });
+        }
+        Ok(())
+    }
+
+    async fn last_price(
+        &self,
+        requester: &ApiRequester<PoloniexContext>,
+        pair: &Pair,
+    ) -> anyhow::Result<f64> {
+        let response = requester
+            .get_response(&MarketPriceRequest { pair })
+            .await
+            .context("get latest trade price")?;
+        response
+            .price
+            .parse()
+            .with_context(|| format!("parse price {:?}", response.price))
+    }
+
+    /// Checks order against open orders and latest trade price, then places it.
+    /// Rejection can be told from other errors with `downcast_ref::<Rejection>`.
+    pub async fn place<P: Borrow<Pair>>(
+        &self,
+        requester: &ApiRequester<PoloniexContext>,
+        tracker: &OrderTracker,
+        order: &CreateOrderRequest<P>,
+    ) -> anyhow::Result<PlacedOrderResponse> {
+        if self.is_killed() {
+            return Err(Rejection::KillSwitch.into());
+        }
+        let last_price = self.last_price(requester, order.pair.borrow()).await?;
+        let reservation = self.reserve(tracker, order.into(), None, Some(last_price))?;
+        let response = requester.send(order).await.context("place order")?;
+        reservation.accept(response.id.clone());
+        Ok(response)
+    }
+
+    /// Checks every order as if the previous ones were placed, then places them at once.
+    /// Nothing is placed if any of them is rejected, error tells which one.
+    pub async fn place_batch<P: Borrow<Pair>>(
+        &self,
+        requester: &ApiRequester<PoloniexContext>,
+        tracker: &OrderTracker,
+        batch: &CreateOrdersRequest<P>,
+    ) -> anyhow::Result<Vec<BatchOrderResponse>> {
+        if self.is_killed() {
+            return Err(Rejection::KillSwitch.into());
+        }
+        let mut last_prices = BTreeMap::new();
+        for order in &batch.orders {
+            let pair = order.pair.borrow();
+            if !last_prices.contains_key(pair) {
+                let last_price = self.last_price(requester, pair).await?;
+                last_prices.insert(pair.clone(), last_price);
+            }
+        }
+        let reservations = batch
+            .orders
+            .iter()
+            .enumerate()
+            .map(|(i, order)| {
+                let last_price = last_prices.get(order.pair.borrow()).copied();
+                self.reserve(tracker, order.into(), None, last_price)
+                    .with_context(|| format!("order {i} of batch"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let responses = requester.send(batch).await.context("place orders")?;
+        for (response, reservation) in responses.iter().zip(reservations) {
+            if let Ok(id) = response.placed() {
+                reservation.accept(id.clone());
+            }
+        }
+        Ok(responses)
+    }
+
+    /// Checks order as it would be after replacement, then replaces it
+    pub async fn replace(
+        &self,
+        requester: &ApiRequester<PoloniexContext>,
+        tracker: &OrderTracker,
+        replace: &ReplaceOrderRequest,
+    ) -> anyhow::Result<PlacedOrderResponse> {
+        if self.is_killed() {
+            return Err(Rejection::KillSwitch.into());
+        }
+        let current = match &replace.order {
+            OrderRef::Id(id) => tracker.order(id),
+            OrderRef::ClientId(client_order_id) => tracker.order_by_client_id(client_order_id),
+        }
+        .with_context(|| format!("order {:?} isn't tracked", replace.order))?;
+        if current.status.is_final() {
+            bail!("order {} is done already", current.id);
+        }
+        let order_type = replace.order_type.unwrap_or(current.order_type);
+        let intent = OrderIntent {
+            pair: &current.pair,
+            order_type,
+            price: match order_type {
+                OrderType::Market => None,
+                OrderType::Limit | OrderType::LimitMaker => {
+                    Some(replace.price.as_deref().unwrap_or(&current.price))
+                }
+            },
+            quantity: replace.quantity.as_deref().or(Some(&current.quantity)),
+            amount: replace.amount.as_deref(),
+        };
+        let last_price = self.last_price(requester, &current.pair).await?;
+        // the replaced order is cancelled
+        let reservation = self.reserve(tracker, intent, Some(&current.id), Some(last_price))?;
+        let response = requester.send(replace).await.context("replace order")?;
+        self.exposure().accepted.remove(&current.id);
+        reservation.accept(response.id.clone());
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::{
+        Json, Router,
+        extract::State,
+        routing::{get, post},
+    };
+    use serde_json::{Value, json};
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::{
+        rest::orders::{OrderResponse, OrderSide},
+        tests::stand_in_requester,
+        trading::tracker::OrderUpdate,
+    };
+
+    fn guard() -> RiskGuard {
+        let market: MarketResponse = serde_json::from_str(
+            r#"{
+                "symbol": "BTC_USDT",
+                "baseCurrencyName": "BTC",
+                "quoteCurrencyName": "USDT",
+                "displayName": "BTC/USDT",
+                "state": "NORMAL",
+                "visibleStartTime": 1659018819512,
+                "tradableStartTime": 1659018819512,
+                "symbolTradeLimit": {
+                    "symbol": "BTC_USDT",
+                    "priceScale": 2,
+                    "quantityScale": 6,
+                    "amountScale": 2,
+                    "minQuantity": "0.000001",
+                    "minAmount": "1",
+                    "highestBid": "0",
+                    "lowestAsk": "0"
+                }
+            }"#,
+        )
+        .unwrap();
+        let pair = Pair::new("BTC", "USDT");
+        let rules = MarketRules::from_response(&market).unwrap();
+        let limits = PairLimits {
+            max_notional: 1000.0,
+            max_open_orders: 2,
+            price_band: 0.05,
+        };
+        RiskGuard::new([(pair.clone(), rules)].into(), [(pair, limits)].into())
+    }
+
+    fn limit<'a>(pair: &'a Pair, price: &'a str, quantity: &'a str) -> OrderIntent<'a> {
+        OrderIntent {
+            pair,
+            order_type: OrderType::Limit,
+            price: Some(price),
+            quantity: Some(quantity),
+            amount: None,
+        }
+    }
+
+    #[test]
+    fn test_risk_checks() {
+        let guard = guard();
+        let pair = Pair::new("BTC", "USDT");
+        let last = Some(60000.0);
+        assert_eq!(
+            guard.check(limit(&pair, "59000.5", "0.01"), 0, last),
+            Ok(())
+        );
+
+        let rejection = |order, open| guard.check(order, open, last).unwrap_err();
+        assert_eq!(
+            rejection(limit(&pair, "59000", "0.01"), 2),
+            Rejection::TooManyOpenOrders { open: 2, max: 2 }
+        );
+        // fat finger: extra zero in price
+        assert!(matches!(
+            rejection(limit(&pair, "590000", "0.001"), 0),
+            Rejection::PriceOutOfBand { .. }
+        ));
+        assert_eq!(
+            rejection(limit(&pair, "59000", "0.1"), 0),
+            Rejection::NotionalTooLarge {
+                notional: 5900.0,
+                max: 1000.0
+            }
+        );
+        assert!(matches!(
+            rejection(limit(&pair, "59000.123", "0.01"), 0),
+            Rejection::Precision { field: "price", .. }
+        ));
+        assert!(matches!(
+            rejection(limit(&pair, "59000", "1e-2"), 0),
+            Rejection::InvalidNumber {
+                field: "quantity",
+                ..
+            }
+        ));
+        assert!(matches!(
+            rejection(limit(&pair, "59000", "0.00001"), 0),
+            Rejection::BelowMinAmount { .. }
+        ));
+        let eth = Pair::new("ETH", "USDT");
+        assert_eq!(
+            rejection(limit(&eth, "3000", "0.1"), 0),
+            Rejection::UnknownMarket(eth.clone())
+        );
+
+        // market sell is valued at the latest trade price
+        let market_sell = OrderIntent {
+            order_type: OrderType::Market,
+            price: None,
+            ..limit(&pair, "", "0.1")
+        };
+        assert_eq!(
+            guard.check(market_sell, 0, None),
+            Err(Rejection::NoReferencePrice)
+        );
+        assert!(matches!(
+            rejection(market_sell, 0),
+            Rejection::NotionalTooLarge { .. }
+        ));
+
+        guard.engage_kill_switch();
+        assert_eq!(
+            rejection(limit(&pair, "59000.5", "0.01"), 0),
+            Rejection::KillSwitch
+        );
+        guard.release_kill_switch();
+        assert_eq!(
+            guard.check(limit(&pair, "59000.5", "0.01"), 0, last),
+            Ok(())
+        );
+    }
+
+    fn done(id: &str) -> OrderUpdate {
+        let response: OrderResponse = serde_json::from_value(json!({
+            "id": id,
+            "clientOrderId": "",
+            "symbol": "BTC_USDT",
+            "state": "FILLED",
+            "side": "BUY",
+            "type": "LIMIT",
+            "timeInForce": "GTC",
+            "quantity": "0.01",
+            "price": "59000.5",
+            "avgPrice": "59000.5",
+            "amount": "0",
+            "filledQuantity": "0.01",
+            "filledAmount": "590.005",
+            "createTime": 1738700743000i64,
+            "updateTime": 1738700750000i64
+        }))
+        .unwrap();
+        OrderUpdate::from_response(&response, &PoloniexContext::init(false).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_orders_are_counted_before_tracker_sees_them() {
+        let guard = guard();
+        let tracker = OrderTracker::default();
+        let pair = Pair::new("BTC", "USDT");
+        let order = limit(&pair, "59000.5", "0.01");
+        let last = Some(60000.0);
+
+        let first = guard.reserve(&tracker, order, None, last).unwrap();
+        let second = guard.reserve(&tracker, order, None, last).unwrap();
+        assert_eq!(
+            guard.reserve(&tracker, order, None, last).err(),
+            Some(Rejection::TooManyOpenOrders { open: 2, max: 2 })
+        );
+        // failed sending frees the slot
+        drop(second);
+        let second = guard.reserve(&tracker, order, None, last).unwrap();
+
+        first.accept("1".into());
+        second.accept("2".into());
+        assert_eq!(guard.open_orders(&tracker, &pair), 2);
+        // but the replaced order isn't counted
+        assert!(
+            guard
+                .reserve(&tracker, order, Some(&"1".into()), last)
+                .is_ok()
+        );
+
+        // released once tracker reports order done
+        tracker.apply(done("1")).unwrap();
+        assert_eq!(guard.open_orders(&tracker, &pair), 1);
+    }
+
+    #[tokio::test]
+    async fn test_batch_is_checked_as_a_whole() {
+        let sent: Arc<Mutex<Vec<Value>>> = Arc::default();
+        let rest = Router::new()
+            .route(
+                "/markets/{symbol}/price",
+                get(|| async {
+                    Json(json!({"symbol": "BTC_USDT", "price": "60000", "time": 1738700743000i64, "ts": 1738700743001i64}))
+                }),
+            )
+            .route(
+                "/orders/batch",
+                post(
+                    |State(sent): State<Arc<Mutex<Vec<Value>>>>, Json(orders): Json<Vec<Value>>| async move {
+                        let mut sent = sent.lock().unwrap();
+                        let responses: Vec<_> = orders
+                            .into_iter()
+                            .map(|order| {
+                                sent.push(order);
+                                json!({"id": sent.len().to_string(), "clientOrderId": ""})
+                            })
+                            .collect();
+                        Json(responses)
+                    },
+                ),
+            )
+            .with_state(sent.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, rest).await.unwrap() });
+        let requester = stand_in_requester(&base_url);
+
+        let guard = guard();
+        let tracker = OrderTracker::default();
+        let pair = Pair::new("BTC", "USDT");
+        let order = || CreateOrderRequest {
+            pair: pair.clone(),
+            side: OrderSide::Buy,
+            order_type: OrderType::Limit,
+            time_in_force: None,
+            price: Some("59000.5".into()),
+            quantity: Some("0.01".into()),
+            amount: None,
+            client_order_id: None,
+            stp_mode: None,
+        };
+
+        let batch = CreateOrdersRequest {
+            orders: vec![order(), order(), order()],
+        };
+        let err = guard
+            .place_batch(&requester, &tracker, &batch)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<Rejection>(),
+            Some(&Rejection::TooManyOpenOrders { open: 2, max: 2 })
+        );
+        assert!(sent.lock().unwrap().is_empty());
+        assert_eq!(guard.open_orders(&tracker, &pair), 0);
+
+        let batch = CreateOrdersRequest {
+            orders: vec![order(), order()],
+        };
+        let responses = guard
+            .place_batch(&requester, &tracker, &batch)
+            .await
+            .unwrap();
+        assert_eq!(responses.len(), 2);
+        // tracker hasn't seen them yet
+        assert_eq!(guard.open_orders(&tracker, &pair), 2);
+        let err = guard
+            .place(&requester, &tracker, &order())
+            .await
+            .unwrap_err();
+        assert!(err.downcast_ref::<Rejection>().is_some());
+    }
+}
@@ -0,0 +1,973 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{Mutex, MutexGuard},
+    time::Duration,
+};
+
+use anyhow::Context as _;
+use bitsgap_shared::{
+    ApiRequester,
+    pair::{ExchangeSymbols, Pair},
+    utils::{Has, time::UnixMillis},
+};
+use tokio::sync::broadcast;
+
+use crate::{
+    context::PoloniexContext,
+    rest::orders::{
+        OrderRef, OrderResponse, OrderSide, OrderState, OrderType,
+        query::{Direction, OpenOrdersRequest, OrderHistoryRequest, OrderRequest},
+    },
+    units::{PxClientOrderId, PxOrderId, PxPrice, PxTimestamp, PxUnits},
+    ws::private::{
+        OrderStream, PrivateEvent,
+        orders::{OrderEventType, OrderMessage},
+    },
+};
+
+// changes which subscriber may lag behind before it misses them
+const CHANGES_CAPACITY: usize = 1024;
+// filled quantities are compared as floats
+const FILLED_EPSILON: f64 = 1e-12;
+/// Final orders are forgotten after that long without updates
+pub const FINAL_ORDERS_RETENTION: Duration = Duration::from_secs(60 * 60);
+// order history can be queried for that long at once
+const HISTORY_WINDOW: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+// and is kept for that long
+const HISTORY_RETENTION: Duration = Duration::from_secs(90 * 24 * 60 * 60);
+
+/// Status of tracked order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OrderStatus {
+    New,
+    PartiallyFilled,
+    Filled,
+    /// possibly partially filled before
+    Canceled,
+    Rejected,
+}
+
+impl OrderStatus {
+    /// None for pending cancel, which doesn't change status by itself
+    fn of(state: OrderState) -> Option<Self> {
+        match state {
+            OrderState::New => Some(Self::New),
+            OrderState::PartiallyFilled => Some(Self::PartiallyFilled),
+            OrderState::Filled => Some(Self::Filled),
+            OrderState::PendingCancel => None,
+            OrderState::PartiallyCanceled | OrderState::Canceled => Some(Self::Canceled),
+            OrderState::Failed => Some(Self::Rejected),
+        }
+    }
+
+    /// Order won't change anymore
+    pub fn is_final(&self) -> bool {
+        matches!(self, Self::Filled | Self::Canceled | Self::Rejected)
+    }
+
+    // status never goes back, final ones can't replace each other
+    fn step(&self) -> u8 {
+        match self {
+            Self::New => 0,
+            Self::PartiallyFilled => 1,
+            Self::Filled | Self::Canceled | Self::Rejected => 2,
+        }
+    }
+}
+
+/// Order as tracker knows it
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackedOrder {
+    pub id: PxOrderId,
+    /// empty if order was placed without one
+    pub client_order_id: PxClientOrderId,
+    pub pair: Pair,
+    pub side: OrderSide,
+    pub order_type: OrderType,
+    /// "0" for market orders
+    pub price: PxPrice,
+    /// base units ordered
+    pub quantity: PxUnits,
+    pub status: OrderStatus,
+    /// cancellation is requested, but isn't done yet
+    pub pending_cancel: bool,
+    /// base units filled
+    pub filled_quantity: PxUnits,
+    /// quote units filled
+    pub filled_amount: PxUnits,
+    pub create_time: PxTimestamp,
+    pub update_time: PxTimestamp,
+}
+
+impl TrackedOrder {
+    fn filled(&self) -> anyhow::Result<f64> {
+        self.filled_quantity
+            .parse()
+            .with_context(|| format!("parse filled quantity {:?}", self.filled_quantity))
+    }
+}
+
+/// Change of tracked order, sent to subscribers
+#[derive(Debug, Clone)]
+pub struct OrderChange {
+    /// none if order wasn't tracked before
+    pub previous: Option<OrderStatus>,
+    pub order: TrackedOrder,
+}
+
+/// Order after an event of stream or as REST API reports it
+#[derive(Debug)]
+pub struct OrderUpdate {
+    order: TrackedOrder,
+    state: OrderState,
+    event: Option<(OrderEventType, PxUnits)>,
+}
+
+impl OrderUpdate {
+    pub fn from_message<C: Has<ExchangeSymbols>>(
+        msg: &OrderMessage,
+        context: &C,
+    ) -> anyhow::Result<Self> {
+        let pair = context.give(ExchangeSymbols).to_pair(&msg.symbol)?.clone();
+        Ok(Self {
+            order: TrackedOrder {
+                id: msg.order_id.clone(),
+                client_order_id: msg.client_order_id.clone(),
+                pair,
+                side: msg.side,
+                order_type: msg.order_type,
+                price: msg.price.clone(),
+                quantity: msg.quantity.clone(),
+                status: OrderStatus::New,
+                pending_cancel: false,
+                filled_quantity: msg.filled_quantity.clone(),
+                filled_amount: msg.filled_amount.clone(),
+                create_time: msg.create_time,
+                update_time: msg.record_time,
+            },
+            state: msg.state,
+            event: Some((msg.event_type, msg.trade_qty.clone())),
+        })
+    }
+
+    pub fn from_response<C: Has<ExchangeSymbols>>(
+        response: &OrderResponse,
+        context: &C,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            order: TrackedOrder {
+                id: response.id.clone(),
+                client_order_id: response.client_order_id.clone(),
+                pair: response.pair(context)?.clone(),
+                side: response.side,
+                order_type: response.order_type,
+                price: response.price.clone(),
+                quantity: response.quantity.clone(),
+                status: OrderStatus::New,
+                pending_cancel: false,
+                filled_quantity: response.filled_quantity.clone(),
+                filled_amount: response.filled_amount.clone(),
+                create_time: response.create_time,
+                update_time: response.update_time,
+            },
+            state: response.state,
+            event: None,
+        })
+    }
+}
+
+/// State machine of every known order, without synchronization.
+/// Final orders are kept for `FINAL_ORDERS_RETENTION`, see `prune`.
+#[derive(Debug, Default)]
+pub struct Orders {
+    orders: BTreeMap<PxOrderId, TrackedOrder>,
+    /// the newest order of every client order id
+    client_ids: BTreeMap<PxClientOrderId, PxOrderId>,
+    /// orders whose events were missed, to be queried from REST API
+    missed: BTreeSet<PxOrderId>,
+    /// events of unknown orders may be missed, e.g. after reconnect
+    stale: bool,
+}
+
+impl Orders {
+    pub fn get(&self, id: &str) -> Option<&TrackedOrder> {
+        self.orders.get(id)
+    }
+
+    /// Newest order, if id was reused after previous order was done
+    pub fn by_client_id(&self, client_order_id: &str) -> Option<&TrackedOrder> {
+        self.orders.get(self.client_ids.get(client_order_id)?)
+    }
+
+    fn insert(&mut self, order: TrackedOrder) {
+        if !order.client_order_id.is_empty() {
+            let newest = self
+                .client_ids
+                .get(&order.client_order_id)
+                .and_then(|id| self.orders.get(id));
+            if newest.is_none_or(|newest| newest.create_time <= order.create_time) {
+                self.client_ids
+                    .insert(order.client_order_id.clone(), order.id.clone());
+            }
+        }
+        self.orders.insert(order.id.clone(), order);
+    }
+
+    /// Forgets final orders which weren't updated for `FINAL_ORDERS_RETENTION` before `now`.
+    /// Orders waiting for reconciliation are kept.
+    pub fn prune(&mut self, now: UnixMillis) {
+        let until = now.saturating_sub(FINAL_ORDERS_RETENTION);
+        let pruned: Vec<PxOrderId> = self
+            .orders
+            .values()
+            .filter(|order| {
+                order.status.is_final()
+                    && order.update_time < until
+                    && !self.missed.contains(&order.id)
+            })
+            .map(|order| order.id.clone())
+            .collect();
+        for id in pruned {
+            let Some(order) = self.orders.remove(&id) else {
+                continue;
+            };
+            if self.client_ids.get(&order.client_order_id) == Some(&id) {
+                self.client_ids.remove(&order.client_order_id);
+            }
+        }
+    }
+
+    /// Open orders of `pair`, or of every pair
+    pub fn open<'a>(&'a self, pair: Option<&'a Pair>) -> impl Iterator<Item = &'a TrackedOrder> {
+        self.orders.values().filter(move |order| {
+            !order.status.is_final() && pair.is_none_or(|pair| &order.pair == pair)
+        })
+    }
+
+    /// Events may be missed, so state has to be reconciled with REST API
+    pub fn needs_reconcile(&self) -> bool {
+        self.stale || !self.missed.is_empty()
+    }
+
+    pub fn mark_stale(&mut self) {
+        self.stale = true;
+    }
+
+    /// Applies update if it's newer than known state, returns change if anything changed
+    pub fn apply(&mut self, update: OrderUpdate) -> anyhow::Result<Option<OrderChange>> {
+        let OrderUpdate {
+            mut order,
+            state,
+            event,
+        } = update;
+        let filled = order.filled()?;
+        let status = OrderStatus::of(state);
+        order.pending_cancel = status.is_none();
+
+        let Some(current) = self.orders.get(&order.id) else {
+            if event.is_some_and(|(event_type, _)| event_type != OrderEventType::Place) {
+                log::warn!("Order {} is unknown, its placement was missed", order.id);
+                self.missed.insert(order.id.clone());
+            }
+            order.status = status.unwrap_or(if filled > FILLED_EPSILON {
+                OrderStatus::PartiallyFilled
+            } else {
+                OrderStatus::New
+            });
+            self.insert(order.clone());
+            return Ok(Some(OrderChange {
+                previous: None,
+                order,
+            }));
+        };
+
+        if current.status.is_final() || order.update_time < current.update_time {
+            return Ok(None);
+        }
+        let current_filled = current.filled()?;
+        if filled + FILLED_EPSILON < current_filled {
+            return Ok(None);
+        }
+        if let Some((OrderEventType::Trade, trade_qty)) = &event {
+            let trade_qty: f64 = trade_qty
+                .parse()
+                .with_context(|| format!("parse trade quantity {trade_qty:?}"))?;
+            if filled > current_filled + trade_qty + FILLED_EPSILON {
+                log::warn!("Order {} has fills which weren't received", order.id);
+                self.missed.insert(order.id.clone());
+            }
+        }
+        order.status = match status {
+            Some(status) => status,
+            None if filled > FILLED_EPSILON => current.status.max(OrderStatus::PartiallyFilled),
+            None => current.status,
+        };
+        if order.status.step() < current.status.step() {
+            return Ok(None);
+        }
+        let previous = current.status;
+        let changed = order.status != current.status
+            || order.pending_cancel != current.pending_cancel
+            || order.filled_quantity != current.filled_quantity;
+        self.insert(order.clone());
+        Ok(changed.then_some(OrderChange {
+            previous: Some(previous),
+            order,
+        }))
+    }
+}
+
+/// Consistent view of our orders, built from private stream and reconciled with REST API.
+/// Shared between tracker task and strategy code.
+pub struct OrderTracker {
+    orders: Mutex<Orders>,
+    changes: broadcast::Sender<OrderChange>,
+}
+
+impl Default for OrderTracker {
+    fn default() -> Self {
+        Self {
+            orders: Default::default(),
+            changes: broadcast::Sender::new(CHANGES_CAPACITY),
+        }
+    }
+}
+
+impl OrderTracker {
+    fn lock(&self) -> MutexGuard<'_, Orders> {
+        // state is consistent after every apply, so poisoning is harmless
+        self.orders.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    pub fn order(&self, id: &str) -> Option<TrackedOrder> {
+        self.lock().get(id).cloned()
+    }
+
+    pub fn order_by_client_id(&self, client_order_id: &str) -> Option<TrackedOrder> {
+        self.lock().by_client_id(client_order_id).cloned()
+    }
+
+    /// Open orders of `pair`, or of every pair
+    pub fn open_orders(&self, pair: Option<&Pair>) -> Vec<TrackedOrder> {
+        self.lock().open(pair).cloned().collect()
+    }
+
+    /// Changes of orders from now on. Receiver which lags more than 1024 changes gets `Lagged` error.
+    pub fn subscribe(&self) -> broadcast::Receiver<OrderChange> {
+        self.changes.subscribe()
+    }
+
+    /// Applies update and notifies subscribers
+    pub fn apply(&self, update: OrderUpdate) -> anyhow::Result<()> {
+        let change = self.lock().apply(update)?;
+        if let Some(change) = change {
+            // no subscribers is fine
+            let _ = self.changes.send(change);
+        }
+        Ok(())
+    }
+
+    /// Applies event of private stream, lost connection makes state stale
+    pub fn handle_event<C: Has<ExchangeSymbols>>(
+        &self,
+        event: PrivateEvent,
+        context: &C,
+    ) -> anyhow::Result<()> {
+        match event {
+            PrivateEvent::Order(msg) => {
+                self.apply(OrderUpdate::from_message(&msg, context)?)?;
+            }
+            PrivateEvent::Reconnected => self.lock().mark_stale(),
+            PrivateEvent::Error(message) => log::error!("Private WS error: {message}"),
+            PrivateEvent::Invalid { raw, error } => {
+                // the order of this event is unknown, so everything is reconciled
+                log::error!("Invalid private WS message {raw}: {error:#}");
+                self.lock().mark_stale();
+            }
+            PrivateEvent::Subscribed(_) | PrivateEvent::Pong => {}
+        }
+        Ok(())
+    }
+
+    /// Queries open orders from REST API, orders which were open or missed but aren't open anymore
+    /// are looked up in order history since the oldest of them was created.
+    /// Final orders without updates for `FINAL_ORDERS_RETENTION` are forgotten afterwards.
+    /// If it fails, state stays stale and missed orders are queried next time.
+    pub async fn reconcile(&self, requester: &ApiRequester<PoloniexContext>) -> anyhow::Result<()> {
+        let (query, missed) = {
+            let mut orders = self.lock();
+            // events since now will be received or found by the next reconciliation
+            orders.stale = false;
+            let open: Vec<_> = orders.open(None).map(|order| order.id.clone()).collect();
+            (open, std::mem::take(&mut orders.missed))
+        };
+        let res = self
+            .reconcile_orders(requester, query, missed.clone())
+            .await;
+        if res.is_err() {
+            // everything is queried again next time
+            let mut orders = self.lock();
+            orders.missed.extend(missed);
+            orders.mark_stale();
+        }
+        res
+    }
+
+    async fn reconcile_orders(
+        &self,
+        requester: &ApiRequester<PoloniexContext>,
+        query: Vec<PxOrderId>,
+        missed: BTreeSet<PxOrderId>,
+    ) -> anyhow::Result<()> {
+        let req = OpenOrdersRequest::<Pair> {
+            limit: Some(OpenOrdersRequest::<Pair>::MAX_LIMIT),
+            ..Default::default()
+        };
+        let responses = requester
+            .get_response(&req)
+            .await
+            .context("get open orders")?;
+        if responses.len() == usize::from(OpenOrdersRequest::<Pair>::MAX_LIMIT) {
+            log::warn!("Too many open orders, some of them aren't reconciled");
+        }
+        let listed: BTreeSet<_> = responses.iter().map(|res| res.id.clone()).collect();
+        for response in &responses {
+            self.apply(OrderUpdate::from_response(response, requester.context())?)?;
+        }
+        // orders which aren't open anymore, or were missed while listing
+        let mut rest: BTreeSet<_> = query
+            .into_iter()
+            .chain(missed)
+            .filter(|id| !listed.contains(id))
+            .collect();
+        let since = {
+            let orders = self.lock();
+            rest.iter()
+                .filter_map(|id| orders.get(id))
+                .map(|order| order.create_time)
+                .min()
+        };
+        if let Some(since) = since {
+            self.reconcile_history(requester, since, &mut rest).await?;
+        }
+        // e.g. order history doesn't have them yet
+        for id in rest {
+            let req = OrderRequest {
+                order: OrderRef::Id(id.clone()),
+            };
+            match requester.get_response(&req).await {
+                Ok(response) => {
+                    self.apply(OrderUpdate::from_response(&response, requester.context())?)?
+                }
+                Err(err) => {
+                    log::error!("Can't query order {id}: {err:#}");
+                    self.lock().missed.insert(id);
+                }
+            }
+        }
+        self.lock().prune(UnixMillis::now());
+        Ok(())
+    }
+
+    /// Applies orders of `ids` found in history since `since`, found ones are removed from `ids`
+    async fn reconcile_history(
+        &self,
+        requester: &ApiRequester<PoloniexContext>,
+        since: UnixMillis,
+        ids: &mut BTreeSet<PxOrderId>,
+    ) -> anyhow::Result<()> {
+        let limit = OrderHistoryRequest::<Pair>::MAX_LIMIT;
+        let now = UnixMillis::now();
+        let mut start_time = since.max(now.saturating_sub(HISTORY_RETENTION));
+        while !ids.is_empty() && start_time <= now {
+            let end_time = start_time.saturating_add(HISTORY_WINDOW);
+            // pages go from newer orders to older ones
+            let mut from = None;
+            loop {
+                let req = OrderHistoryRequest::<Pair> {
+                    direction: from.is_some().then_some(Direction::Pre),
+                    from: from.clone(),
+                    limit: Some(limit),
+                    start_time: Some(start_time),
+                    end_time: Some(end_time),
+                    ..Default::default()
+                };
+                let responses = requester
+                    .get_response(&req)
+                    .await
+                    .context("get order history")?;
+                for response in &responses {
+                    if ids.remove(&response.id) {
+                        self.apply(OrderUpdate::from_response(response, requester.context())?)?;
+                    }
+                }
+                let last = responses.last().map(|response| response.id.clone());
+                if responses.len() < usize::from(limit) || ids.is_empty() || last == from {
+                    break;
+                }
+                from = last;
+            }
+            start_time = end_time;
+        }
+        Ok(())
+    }
+
+    /// Applies events of stream until it fails.
+    /// Reconciles at start, after reconnects and missed events, and every `reconcile_interval`.
+    pub async fn run(
+        &self,
+        stream: &mut OrderStream<'_>,
+        requester: &ApiRequester<PoloniexContext>,
+        reconcile_interval: Duration,
+    ) -> anyhow::Result<()> {
+        self.lock().mark_stale();
+        let mut interval = tokio::time::interval(reconcile_interval);
+        loop {
+            tokio::select! {
+                res = stream.next() => {
+                    for event in res? {
+                        self.handle_event(event, requester.context())?;
+                    }
+                }
+                _ = interval.tick() => self.lock().mark_stale(),
+            }
+            if self.lock().needs_reconcile() {
+                if let Err(err) = self.reconcile(requester).await {
+                    // stays stale, so it's retried after the next event or tick
+                    log::error!("Can't reconcile orders: {err:#}");
+                    self.lock().mark_stale();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::{
+        Json, Router,
+        extract::{Path, State},
+        http::{StatusCode, Uri},
+        routing::get,
+    };
+    use bitsgap_shared::AuthMethod;
+    use futures::{SinkExt, StreamExt};
+    use serde_json::{Value, json};
+    use tokio::{net::TcpListener, time::timeout};
+    use tokio_tungstenite::tungstenite::Message;
+
+    use super::*;
+    use crate::tests::stand_in_requester;
+
+    fn message(
+        event_type: &str,
+        state: &str,
+        filled: &str,
+        trade_qty: &str,
+        ts: i64,
+    ) -> OrderMessage {
+        serde_json::from_value(serde_json::json!({
+            "symbol": "BTC_USDT",
+            "type": "LIMIT",
+            "quantity": "0.003",
+            "orderId": "32471407854219264",
+            "clientOrderId": "grid-1",
+            "eventType": event_type,
+            "side": "BUY",
+            "filledQuantity": filled,
+            "filledAmount": "0",
+            "state": state,
+            "orderAmount": "0",
+            "createTime": 1648708186922i64,
+            "price": "47112.1",
+            "tradeQty": trade_qty,
+            "tradePrice": "47112.1",
+            "tradeId": "0",
+            "ts": ts
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_order_state_machine() {
+        let context = PoloniexContext::init(false).unwrap();
+        let tracker = OrderTracker::default();
+        let mut changes = tracker.subscribe();
+        let handle = |msg: OrderMessage| {
+            tracker
+                .handle_event(PrivateEvent::Order(Box::new(msg)), &context)
+                .unwrap()
+        };
+
+        handle(message("place", "NEW", "0", "0", 1000));
+        let change = changes.try_recv().unwrap();
+        assert_eq!(change.previous, None);
+        assert_eq!(change.order.status, OrderStatus::New);
+
+        handle(message("trade", "PARTIALLY_FILLED", "0.001", "0.001", 2000));
+        let change = changes.try_recv().unwrap();
+        assert_eq!(change.previous, Some(OrderStatus::New));
+        assert_eq!(change.order.status, OrderStatus::PartiallyFilled);
+        assert!(!tracker.lock().needs_reconcile());
+
+        // late event doesn't move order back
+        handle(message("place", "NEW", "0", "0", 1500));
+        assert!(changes.try_recv().is_err());
+
+        // one trade is lost
+        handle(message(
+            "trade",
+            "PARTIALLY_FILLED",
+            "0.0025",
+            "0.0005",
+            3000,
+        ));
+        assert_eq!(changes.try_recv().unwrap().order.filled_quantity, "0.0025");
+        assert!(tracker.lock().needs_reconcile());
+
+        let pair = Pair::new("BTC", "USDT");
+        assert_eq!(tracker.open_orders(Some(&pair)).len(), 1);
+        assert!(
+            tracker
+                .open_orders(Some(&Pair::new("ETH", "USDT")))
+                .is_empty()
+        );
+
+        handle(message(
+            "canceled",
+            "PARTIALLY_CANCELED",
+            "0.0025",
+            "0",
+            4000,
+        ));
+        let change = changes.try_recv().unwrap();
+        assert_eq!(change.previous, Some(OrderStatus::PartiallyFilled));
+        assert_eq!(change.order.status, OrderStatus::Canceled);
+        assert!(tracker.open_orders(None).is_empty());
+
+        // nothing changes after final status
+        handle(message("trade", "FILLED", "0.003", "0.0005", 5000));
+        assert!(changes.try_recv().is_err());
+        assert_eq!(
+            tracker.order_by_client_id("grid-1").unwrap().status,
+            OrderStatus::Canceled
+        );
+    }
+
+    #[test]
+    fn test_order_reconciled_from_rest() {
+        let context = PoloniexContext::init(false).unwrap();
+        let mut orders = Orders::default();
+        // placement was missed, order is known from its trade
+        let update = OrderUpdate::from_message(
+            &message("trade", "PARTIALLY_FILLED", "0.001", "0.001", 2000),
+            &context,
+        )
+        .unwrap();
+        orders.apply(update).unwrap().unwrap();
+        assert!(orders.needs_reconcile());
+
+        let response: OrderResponse = serde_json::from_value(serde_json::json!({
+            "id": "32471407854219264",
+            "clientOrderId": "grid-1",
+            "symbol": "BTC_USDT",
+            "state": "FILLED",
+            "side": "BUY",
+            "type": "LIMIT",
+            "timeInForce": "GTC",
+            "quantity": "0.003",
+            "price": "47112.1",
+            "avgPrice": "47112.1",
+            "amount": "0",
+            "filledQuantity": "0.003",
+            "filledAmount": "141.3363",
+            "createTime": 1648708186922i64,
+            "updateTime": 6000
+        }))
+        .unwrap();
+        let change = orders
+            .apply(OrderUpdate::from_response(&response, &context).unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(change.previous, Some(OrderStatus::PartiallyFilled));
+        assert_eq!(change.order.status, OrderStatus::Filled);
+        assert_eq!(orders.open(None).count(), 0);
+    }
+
+    fn response(id: &str, client_order_id: &str, state: &str, filled: &str, created: i64) -> Value {
+        json!({
+            "id": id,
+            "clientOrderId": client_order_id,
+            "symbol": "BTC_USDT",
+            "state": state,
+            "side": "BUY",
+            "type": "LIMIT",
+            "timeInForce": "GTC",
+            "quantity": "0.003",
+            "price": "47112.1",
+            "avgPrice": "47112.1",
+            "amount": "0",
+            "filledQuantity": filled,
+            "filledAmount": "0",
+            "createTime": created,
+            "updateTime": created + 1000
+        })
+    }
+
+    fn update(response: Value) -> OrderUpdate {
+        let context = PoloniexContext::init(false).unwrap();
+        let response: OrderResponse = serde_json::from_value(response).unwrap();
+        OrderUpdate::from_response(&response, &context).unwrap()
+    }
+
+    #[test]
+    fn test_client_ids_and_pruning() {
+        let mut orders = Orders::default();
+        let now = UnixMillis::now().as_i64();
+        let old = now - 2 * FINAL_ORDERS_RETENTION.as_millis() as i64;
+        orders
+            .apply(update(response("1", "grid-1", "FILLED", "0.003", old)))
+            .unwrap();
+        // client id is reused by a newer order
+        orders
+            .apply(update(response("2", "grid-1", "NEW", "0", now)))
+            .unwrap();
+        orders
+            .apply(update(response("3", "grid-3", "CANCELED", "0", old)))
+            .unwrap();
+        orders
+            .apply(update(response("4", "grid-4", "FILLED", "0.003", now)))
+            .unwrap();
+        assert_eq!(orders.by_client_id("grid-1").unwrap().id, "2");
+        assert_eq!(orders.by_client_id("grid-3").unwrap().id, "3");
+
+        orders.prune(UnixMillis(now));
+        assert!(orders.get("1").is_none() && orders.get("3").is_none());
+        assert!(orders.by_client_id("grid-3").is_none());
+        // open and recently finished orders are kept
+        assert_eq!(orders.by_client_id("grid-1").unwrap().id, "2");
+        assert_eq!(orders.by_client_id("grid-4").unwrap().id, "4");
+    }
+
+    /// Orders endpoints like Poloniex would serve them, path and query of every request are logged
+    #[derive(Clone, Default)]
+    struct StandIn {
+        open: Arc<Mutex<Vec<Value>>>,
+        history: Arc<Mutex<Vec<Value>>>,
+        orders: Arc<Mutex<Vec<Value>>>,
+        requests: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl StandIn {
+        async fn serve(&self) -> ApiRequester<PoloniexContext> {
+            let log = |uri: &Uri, state: &StandIn| {
+                state.requests.lock().unwrap().push(uri.to_string());
+            };
+            let rest =
+                Router::new()
+                    .route(
+                        "/orders",
+                        get(move |State(state): State<StandIn>, uri: Uri| async move {
+                            log(&uri, &state);
+                            Json(state.open.lock().unwrap().clone())
+                        }),
+                    )
+                    .route(
+                        "/orders/history",
+                        get(move |State(state): State<StandIn>, uri: Uri| async move {
+                            log(&uri, &state);
+                            Json(state.history.lock().unwrap().clone())
+                        }),
+                    )
+                    .route(
+                        "/orders/{id}",
+                        get(
+                            move |State(state): State<StandIn>,
+                                  Path(id): Path<String>,
+                                  uri: Uri| async move {
+                                log(&uri, &state);
+                                let orders = state.orders.lock().unwrap();
+                                match orders.iter().find(|order| order["id"] == id.as_str()) {
+                                    Some(order) => Ok(Json(order.clone())),
+                                    None => Err(StatusCode::NOT_FOUND),
+                                }
+                            },
+                        ),
+                    )
+                    .with_state(self.clone());
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let base_url = format!("http://{}", listener.local_addr().unwrap());
+            tokio::spawn(async move { axum::serve(listener, rest).await.unwrap() });
+            stand_in_requester(&base_url)
+        }
+
+        fn requests(&self) -> Vec<String> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reconcile() {
+        let now = UnixMillis::now().as_i64();
+        let created = now - 60_000;
+        let tracker = OrderTracker::default();
+        tracker
+            .apply(update(response("1", "grid-1", "NEW", "0", created)))
+            .unwrap();
+        tracker
+            .apply(update(response("2", "grid-2", "NEW", "0", created + 1)))
+            .unwrap();
+        // placement of the third one was missed
+        let context = PoloniexContext::init(false).unwrap();
+        let mut msg = message("trade", "PARTIALLY_FILLED", "0.001", "0.001", created + 500);
+        msg.order_id = "3".into();
+        msg.client_order_id = "grid-3".into();
+        msg.create_time = UnixMillis(created + 2);
+        tracker
+            .handle_event(PrivateEvent::Order(Box::new(msg)), &context)
+            .unwrap();
+        assert!(tracker.lock().needs_reconcile());
+
+        // server without orders endpoints
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, Router::new()).await.unwrap() });
+        assert!(
+            tracker
+                .reconcile(&stand_in_requester(&base_url))
+                .await
+                .is_err()
+        );
+        assert!(tracker.lock().needs_reconcile());
+        assert!(tracker.lock().missed.contains("3"));
+
+        let stand_in = StandIn::default();
+        *stand_in.open.lock().unwrap() = vec![response(
+            "2",
+            "grid-2",
+            "PARTIALLY_FILLED",
+            "0.001",
+            created + 1,
+        )];
+        *stand_in.history.lock().unwrap() = vec![
+            response("9", "other", "FILLED", "0.003", created),
+            response("1", "grid-1", "FILLED", "0.003", created),
+        ];
+        // history doesn't have it yet
+        *stand_in.orders.lock().unwrap() =
+            vec![response("3", "grid-3", "CANCELED", "0.001", created + 2)];
+        let requester = stand_in.serve().await;
+        tracker.reconcile(&requester).await.unwrap();
+
+        let status = |id: &str| tracker.order(id).unwrap().status;
+        assert_eq!(status("1"), OrderStatus::Filled);
+        assert_eq!(status("2"), OrderStatus::PartiallyFilled);
+        assert_eq!(status("3"), OrderStatus::Canceled);
+        assert!(tracker.order("9").is_none());
+        assert!(!tracker.lock().needs_reconcile());
+        // history is queried since the oldest order, only the rest is queried one by one
+        let end = created + HISTORY_WINDOW.as_millis() as i64;
+        assert_eq!(
+            stand_in.requests(),
+            [
+                "/orders?limit=2000".to_string(),
+                format!("/orders/history?limit=1000&startTime={created}&endTime={end}"),
+                "/orders/3".to_string(),
+            ]
+        );
+    }
+
+    fn order_event(state: &str, filled: &str, ts: i64) -> Message {
+        let event = json!({"channel": "orders", "data": [{
+            "symbol": "BTC_USDT",
+            "type": "LIMIT",
+            "quantity": "0.003",
+            "orderId": "1",
+            "clientOrderId": "grid-1",
+            "eventType": if state == "NEW" { "place" } else { "trade" },
+            "side": "BUY",
+            "filledQuantity": filled,
+            "filledAmount": "0",
+            "state": state,
+            "orderAmount": "0",
+            "createTime": ts,
+            "price": "47112.1",
+            "tradeQty": filled,
+            "tradePrice": "47112.1",
+            "tradeId": "0",
+            "ts": ts
+        }]});
+        Message::Text(event.to_string().into())
+    }
+
+    /// Authenticates two connections like Poloniex would: order is placed on the first one,
+    /// which is dropped then, and filled on the second one
+    async fn private_stand_in(placed: i64) -> Uri {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("ws://{}/ws/private", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            for event in [
+                order_event("NEW", "0", placed),
+                order_event("FILLED", "0.003", placed + 1000),
+            ] {
+                let (tcp, _) = listener.accept().await.unwrap();
+                let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+                while let Some(Ok(msg)) = ws.next().await {
+                    let Message::Text(text) = msg else {
+                        continue;
+                    };
+                    let request: Value = serde_json::from_str(&text).unwrap();
+                    if request["channel"] == json!(["auth"]) {
+                        let reply =
+                            json!({"data": {"success": true, "ts": placed}, "channel": "auth"});
+                        ws.send(Message::Text(reply.to_string().into()))
+                            .await
+                            .unwrap();
+                    } else if request["channel"] == json!(["orders"]) {
+                        ws.send(event).await.unwrap();
+                        break;
+                    }
+                }
+            }
+            // keeps listener, so further reconnects just wait
+            std::future::pending::<()>().await;
+        });
+        uri.parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_run_survives_reconnect() {
+        let placed = UnixMillis::now().as_i64() - 60_000;
+        let stand_in = StandIn::default();
+        let requester = stand_in.serve().await;
+        let auth = AuthMethod::HmacSha256 {
+            api_key: "key".into(),
+            secret_key: "secret".into(),
+        };
+        let uri = private_stand_in(placed).await;
+        let mut stream = OrderStream::connect(&auth, uri).await.unwrap();
+        let tracker = OrderTracker::default();
+        let mut changes = tracker.subscribe();
+
+        // ticks are much more often than reconnection, which takes a second
+        let run = tracker.run(&mut stream, &requester, Duration::from_millis(50));
+        let filled = async {
+            loop {
+                let change = changes.recv().await.unwrap();
+                if change.order.status == OrderStatus::Filled {
+                    return change;
+                }
+            }
+        };
+        let change = tokio::select! {
+            res = run => panic!("tracker stopped: {res:?}"),
+            change = timeout(Duration::from_secs(10), filled) => change.unwrap(),
+        };
+        assert_eq!(change.previous, Some(OrderStatus::New));
+        assert_eq!(change.order.client_order_id, "grid-1");
+        // reconciled at start, on ticks and after reconnect
+        assert!(stand_in.requests().len() > 2);
+    }
+}
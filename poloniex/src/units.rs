@@ -1,7 +1,11 @@
+use bitsgap_shared::utils::time::UnixMillis;
+
 // Poloniex-specific basic units
 pub type PxSymbol = String;
-pub type PxTimestamp = u64;
+pub type PxTimestamp = UnixMillis;
 pub type PxPrice = String;
 pub type PxUnits = String;
 pub type PxCount = u32;
 pub type PxInterval = String;
+pub type PxOrderId = String;
+pub type PxClientOrderId = String;
@@ -1,10 +1,10 @@
 use anyhow::Context;
 use bitsgap_shared::{
     interval::{DatabaseIntervals, Interval},
-    records::kline::{Kline, VBS},
+    pair::ExchangeSymbols,
+    records::kline::{Kline, KlineStatus, VBS},
     utils::Has,
 };
-use let_clone::let_clone;
 
 use crate::units::{PxCount, PxPrice, PxSymbol, PxTimestamp, PxUnits};
 
@@ -39,7 +39,7 @@ pub struct CandlesMessage {
 
 impl CandlesMessage {
     // TODO: refactor into trait, something like `ToInternal`
-    pub fn kline<C: Has<DatabaseIntervals>>(
+    pub fn kline<C: Has<DatabaseIntervals> + Has<ExchangeSymbols>>(
         &self,
         interval: Interval,
         context: &C,
@@ -49,10 +49,13 @@ impl CandlesMessage {
             amount,
             high,
             quantity,
+            trade_count,
             low,
             start_time,
+            close_time,
             close,
             open,
+            record_time,
             ..
         } = self;
 
@@ -62,7 +65,11 @@ impl CandlesMessage {
             .context("convert interval to databse time frame format")?
             .into();
 
-        let_clone!(symbol: pair);
+        let pair = context
+            .give(ExchangeSymbols)
+            .to_pair(symbol)
+            .context("convert symbol to pair")?
+            .clone();
 
         let amount: f64 = amount.parse().context("parse amount")?;
         let quantity: f64 = quantity.parse().context("parse quantity")?;
@@ -77,15 +84,91 @@ impl CandlesMessage {
             sell_quote: 0.0,
         };
 
+        // WS doesn't send weighted average, but it's just quote volume over base volume
+        let vwap = if quantity > 0.0 {
+            amount / quantity
+        } else {
+            0.0
+        };
+
         Ok(Kline {
+            schema: Kline::SCHEMA_VERSION,
+            exchange: crate::EXCHANGE.into(),
             pair,
             time_frame,
             o: open.parse().context("parse open price")?,
             h: high.parse().context("parse open price")?,
             l: low.parse().context("parse open price")?,
             c: close.parse().context("parse open price")?,
-            utc_begin: (*start_time).try_into().context("convert start time")?,
+            utc_begin: *start_time,
+            utc_end: *close_time,
+            utc_record: *record_time,
+            status: KlineStatus::from_record_time(*record_time, *close_time),
+            trade_count: *trade_count,
+            vwap,
             volume_bs,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bitsgap_shared::{
+        interval::{Interval, IntervalKind},
+        records::kline::KlineV1,
+        utils::time::UnixMillis,
+    };
+
+    use super::*;
+    use crate::context::PoloniexContext;
+
+    #[test]
+    fn test_candles_kline() {
+        let context = PoloniexContext::init(false).unwrap();
+        let msg = CandlesMessage {
+            symbol: "BTC_USDT".into(),
+            amount: "200".into(),
+            high: "110".into(),
+            quantity: "2".into(),
+            trade_count: 3,
+            low: "90".into(),
+            close_time: UnixMillis(1648057199999),
+            start_time: UnixMillis(1648057140000),
+            close: "95".into(),
+            open: "105".into(),
+            record_time: UnixMillis(1648057141081),
+        };
+        let kline = msg
+            .kline(
+                Interval {
+                    kind: IntervalKind::Minute,
+                    value: 1,
+                },
+                &context,
+            )
+            .unwrap();
+        assert_eq!(kline.status, KlineStatus::InProgress);
+        assert_eq!(kline.trade_count, 3);
+        assert_eq!(kline.vwap, 100.0);
+
+        let legacy = serde_json::to_value(KlineV1::from(&kline)).unwrap();
+        assert_eq!(
+            legacy,
+            serde_json::json!({
+                "pair": "BTC/USDT",
+                "time_frame": "1m",
+                "o": 105.0,
+                "h": 110.0,
+                "l": 90.0,
+                "c": 95.0,
+                "utc_begin": 1648057140000i64,
+                "volume_bs": {
+                    "buy_base": 2.0,
+                    "sell_base": 0.0,
+                    "buy_quote": 200.0,
+                    "sell_quote": 0.0,
+                },
+            })
+        );
+    }
+}
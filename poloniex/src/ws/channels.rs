@@ -1,6 +1,81 @@
-use bitsgap_shared::interval::Interval;
+use std::sync::LazyLock;
 
-pub enum Channel {
-    Candles(Interval),
-    Trades,
+use bitsgap_shared::{
+    channel::{self, ChannelNames},
+    interval::{Interval, IntervalsDict},
+};
+
+use super::{
+    candles::CandlesMessage,
+    intervals::{WsCandlesChannels, all_ws_candles_channels},
+    trades::TradesMessage,
+};
+
+// names of all candles channels, to convert channel without context
+static CANDLES_CHANNELS: LazyLock<IntervalsDict> =
+    LazyLock::new(|| all_ws_candles_channels().expect("valid candles channels"));
+
+/// Names of Poloniex channels, like "candles_minute_1" or "trades"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PoloniexChannels;
+
+impl ChannelNames for PoloniexChannels {
+    type Supported = WsCandlesChannels;
+
+    const EXCHANGE: &'static str = crate::EXCHANGE;
+    const TRADES: &'static str = "trades";
+    const SUPPORTED: WsCandlesChannels = WsCandlesChannels;
+
+    fn klines() -> &'static IntervalsDict {
+        &CANDLES_CHANNELS
+    }
+}
+
+/// Public channel, (de)serialized as Poloniex channel name. Klines are candles of Poloniex.
+pub type Channel = channel::Channel<PoloniexChannels>;
+
+/// Event of stream, typed according to its channel
+#[derive(Debug)]
+pub enum StreamEvent {
+    Candles(Interval, CandlesMessage),
+    Trades(TradesMessage),
+}
+
+impl StreamEvent {
+    pub fn symbol(&self) -> &str {
+        match self {
+            Self::Candles(_, msg) => &msg.symbol,
+            Self::Trades(msg) => &msg.symbol,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitsgap_shared::interval::IntervalKind;
+
+    use super::*;
+    use crate::context::PoloniexContext;
+
+    #[test]
+    fn test_channel_names() {
+        let minute = Channel::Klines(Interval {
+            kind: IntervalKind::Minute,
+            value: 1,
+        });
+        for (channel, name) in [(minute, "candles_minute_1"), (Channel::Trades, "trades")] {
+            assert_eq!(channel.to_string(), name);
+            assert_eq!(name.parse::<Channel>().unwrap(), channel);
+            assert_eq!(
+                serde_json::to_string(&channel).unwrap(),
+                format!("{name:?}")
+            );
+        }
+        assert!("candles_minute_2".parse::<Channel>().is_err());
+
+        let context = PoloniexContext::init(true).unwrap();
+        let supported: Vec<_> = Channel::supported(&context).collect();
+        assert!(supported.contains(&minute) && supported.contains(&Channel::Trades));
+        assert!(supported.len() < CANDLES_CHANNELS.iter().count() + 1);
+    }
 }
@@ -0,0 +1,467 @@
+use std::collections::VecDeque;
+
+use anyhow::Context as _;
+use bitsgap_shared::{
+    exchange::{self, RequestId},
+    journal::JournalingCodec,
+    ws::{
+        Parsed, SimpleJsonCodec, Unparsed, Uri, WsClient,
+        market_data::{self, MarketProtocol},
+    },
+};
+
+use super::{
+    channels::{Channel, StreamEvent},
+    protocol::{
+        ClientMsg, ServerError, ServerErrorKind, ServerEvent, ServerMsg, ServerStream, StreamData,
+    },
+    public_ws_with_codec,
+};
+use crate::context::PoloniexContext;
+
+/// Normalized event of public market data
+pub type MarketEvent = exchange::MarketEvent<Channel>;
+
+/// Events of one server message
+pub type MarketUpdate = exchange::MarketUpdate<Channel>;
+
+/// Subscribe and unsubscribe messages waiting for acknowledgements.
+/// Server acknowledges every channel of message in order of sending, but without id, so ids are kept here.
+#[derive(Debug, Default)]
+pub struct Requests {
+    last_id: RequestId,
+    pending: VecDeque<PendingRequest>,
+}
+
+#[derive(Debug)]
+struct PendingRequest {
+    id: RequestId,
+    subscribe: bool,
+    channels: Vec<Channel>,
+}
+
+impl Requests {
+    pub fn start(&mut self, subscribe: bool, channels: Vec<Channel>) -> RequestId {
+        self.last_id += 1;
+        self.pending.push_back(PendingRequest {
+            id: self.last_id,
+            subscribe,
+            channels,
+        });
+        self.last_id
+    }
+
+    /// Id of the oldest message which waits for acknowledgement of channel
+    fn acknowledge(&mut self, subscribe: bool, channel: Channel) -> Option<RequestId> {
+        let index = self.pending.iter().position(|request| {
+            request.subscribe == subscribe && request.channels.contains(&channel)
+        });
+        let Some(index) = index else {
+            // e.g. request of previous connection
+            log::debug!("WS server acknowledged {channel} without request");
+            return None;
+        };
+        let request = &mut self.pending[index];
+        let id = request.id;
+        request.channels.retain(|pending| *pending != channel);
+        if request.channels.is_empty() {
+            self.pending.remove(index);
+        }
+        Some(id)
+    }
+}
+
+/// Normalizes server message, stream message gives an event per its event.
+/// Acknowledgements are matched with requests.
+pub fn market_events(
+    msg: Parsed<ServerMsg>,
+    context: &PoloniexContext,
+    requests: &mut Requests,
+) -> Vec<MarketEvent> {
+    let msg = match msg {
+        Parsed::Msg(msg) => msg,
+        Parsed::Unparsed(Unparsed { raw, error }) => {
+            return vec![MarketEvent::Invalid {
+                raw,
+                channel: None,
+                error,
+            }];
+        }
+    };
+    let event = match msg {
+        ServerMsg::Stream(stream) => return stream_events(&stream, context),
+        ServerMsg::Event(ServerEvent::Subscribe { channel }) => MarketEvent::Subscribed {
+            channel,
+            request: requests.acknowledge(true, channel),
+        },
+        ServerMsg::Event(ServerEvent::Unsubscribe { channel }) => MarketEvent::Unsubscribed {
+            channel,
+            request: requests.acknowledge(false, channel),
+        },
+        ServerMsg::Event(ServerEvent::UnsubscribeAll { .. }) => MarketEvent::UnsubscribedAll,
+        ServerMsg::Event(ServerEvent::Pong) => MarketEvent::Pong,
+        ServerMsg::Event(ServerEvent::Error { message }) => MarketEvent::Error {
+            // resubscription may race with server's own view
+            harmless: matches!(
+                message,
+                ServerError::Kind(
+                    ServerErrorKind::AlreadySubscribed | ServerErrorKind::NotSubscribed
+                )
+            ),
+            message: format!("{message:?}"),
+        },
+        ServerMsg::Subscriptions { subscriptions } => {
+            let channels = subscriptions
+                .iter()
+                .filter_map(|name| match name.parse() {
+                    Ok(channel) => Some(channel),
+                    Err(err) => {
+                        log::warn!("WS server has subscription of unknown channel: {err:#}");
+                        None
+                    }
+                })
+                .collect();
+            MarketEvent::Subscriptions(channels)
+        }
+    };
+    vec![event]
+}
+
+fn stream_events(stream: &ServerStream, context: &PoloniexContext) -> Vec<MarketEvent> {
+    let channel = stream.channel().ok();
+    stream
+        .events()
+        .map(|(value, res)| {
+            let res = res.and_then(|event| match event {
+                StreamEvent::Candles(interval, msg) => msg
+                    .kline(interval, context)
+                    .context("convert candle to kline")
+                    .map(|kline| MarketEvent::Kline {
+                        channel: Channel::Klines(interval),
+                        interval,
+                        kline,
+                    }),
+                StreamEvent::Trades(msg) => msg
+                    .recent_trade(context)
+                    .context("convert trade to recent trade")
+                    .map(|recent_trade| MarketEvent::Trade {
+                        channel: Channel::Trades,
+                        recent_trade,
+                    }),
+            });
+            res.unwrap_or_else(|error| MarketEvent::Invalid {
+                raw: match channel {
+                    Some(_) => value.to_string(),
+                    // whole message of single event, so it can be reprocessed once channel is known
+                    None => {
+                        serde_json::json!({"channel": stream.channel, "data": [value]}).to_string()
+                    }
+                },
+                channel,
+                error,
+            })
+        })
+        .collect()
+}
+
+/// Normalizes raw message, or single event of stream message if channel is known
+pub fn parse_events(
+    raw: &str,
+    channel: Option<Channel>,
+    context: &PoloniexContext,
+) -> Vec<MarketEvent> {
+    let res = match channel {
+        Some(channel) => serde_json::from_str(raw).map(|value| {
+            ServerStream {
+                data: StreamData(vec![value]),
+                channel: channel.to_string(),
+            }
+            .into_msg()
+        }),
+        None => serde_json::from_str(raw),
+    };
+    match res {
+        // acknowledgements without requests are still reported
+        Ok(msg) => market_events(Parsed::Msg(msg), context, &mut Requests::default()),
+        Err(error) => vec![MarketEvent::Invalid {
+            raw: raw.into(),
+            channel,
+            error: anyhow::Error::new(error).context("deserialize JSON message"),
+        }],
+    }
+}
+
+/// Public server of Poloniex, keeps subscribe and unsubscribe messages until they're acknowledged
+#[derive(Debug, Default)]
+pub struct PublicProtocol {
+    requests: Requests,
+}
+
+impl MarketProtocol for PublicProtocol {
+    type Channel = Channel;
+    type Context = PoloniexContext;
+    type ClientMsg = ClientMsg;
+    type ServerMsg = ServerMsg;
+
+    const JOURNAL_PREFIX: &'static str = "poloniex-public";
+
+    async fn connect(
+        uri: Uri,
+        codec: JournalingCodec<SimpleJsonCodec>,
+    ) -> anyhow::Result<WsClient<ClientMsg, Parsed<ServerMsg>>> {
+        public_ws_with_codec(uri, codec)
+            .await
+            .context("connect to poloniex public WebSocket server")
+    }
+
+    fn request(
+        &mut self,
+        subscribe: bool,
+        channels: &[Channel],
+        symbols: Vec<String>,
+    ) -> anyhow::Result<(RequestId, ClientMsg)> {
+        let msg = if subscribe {
+            ClientMsg::subscribe(channels.iter().copied(), symbols)
+        } else {
+            ClientMsg::unsubscribe(channels.iter().copied(), symbols)
+        };
+        Ok((self.requests.start(subscribe, channels.to_vec()), msg))
+    }
+
+    fn list_subscriptions(&mut self) -> ClientMsg {
+        ClientMsg::ListSubscriptions
+    }
+
+    fn unsubscribe_all(&self) -> Option<ClientMsg> {
+        Some(ClientMsg::UnsubscribeAll)
+    }
+
+    fn events(&mut self, msg: Parsed<ServerMsg>, context: &PoloniexContext) -> Vec<MarketEvent> {
+        market_events(msg, context, &mut self.requests)
+    }
+
+    fn forget_requests(&mut self) {
+        self.requests.pending.clear();
+    }
+}
+
+/// Public market data of Poloniex, `uri` of `connect` is usually `ws::PUBLIC_WS_URI`
+pub type MarketDataStream<'a> = market_data::MarketDataStream<'a, PublicProtocol>;
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bitsgap_shared::{exchange::MarketStream, pair::Pair};
+    use futures::{SinkExt, StreamExt};
+    use serde_json::{Value, json};
+    use tokio::{net::TcpListener, sync::mpsc, time::timeout};
+    use tokio_tungstenite::tungstenite::Message;
+
+    use super::*;
+
+    #[test]
+    fn test_market_events() {
+        let context = PoloniexContext::init(true).unwrap();
+        let raw = r#"
+        {
+            "channel": "trades",
+            "data": [{
+                "symbol": "BTC_USDT",
+                "amount": "70",
+                "takerSide": "buy",
+                "quantity": "4",
+                "createTime": 1648059516810,
+                "price": "17.5",
+                "id": "1",
+                "ts": 1648059516832
+            }, {
+                "symbol": "BTC_USDT",
+                "renamedField": "0"
+            }]
+        }
+        "#;
+        let msg = serde_json::from_str(raw).unwrap();
+        let mut requests = Requests::default();
+        let events = market_events(Parsed::Msg(msg), &context, &mut requests);
+        assert_eq!(events.len(), 2);
+        match &events[0] {
+            MarketEvent::Trade { recent_trade, .. } => {
+                assert_eq!(recent_trade.pair, Pair::new("BTC", "USDT"))
+            }
+            other => panic!("unexpected event {other:?}"),
+        }
+        assert!(matches!(
+            &events[1],
+            MarketEvent::Invalid {
+                channel: Some(Channel::Trades),
+                ..
+            }
+        ));
+
+        // acknowledgements of the same channel come in order of requests
+        let candles: Channel = "candles_minute_1".parse().unwrap();
+        let first = requests.start(true, vec![Channel::Trades, candles]);
+        let second = requests.start(true, vec![Channel::Trades]);
+        let subscribed = |requests: &mut Requests, channel: &str| {
+            let raw = format!(r#"{{"event": "subscribe", "channel": "{channel}"}}"#);
+            let msg = serde_json::from_str(&raw).unwrap();
+            market_events(Parsed::Msg(msg), &context, requests)
+        };
+        for (channel, request) in [
+            ("trades", Some(first)),
+            ("trades", Some(second)),
+            ("candles_minute_1", Some(first)),
+            ("trades", None),
+        ] {
+            let events = subscribed(&mut requests, channel);
+            assert!(
+                matches!(
+                    events[..],
+                    [MarketEvent::Subscribed { channel: acknowledged, request: acknowledged_request }]
+                        if acknowledged.to_string() == channel && acknowledged_request == request
+                ),
+                "{events:?}"
+            );
+        }
+        assert!(requests.pending.is_empty());
+
+        // unknown channel fails its events one by one, not the whole message
+        let raw =
+            r#"{"channel": "book_lv2", "data": [{"symbol": "BTC_USDT"}, {"symbol": "ETH_USDT"}]}"#;
+        let events = parse_events(raw, None, &context);
+        assert_eq!(events.len(), 2);
+        match &events[1] {
+            MarketEvent::Invalid {
+                raw, channel: None, ..
+            } => assert_eq!(
+                raw,
+                r#"{"channel":"book_lv2","data":[{"symbol":"ETH_USDT"}]}"#
+            ),
+            other => panic!("unexpected event {other:?}"),
+        }
+        let events = parse_events(
+            r#"{"subscriptions": ["book_lv2", "trades"]}"#,
+            None,
+            &context,
+        );
+        assert!(matches!(
+            &events[..],
+            [MarketEvent::Subscriptions(channels)] if channels == &[Channel::Trades]
+        ));
+
+        // single event, like in dead letters
+        let raw = r#"{"symbol": "BTC_USDT", "renamedField": "0"}"#;
+        let events = parse_events(raw, Some(Channel::Trades), &context);
+        assert!(matches!(
+            events[..],
+            [MarketEvent::Invalid {
+                channel: Some(Channel::Trades),
+                ..
+            }]
+        ));
+    }
+
+    /// Accepts two connections like Poloniex would: the first one is dropped once subscription is acknowledged,
+    /// the second one sends a trade after acknowledgement. Subscribe messages are passed to the test.
+    async fn stand_in_server() -> (Uri, mpsc::UnboundedReceiver<Value>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("ws://{}/ws/public", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            for connection in 0..2 {
+                let (tcp, _) = listener.accept().await.unwrap();
+                let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+                while let Some(Ok(msg)) = ws.next().await {
+                    let Message::Text(text) = msg else {
+                        continue;
+                    };
+                    let request: Value = serde_json::from_str(&text).unwrap();
+                    if request["event"] != "subscribe" {
+                        continue;
+                    }
+                    for channel in request["channel"].as_array().unwrap() {
+                        let ack = json!({"event": "subscribe", "channel": channel});
+                        ws.send(Message::Text(ack.to_string().into()))
+                            .await
+                            .unwrap();
+                    }
+                    tx.send(request).unwrap();
+                    if connection == 0 {
+                        break;
+                    }
+                    let trade = json!({"channel": "trades", "data": [{
+                        "symbol": "BTC_USDT",
+                        "amount": "70",
+                        "takerSide": "buy",
+                        "quantity": "4",
+                        "createTime": 1648059516810i64,
+                        "price": "17.5",
+                        "id": "1",
+                        "ts": 1648059516832i64
+                    }]});
+                    ws.send(Message::Text(trade.to_string().into()))
+                        .await
+                        .unwrap();
+                }
+            }
+        });
+        (uri.parse().unwrap(), rx)
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_survives_cancelled_next() {
+        let (uri, mut subscribes) = stand_in_server().await;
+        let context = PoloniexContext::init(true).unwrap();
+        let mut stream = MarketDataStream::connect(&context, uri, None)
+            .await
+            .unwrap();
+        let seconds = Duration::from_secs(5);
+
+        let request = stream
+            .subscribe(&[Channel::Trades], &[Pair::new("BTC", "USDT")])
+            .await
+            .unwrap();
+        let update = timeout(seconds, stream.next()).await.unwrap().unwrap();
+        assert!(matches!(
+            update.events[..],
+            [MarketEvent::Subscribed {
+                channel: Channel::Trades,
+                request: Some(acknowledged),
+            }] if acknowledged == request
+        ));
+
+        // connection is lost, reconnection waits for its delay of a second and is cancelled meanwhile
+        assert!(
+            timeout(Duration::from_millis(500), stream.next())
+                .await
+                .is_err()
+        );
+        assert!(!stream.is_connected());
+        let update = timeout(seconds, stream.next()).await.unwrap().unwrap();
+        assert!(matches!(update.events[..], [MarketEvent::Reconnected]));
+
+        let mut events = vec![];
+        while events.len() < 2 {
+            let update = timeout(seconds, stream.next()).await.unwrap().unwrap();
+            events.extend(update.events);
+        }
+        assert!(matches!(
+            events[..],
+            [
+                MarketEvent::Subscribed {
+                    channel: Channel::Trades,
+                    request: Some(_),
+                },
+                MarketEvent::Trade { .. },
+            ]
+        ));
+        let subscribed = subscribes.recv().await.unwrap();
+        assert_eq!(
+            subscribed,
+            json!({"event": "subscribe", "channel": ["trades"], "symbols": ["BTC_USDT"]})
+        );
+        assert_eq!(subscribes.recv().await.unwrap(), subscribed);
+        stream.close().await;
+    }
+}
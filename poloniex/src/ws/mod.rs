@@ -1,26 +1,70 @@
 use std::time::Duration;
 
 use anyhow::Context as _;
-use bitsgap_shared::ws::{Message, SimpleJsonCodec, WsClient, WsConfig};
-use protocol::{ClientMsg, ServerMsg};
+use bitsgap_shared::{
+    utils::Strict,
+    ws::{CodecIn, CodecOut, Message, SimpleJsonCodec, Uri, WsClient, WsConfig},
+};
+use private::protocol::PrivateClientMsg;
+use protocol::ClientMsg;
 
 pub mod candles;
 pub mod channels;
 pub mod intervals;
+pub mod market_data;
+pub mod private;
 pub mod protocol;
 pub mod trades;
 
-pub async fn public_ws() -> anyhow::Result<WsClient<ClientMsg, ServerMsg>> {
+/// `RX` is usually `ServerMsg`, or `Parsed<ServerMsg>` to receive unparsable messages too
+pub async fn public_ws<RX: Strict>() -> anyhow::Result<WsClient<ClientMsg, RX>>
+where
+    SimpleJsonCodec: CodecIn<RX>,
+{
+    let uri = PUBLIC_WS_URI.parse().context("parse uri")?;
+    public_ws_with_codec(uri, SimpleJsonCodec).await
+}
+
+// TODO: move to config
+pub const PUBLIC_WS_URI: &str = "wss://ws.poloniex.com/ws/public";
+
+/// Same as `public_ws`, but with custom server and codec, e.g. `JournalingCodec`
+pub async fn public_ws_with_codec<C, RX: Strict>(
+    uri: Uri,
+    codec: C,
+) -> anyhow::Result<WsClient<ClientMsg, RX>>
+where
+    C: Strict + CodecOut<ClientMsg> + CodecIn<RX>,
+{
     let ping = serde_json::to_string(&ClientMsg::Ping).context("ping message to json")?;
+    start_ws(uri, ping, codec).await
+}
+
+/// Private server, `uri` is usually `PRIVATE_WS_URI`. Connection has to be authenticated first.
+pub async fn private_ws<RX: Strict>(uri: Uri) -> anyhow::Result<WsClient<PrivateClientMsg, RX>>
+where
+    SimpleJsonCodec: CodecIn<RX>,
+{
+    let ping = serde_json::to_string(&PrivateClientMsg::Ping).context("ping message to json")?;
+    start_ws(uri, ping, SimpleJsonCodec).await
+}
+
+pub const PRIVATE_WS_URI: &str = "wss://ws.poloniex.com/ws/private";
+
+async fn start_ws<C, TX: Strict, RX: Strict>(
+    uri: Uri,
+    ping: String,
+    codec: C,
+) -> anyhow::Result<WsClient<TX, RX>>
+where
+    C: Strict + CodecOut<TX> + CodecIn<RX>,
+{
     let config = WsConfig {
         ping: Message::Text(ping.into()),
         // The WebSockets server expects a message or a ping every 30 seconds
         ping_interval: Duration::from_secs(20),
-        // TODO: move to config
-        uri: "wss://ws.poloniex.com/ws/public"
-            .parse()
-            .context("parse uri")?,
-        codec: SimpleJsonCodec,
+        uri,
+        codec,
     };
     config.start().await
 }
@@ -31,39 +75,34 @@ mod tests {
 
     use bitsgap_shared::interval::{Interval, IntervalKind};
     use candles::CandlesMessage;
-    use protocol::{ServerEvent, ServerStream};
+    use channels::Channel;
+    use protocol::{ServerEvent, ServerMsg, ServerStream};
     use trades::TradesMessage;
 
     use super::*;
     use crate::{context::PoloniexContext, tests::init_logger};
 
     async fn test_ws_public_channel<T: serde::de::DeserializeOwned + fmt::Debug>(
-        ch: &str,
+        ch: Channel,
         num: usize,
     ) -> Vec<T> {
         init_logger();
 
-        let mut client = public_ws().await.unwrap();
+        let mut client = public_ws::<ServerMsg>().await.unwrap();
         let seconds = Duration::from_secs(1);
         assert!(client.try_recv().unwrap().is_none());
 
         client
-            .send(ClientMsg::Subscribe {
-                channel: vec![ch.into()],
-                symbols: vec!["BTC_USDT".into()],
-            })
+            .send(ClientMsg::subscribe([ch], ["BTC_USDT"]))
             .await
             .unwrap();
         let msg = client.recv_timeout(seconds).await.unwrap().unwrap();
-        assert_eq!(
-            msg,
-            ServerEvent::Subscribe { channel: ch.into() }.into_msg()
-        );
+        assert_eq!(msg, ServerEvent::Subscribe { channel: ch }.into_msg());
         let mut messages = vec![];
         for _ in 0..num {
             let msg = client.recv().await.unwrap();
             match msg {
-                ServerMsg::Stream(ServerStream { data, channel }) if channel == ch => {
+                ServerMsg::Stream(ServerStream { data, channel }) if channel == ch.to_string() => {
                     assert_eq!(data.0.len(), 1);
                     println!("{data:?}");
                     let msg: T = data.into_events().next().unwrap().unwrap();
@@ -78,29 +117,24 @@ mod tests {
 
     #[tokio::test]
     async fn test_public_ws_candles() {
-        let ch = "candles_minute_1";
+        let interval = Interval {
+            kind: IntervalKind::Minute,
+            value: 1,
+        };
         let context = PoloniexContext::init(false).unwrap();
-        let messages = test_ws_public_channel::<CandlesMessage>(ch, 3).await;
+        let messages = test_ws_public_channel::<CandlesMessage>(Channel::Klines(interval), 3).await;
         for msg in messages {
-            let kline = msg
-                .kline(
-                    Interval {
-                        kind: IntervalKind::Minute,
-                        value: 1,
-                    },
-                    &context,
-                )
-                .unwrap();
+            let kline = msg.kline(interval, &context).unwrap();
             println!("{kline:?}");
         }
     }
 
     #[tokio::test]
     async fn test_public_ws_trades() {
-        let ch = "trades";
-        let messages = test_ws_public_channel::<TradesMessage>(ch, 3).await;
+        let context = PoloniexContext::init(false).unwrap();
+        let messages = test_ws_public_channel::<TradesMessage>(Channel::Trades, 3).await;
         for msg in messages {
-            let recent_trade = msg.recent_trade();
+            let recent_trade = msg.recent_trade(&context).unwrap();
             println!("{recent_trade:?}");
         }
     }
@@ -0,0 +1,271 @@
+//! Private WebSocket server: orders of our account, connection is authenticated with API keys
+
+use std::time::Duration;
+
+use anyhow::{Context as _, bail};
+use bitsgap_shared::{
+    AuthMethod,
+    ws::{
+        Parsed, Unparsed, Uri, WsClient,
+        reconnecting::{Connector, Received, ReconnectingClient},
+    },
+};
+use orders::OrderMessage;
+use protocol::{AuthData, PrivateChannel, PrivateClientMsg, PrivateServerEvent, PrivateServerMsg};
+use serde::Deserialize as _;
+
+use super::private_ws;
+
+pub mod orders;
+pub mod protocol;
+
+const AUTH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Event of private stream
+#[derive(Debug)]
+pub enum PrivateEvent {
+    Order(Box<OrderMessage>),
+    Subscribed(PrivateChannel),
+    Pong,
+    Error(String),
+    /// message, or single event of stream, which couldn't be parsed
+    Invalid {
+        raw: String,
+        error: anyhow::Error,
+    },
+    /// connection was lost and is established again, events in between are lost
+    Reconnected,
+}
+
+/// Normalizes server message, stream message gives an event per its event
+pub fn private_events(msg: Parsed<PrivateServerMsg>) -> Vec<PrivateEvent> {
+    let msg = match msg {
+        Parsed::Msg(msg) => msg,
+        Parsed::Unparsed(Unparsed { raw, error }) => {
+            return vec![PrivateEvent::Invalid { raw, error }];
+        }
+    };
+    let event = match msg {
+        PrivateServerMsg::Stream {
+            channel: PrivateChannel::Orders,
+            data,
+        } => {
+            return data
+                .0
+                .iter()
+                .map(|value| match OrderMessage::deserialize(value) {
+                    Ok(msg) => PrivateEvent::Order(Box::new(msg)),
+                    Err(error) => PrivateEvent::Invalid {
+                        raw: value.to_string(),
+                        error: anyhow::Error::new(error).context("parse order event"),
+                    },
+                })
+                .collect();
+        }
+        PrivateServerMsg::Stream { channel, data } => PrivateEvent::Invalid {
+            raw: format!("{data:?}"),
+            error: anyhow::anyhow!("unexpected stream of {channel:?} channel"),
+        },
+        PrivateServerMsg::Auth { data, .. } => {
+            PrivateEvent::Error(format!("unexpected authentication reply: {data:?}"))
+        }
+        PrivateServerMsg::Event(PrivateServerEvent::Subscribe { channel }) => {
+            PrivateEvent::Subscribed(channel)
+        }
+        PrivateServerMsg::Event(PrivateServerEvent::Pong) => PrivateEvent::Pong,
+        PrivateServerMsg::Event(PrivateServerEvent::Error { message }) => {
+            PrivateEvent::Error(format!("{message:?}"))
+        }
+    };
+    vec![event]
+}
+
+/// Connects, authenticates and subscribes to orders of every symbol
+struct PrivateConnector<'a> {
+    auth: &'a AuthMethod,
+    uri: Uri,
+}
+
+impl Connector for PrivateConnector<'_> {
+    type Tx = PrivateClientMsg;
+    type Rx = Parsed<PrivateServerMsg>;
+
+    async fn connect(
+        &self,
+    ) -> anyhow::Result<WsClient<PrivateClientMsg, Parsed<PrivateServerMsg>>> {
+        connect_client(self.auth, &self.uri).await
+    }
+}
+
+/// Events of our orders, which reconnects and authenticates again by itself
+pub struct OrderStream<'a> {
+    client: ReconnectingClient<PrivateConnector<'a>>,
+}
+
+impl<'a> OrderStream<'a> {
+    /// Connects, authenticates and subscribes to orders of every symbol
+    pub async fn connect(auth: &'a AuthMethod, uri: Uri) -> anyhow::Result<Self> {
+        let client = ReconnectingClient::connect(PrivateConnector { auth, uri }).await?;
+        Ok(Self { client })
+    }
+
+    /// Waits for the next server message. Cancel safe: lost connection is kept in the stream,
+    /// reconnection resumes on the next call.
+    pub async fn next(&mut self) -> anyhow::Result<Vec<PrivateEvent>> {
+        match self.client.recv().await {
+            Received::Msg(msg) => Ok(private_events(msg)),
+            Received::Reconnected => {
+                // new connection is subscribed already
+                self.client.restored();
+                Ok(vec![PrivateEvent::Reconnected])
+            }
+        }
+    }
+
+    pub async fn close(self) {
+        self.client.close().await;
+    }
+}
+
+async fn connect_client(
+    auth: &AuthMethod,
+    uri: &Uri,
+) -> anyhow::Result<WsClient<PrivateClientMsg, Parsed<PrivateServerMsg>>> {
+    let mut client = private_ws(uri.clone())
+        .await
+        .context("connect to poloniex private WebSocket server")?;
+    let signature = auth.sign("GET", "/ws").context("sign authentication")?;
+    if client
+        .send(PrivateClientMsg::auth(signature))
+        .await
+        .is_err()
+    {
+        bail!("connection is closed before authentication");
+    }
+    match client.recv_timeout(AUTH_TIMEOUT).await? {
+        Some(Parsed::Msg(PrivateServerMsg::Auth {
+            data: AuthData { success: true, .. },
+            ..
+        })) => {}
+        Some(Parsed::Msg(PrivateServerMsg::Auth {
+            data: AuthData { message, .. },
+            ..
+        })) => bail!("authentication failed: {}", message.unwrap_or_default()),
+        other => bail!("unexpected reply to authentication: {other:?}"),
+    }
+    if client
+        .send(PrivateClientMsg::subscribe_orders())
+        .await
+        .is_err()
+    {
+        bail!("connection is closed before subscription");
+    }
+    Ok(client)
+}
+
+#[cfg(test)]
+mod tests {
+    use bitsgap_shared::{auth::Signature, utils::time::UnixMillis};
+
+    use super::{orders::OrderEventType, *};
+    use crate::rest::orders::OrderState;
+
+    #[test]
+    fn test_private_protocol() {
+        let auth = PrivateClientMsg::auth(Signature {
+            api_key: "key".into(),
+            signature: "c2lnbmF0dXJl".into(),
+            timestamp: UnixMillis(1649371369000),
+        });
+        assert_eq!(
+            serde_json::to_string(&auth).unwrap(),
+            r#"{"event":"subscribe","channel":["auth"],"params":{"key":"key","signTimestamp":1649371369000,"signature":"c2lnbmF0dXJl"}}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&PrivateClientMsg::subscribe_orders()).unwrap(),
+            r#"{"event":"subscribe","channel":["orders"],"symbols":["all"]}"#
+        );
+
+        let reply: PrivateServerMsg = serde_json::from_str(
+            r#"{"data": {"success": true, "ts": 1645597033915}, "channel": "auth"}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            reply,
+            PrivateServerMsg::Auth {
+                data: AuthData { success: true, .. },
+                ..
+            }
+        ));
+        let reply: PrivateServerMsg = serde_json::from_str(
+            r#"{"data": {"success": false, "message": "Authentication failed!", "ts": 1646276295075}, "channel": "auth"}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            reply,
+            PrivateServerMsg::Auth {
+                data: AuthData { success: false, .. },
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_private_events() {
+        let raw = r#"
+        {
+            "channel": "orders",
+            "data": [{
+                "symbol": "BTC_USDT",
+                "type": "LIMIT",
+                "quantity": "0.001",
+                "orderId": "32471407854219264",
+                "tradeFee": "0",
+                "clientOrderId": "grid-1",
+                "accountType": "SPOT",
+                "feeCurrency": "",
+                "eventType": "place",
+                "source": "API",
+                "side": "BUY",
+                "filledQuantity": "0",
+                "filledAmount": "0",
+                "matchRole": "MAKER",
+                "state": "NEW",
+                "tradeTime": 0,
+                "tradeAmount": "0",
+                "orderAmount": "0",
+                "createTime": 1648708186922,
+                "price": "47112.1",
+                "tradeQty": "0",
+                "tradePrice": "0",
+                "tradeId": "0",
+                "ts": 1648708187469
+            }, {
+                "symbol": "BTC_USDT",
+                "eventType": "renamed"
+            }]
+        }
+        "#;
+        let msg = serde_json::from_str(raw).unwrap();
+        let events = private_events(Parsed::Msg(msg));
+        assert_eq!(events.len(), 2);
+        match &events[0] {
+            PrivateEvent::Order(msg) => {
+                assert_eq!(msg.event_type, OrderEventType::Place);
+                assert_eq!(msg.state, OrderState::New);
+                assert_eq!(msg.client_order_id, "grid-1");
+            }
+            other => panic!("unexpected event {other:?}"),
+        }
+        assert!(matches!(&events[1], PrivateEvent::Invalid { .. }));
+
+        let msg = serde_json::from_str(
+            r#"{"event": "subscribe", "channel": "orders", "symbols": ["all"]}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            private_events(Parsed::Msg(msg))[..],
+            [PrivateEvent::Subscribed(PrivateChannel::Orders)]
+        ));
+    }
+}
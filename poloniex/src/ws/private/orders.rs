@@ -0,0 +1,47 @@
+use crate::{
+    rest::orders::{OrderSide, OrderState, OrderType},
+    units::{PxClientOrderId, PxOrderId, PxPrice, PxSymbol, PxTimestamp, PxUnits},
+};
+
+/// What happened to order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderEventType {
+    Place,
+    Trade,
+    Canceled,
+}
+
+/// Event of `orders` channel, carries whole order after the event
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderMessage {
+    pub symbol: PxSymbol,
+    pub order_id: PxOrderId,
+    /// empty if order was placed without one
+    pub client_order_id: PxClientOrderId,
+    pub event_type: OrderEventType,
+    pub state: OrderState,
+    pub side: OrderSide,
+    #[serde(rename = "type")]
+    pub order_type: OrderType,
+    /// "0" for market orders
+    pub price: PxPrice,
+    /// base units ordered
+    pub quantity: PxUnits,
+    /// quote units ordered
+    pub order_amount: PxUnits,
+    /// base units filled
+    pub filled_quantity: PxUnits,
+    /// quote units filled
+    pub filled_amount: PxUnits,
+    /// "0" unless event is a trade
+    pub trade_id: String,
+    /// base units filled by this trade
+    pub trade_qty: PxUnits,
+    pub trade_price: PxPrice,
+    pub create_time: PxTimestamp,
+    /// time the record was pushed
+    #[serde(rename = "ts")]
+    pub record_time: PxTimestamp,
+}
@@ -0,0 +1,98 @@
+use bitsgap_shared::auth::Signature;
+
+use crate::{
+    units::PxTimestamp,
+    ws::protocol::{ServerError, StreamData},
+};
+
+/// Private channel, (de)serialized as Poloniex channel name
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum PrivateChannel {
+    /// not a real channel, subscription to it authenticates connection
+    Auth,
+    Orders,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum PrivateClientMsg {
+    Ping,
+    Subscribe {
+        channel: Vec<PrivateChannel>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        symbols: Vec<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        params: Option<AuthParams>,
+    },
+}
+
+impl PrivateClientMsg {
+    pub fn auth(signature: Signature) -> Self {
+        let Signature {
+            api_key,
+            signature,
+            timestamp,
+        } = signature;
+        Self::Subscribe {
+            channel: vec![PrivateChannel::Auth],
+            symbols: vec![],
+            params: Some(AuthParams {
+                key: api_key,
+                sign_timestamp: timestamp,
+                signature,
+            }),
+        }
+    }
+
+    /// Events of orders of every symbol
+    pub fn subscribe_orders() -> Self {
+        Self::Subscribe {
+            channel: vec![PrivateChannel::Orders],
+            symbols: vec!["all".into()],
+            params: None,
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthParams {
+    pub key: String,
+    pub sign_timestamp: PxTimestamp,
+    /// of "GET\n/ws\nsignTimestamp={sign_timestamp}"
+    pub signature: String,
+}
+
+#[derive(Debug, serde::Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum PrivateServerMsg {
+    Stream {
+        channel: PrivateChannel,
+        data: StreamData,
+    },
+    /// reply to authentication, same shape as stream, but with object as data
+    Auth {
+        channel: PrivateChannel,
+        data: AuthData,
+    },
+    Event(PrivateServerEvent),
+}
+
+#[derive(Debug, serde::Deserialize, PartialEq)]
+pub struct AuthData {
+    pub success: bool,
+    /// reason of failure
+    pub message: Option<String>,
+    pub ts: PxTimestamp,
+}
+
+#[derive(Debug, serde::Deserialize, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum PrivateServerEvent {
+    Pong,
+    Subscribe { channel: PrivateChannel },
+    Error { message: ServerError },
+}
@@ -1,22 +1,53 @@
 use anyhow::Context;
+use serde::Deserialize as _;
 use serde_json::Value;
 
+use super::{
+    candles::CandlesMessage,
+    channels::{Channel, StreamEvent},
+    trades::TradesMessage,
+};
+
 #[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq)]
 #[serde(tag = "event", rename_all = "snake_case")]
 pub enum ClientMsg {
     Ping,
     Subscribe {
-        channel: Vec<String>,
+        channel: Vec<Channel>,
         symbols: Vec<String>,
     },
     Unsubscribe {
-        channel: Vec<String>,
+        channel: Vec<Channel>,
         symbols: Vec<String>,
     },
     UnsubscribeAll,
     ListSubscriptions,
 }
 
+impl ClientMsg {
+    /// Every channel of every symbol
+    pub fn subscribe<S: Into<String>>(
+        channels: impl IntoIterator<Item = Channel>,
+        symbols: impl IntoIterator<Item = S>,
+    ) -> Self {
+        Self::Subscribe {
+            channel: channels.into_iter().collect(),
+            symbols: symbols.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    pub fn unsubscribe<S: Into<String>>(
+        channels: impl IntoIterator<Item = Channel>,
+        symbols: impl IntoIterator<Item = S>,
+    ) -> Self {
+        Self::Unsubscribe {
+            channel: channels.into_iter().collect(),
+            symbols: symbols.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+// channels are kept as names, so unknown channel doesn't make the whole message unparsable
 #[derive(Debug, serde::Deserialize, PartialEq)]
 #[serde(untagged)]
 pub enum ServerMsg {
@@ -28,12 +59,33 @@ pub enum ServerMsg {
 #[derive(Debug, serde::Deserialize, PartialEq)]
 pub struct ServerStream {
     pub data: StreamData,
+    /// channel name, like "trades"
     pub channel: String,
 }
 impl ServerStream {
     pub fn into_msg(self) -> ServerMsg {
         ServerMsg::Stream(self)
     }
+
+    pub fn channel(&self) -> anyhow::Result<Channel> {
+        self.channel.parse()
+    }
+
+    /// Parses events into types of the channel, keeps json value of each event, e.g. to store it if it's unparsable.
+    /// Every event of unknown channel is an error.
+    pub fn events(&self) -> impl Iterator<Item = (&Value, anyhow::Result<StreamEvent>)> {
+        self.data.0.iter().map(move |value| {
+            let res = self.channel().and_then(|channel| {
+                match channel {
+                    Channel::Klines(interval) => CandlesMessage::deserialize(value)
+                        .map(|msg| StreamEvent::Candles(interval, msg)),
+                    Channel::Trades => TradesMessage::deserialize(value).map(StreamEvent::Trades),
+                }
+                .context("parse stream event from json value")
+            });
+            (value, res)
+        })
+    }
 }
 
 #[derive(Debug, serde::Deserialize, PartialEq)]
@@ -48,6 +100,16 @@ impl StreamData {
             serde_json::from_value(value).context("parse stream event from json value")
         })
     }
+
+    /// Same as `into_events`, but keeps json value of each event, e.g. to store it if it's unparsable
+    pub fn parse_events<T: serde::de::DeserializeOwned>(
+        &self,
+    ) -> impl Iterator<Item = (&Value, anyhow::Result<T>)> {
+        self.0.iter().map(|value| {
+            let res = T::deserialize(value).context("parse stream event from json value");
+            (value, res)
+        })
+    }
 }
 
 #[derive(Debug, serde::Deserialize, PartialEq)]
@@ -55,11 +117,11 @@ impl StreamData {
 pub enum ServerEvent {
     Pong,
     Subscribe {
-        channel: String,
+        channel: Channel,
     },
     #[serde(rename = "UNSUBSCRIBE")]
     Unsubscribe {
-        channel: String,
+        channel: Channel,
     },
     #[serde(rename = "UNSUBSCRIBE_ALL")]
     UnsubscribeAll {
@@ -115,9 +177,9 @@ mod tests {
     use std::fmt;
 
     use anyhow::Context;
+    use bitsgap_shared::{interval::IntervalKind, utils::time::UnixMillis};
 
     use super::*;
-    use crate::ws::candles::CandlesMessage;
 
     #[track_caller]
     fn assert_eq<T: serde::de::DeserializeOwned + PartialEq + fmt::Debug>(msg: T, json: &str) {
@@ -137,13 +199,13 @@ mod tests {
     fn test_subscribe() {
         assert_eq(
             ClientMsg::Subscribe {
-                channel: vec!["<channel>".into()],
+                channel: vec![Channel::Trades],
                 symbols: vec!["<symbol1>".into(), "<symbol2>".into(), "<symbol3>".into()],
             },
             r#"
             {
                 "event": "subscribe",
-                "channel": ["<channel>"],
+                "channel": ["trades"],
                 "symbols": [
                     "<symbol1>",
                     "<symbol2>",
@@ -154,13 +216,13 @@ mod tests {
         );
         assert_eq(
             ClientMsg::Subscribe {
-                channel: vec!["<channel>".into()],
+                channel: vec![Channel::Trades],
                 symbols: vec!["all".into()],
             },
             r#"
             {
                 "event": "subscribe",
-                "channel": ["<channel>"],
+                "channel": ["trades"],
                 "symbols": ["all"]
             }
             "#,
@@ -168,13 +230,13 @@ mod tests {
 
         assert_eq(
             ServerEvent::Subscribe {
-                channel: "<channel>".into(),
+                channel: Channel::Trades,
             }
             .into_msg(),
             r#"
             {
                 "event": "subscribe",
-                "channel": "<channel>"
+                "channel": "trades"
             }
             "#,
         );
@@ -184,13 +246,13 @@ mod tests {
     fn test_unsubscribe() {
         assert_eq(
             ClientMsg::Unsubscribe {
-                channel: vec!["<channel>".into()],
+                channel: vec![Channel::Trades],
                 symbols: vec!["<symbol>".into()],
             },
             r#"
             {
                 "event": "unsubscribe",
-                "channel": ["<channel>"],
+                "channel": ["trades"],
                 "symbols": [
                 "<symbol>"
                 ]
@@ -199,13 +261,13 @@ mod tests {
         );
         assert_eq(
             ClientMsg::Unsubscribe {
-                channel: vec!["<channel>".into()],
+                channel: vec![Channel::Trades],
                 symbols: vec!["all".into()],
             },
             r#"
             {
                 "event": "unsubscribe",
-                "channel": ["<channel>"],
+                "channel": ["trades"],
                 "symbols": ["all"]
             }
             "#,
@@ -213,12 +275,12 @@ mod tests {
 
         assert_eq(
             ServerEvent::Unsubscribe {
-                channel: "<channel>".into(),
+                channel: Channel::Trades,
             }
             .into_msg(),
             r#"
             {
-                "channel": "<channel>",
+                "channel": "trades",
                 "event": "UNSUBSCRIBE"
             }          
             "#,
@@ -263,11 +325,11 @@ mod tests {
 
         assert_eq(
             ServerMsg::Subscriptions {
-                subscriptions: vec!["<channel>".into()],
+                subscriptions: vec!["trades".into()],
             },
             r#"
             {
-                "subscriptions": ["<channel>"]
+                "subscriptions": ["trades"]
             }
             "#,
         );
@@ -344,11 +406,11 @@ mod tests {
                         quantity: "0".into(),
                         trade_count: 0,
                         low: "9999.07".into(),
-                        close_time: 1648057199999,
-                        start_time: 1648057140000,
+                        close_time: UnixMillis(1648057199999),
+                        start_time: UnixMillis(1648057140000),
                         close: "9999.07".into(),
                         open: "9999.07".into(),
-                        record_time: 1648057141081,
+                        record_time: UnixMillis(1648057141081),
                     })
                     .unwrap(),
                 ]),
@@ -374,4 +436,42 @@ mod tests {
         "#,
         );
     }
+
+    #[test]
+    fn test_parse_events_keeps_unparsable() {
+        let data = StreamData(vec![
+            serde_json::json!({"symbol": "BTC_USDT", "renamedField": "0"}),
+            serde_json::json!({
+                "symbol": "BTC_USDT",
+                "amount": "0",
+                "high": "9999.07",
+                "quantity": "0",
+                "tradeCount": 0,
+                "low": "9999.07",
+                "closeTime": 1648057199999i64,
+                "startTime": 1648057140000i64,
+                "close": "9999.07",
+                "open": "9999.07",
+                "ts": 1648057141081i64
+            }),
+        ]);
+        let events: Vec<(&Value, anyhow::Result<CandlesMessage>)> = data.parse_events().collect();
+        assert!(events[0].1.is_err());
+        assert_eq!(events[0].0, &data.0[0]);
+        assert!(events[1].1.is_ok());
+
+        let stream = ServerStream {
+            data,
+            channel: "candles_minute_1".into(),
+        };
+        let events: Vec<_> = stream.events().collect();
+        assert!(events[0].1.is_err());
+        match &events[1].1 {
+            Ok(event @ StreamEvent::Candles(interval, _)) => {
+                assert_eq!(interval.kind, IntervalKind::Minute);
+                assert_eq!(event.symbol(), "BTC_USDT");
+            }
+            other => panic!("unexpected event {other:?}"),
+        }
+    }
 }
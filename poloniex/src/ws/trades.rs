@@ -1,4 +1,5 @@
-use bitsgap_shared::records::recent_trade::RecentTrade;
+use anyhow::Context as _;
+use bitsgap_shared::{pair::ExchangeSymbols, records::recent_trade::RecentTrade, utils::Has};
 use let_clone::let_clone;
 
 use crate::units::{PxPrice, PxSymbol, PxTimestamp, PxUnits};
@@ -42,27 +43,72 @@ impl TakerSide {
 }
 
 impl TradesMessage {
-    pub fn recent_trade(&self) -> RecentTrade {
+    pub fn recent_trade<C: Has<ExchangeSymbols>>(
+        &self,
+        context: &C,
+    ) -> anyhow::Result<RecentTrade> {
         let Self {
             symbol,
-            amount,
+            quantity,
             taker_side,
             create_time,
             price,
             id,
             ..
         } = self;
-        let_clone!(symbol: pair, price, amount, id: tid);
-        RecentTrade {
+        // `RecentTrade::amount` is in base units, so it's `quantity`, not `amount`
+        let_clone!(price, quantity: amount, id: tid);
+        let pair = context
+            .give(ExchangeSymbols)
+            .to_pair(symbol)
+            .context("convert symbol to pair")?
+            .clone();
+        Ok(RecentTrade {
+            schema: RecentTrade::SCHEMA_VERSION,
             tid,
-            // TODO: make sure it looks like "BTC_USDT"
+            exchange: crate::EXCHANGE.into(),
             pair,
             price,
             amount,
             side: taker_side.as_str().into(),
-            // it's safe to convert u64 milliseconds to i64, it's still enough for ~242 million years
-            // but it's better if we encapsulate it in newtype, and use proper type for inner record type.
-            timestamp: *create_time as _,
-        }
+            timestamp: create_time.to_nanos().context("convert create time")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitsgap_shared::utils::time::UnixNanos;
+
+    use super::*;
+    use crate::context::PoloniexContext;
+
+    #[test]
+    fn test_trades_recent_trade() {
+        let context = PoloniexContext::init(false).unwrap();
+        let msg: TradesMessage = serde_json::from_str(
+            r#"
+            {
+                "symbol": "BTC_USDT",
+                "amount": "70",
+                "takerSide": "buy",
+                "quantity": "4",
+                "createTime": 1648057141081,
+                "price": "17.5",
+                "id": "1",
+                "ts": 1648057141091
+            }
+            "#,
+        )
+        .unwrap();
+        let recent_trade = msg.recent_trade(&context).unwrap();
+        assert_eq!(recent_trade.pair.to_string(), "BTC/USDT");
+        assert_eq!(recent_trade.amount, "4");
+        assert_eq!(recent_trade.side, "buy");
+        assert_eq!(recent_trade.timestamp, UnixNanos(1648057141081000000));
+        assert_eq!(
+            recent_trade.timestamp.display().to_string(),
+            "2022-03-23T17:39:01.081Z"
+        );
     }
 }
@@ -0,0 +1,292 @@
+use std::collections::BTreeSet;
+
+use anyhow::{Context as _, bail};
+use bitsgap_shared::{exchange::RequestId, pair::Pair};
+use tokio::sync::{mpsc, oneshot};
+
+/// Channels and pairs the stream should be subscribed to, every channel with every pair
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(bound(deserialize = "C: Ord + serde::Deserialize<'de>"))]
+pub(crate) struct DesiredSubscriptions<C> {
+    pub(crate) channels: BTreeSet<C>,
+    pub(crate) pairs: BTreeSet<Pair>,
+}
+
+/// Body of `POST /subscriptions` and `DELETE /subscriptions`
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(bound(deserialize = "C: Ord + serde::Deserialize<'de>"))]
+pub(crate) struct SubscriptionChange<C> {
+    #[serde(default)]
+    pub(crate) channels: BTreeSet<C>,
+    #[serde(default)]
+    pub(crate) pairs: BTreeSet<Pair>,
+}
+
+impl<C> Default for SubscriptionChange<C> {
+    fn default() -> Self {
+        Self {
+            channels: BTreeSet::new(),
+            pairs: BTreeSet::new(),
+        }
+    }
+}
+
+/// Channels and pairs of one `Subscribe` or `Unsubscribe` message
+#[derive(Debug, PartialEq)]
+pub(crate) struct SubscriptionBatch<C> {
+    pub(crate) channels: Vec<C>,
+    pub(crate) pairs: Vec<Pair>,
+}
+
+impl<C: Copy + Ord> DesiredSubscriptions<C> {
+    /// Adds or removes channels and pairs, returns batches to subscribe or unsubscribe.
+    /// Every desired channel stays subscribed to every desired pair, so new channel gets all pairs
+    /// and new pair gets all channels.
+    pub(crate) fn apply(
+        &mut self,
+        change: SubscriptionChange<C>,
+        subscribe: bool,
+    ) -> Vec<SubscriptionBatch<C>> {
+        let (changed_channels, changed_pairs): (BTreeSet<_>, BTreeSet<_>) = if subscribe {
+            (
+                &change.channels - &self.channels,
+                &change.pairs - &self.pairs,
+            )
+        } else {
+            (
+                &change.channels & &self.channels,
+                &change.pairs & &self.pairs,
+            )
+        };
+        let kept_channels = &self.channels - &changed_channels;
+        let all_pairs = if subscribe {
+            &self.pairs | &changed_pairs
+        } else {
+            self.pairs.clone()
+        };
+        if subscribe {
+            self.channels.extend(changed_channels.iter().copied());
+            self.pairs.extend(changed_pairs.iter().cloned());
+        } else {
+            self.channels = kept_channels.clone();
+            self.pairs = &self.pairs - &changed_pairs;
+        }
+
+        let batches = [
+            (changed_channels, all_pairs),
+            (kept_channels, changed_pairs),
+        ];
+        batches
+            .into_iter()
+            .filter(|(channels, pairs)| !channels.is_empty() && !pairs.is_empty())
+            .map(|(channels, pairs)| SubscriptionBatch {
+                channels: channels.into_iter().collect(),
+                pairs: pairs.into_iter().collect(),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum ControlCommand<C> {
+    List,
+    Subscribe(SubscriptionChange<C>),
+    Unsubscribe(SubscriptionChange<C>),
+}
+
+pub(crate) type ControlReply<C> = Result<DesiredSubscriptions<C>, String>;
+
+#[derive(Debug)]
+pub(crate) struct ControlRequest<C> {
+    pub(crate) command: ControlCommand<C>,
+    pub(crate) reply: oneshot::Sender<ControlReply<C>>,
+}
+
+/// Sends commands to running stream, used by HTTP handlers
+#[derive(Debug, Clone)]
+pub(crate) struct Control<C> {
+    tx: mpsc::Sender<ControlRequest<C>>,
+}
+
+impl<C> Control<C> {
+    pub(crate) fn new() -> (Self, mpsc::Receiver<ControlRequest<C>>) {
+        // commands are rare, a few is plenty
+        let (tx, rx) = mpsc::channel(8);
+        (Self { tx }, rx)
+    }
+
+    /// Resolves once server acknowledged all subscriptions of the command
+    pub(crate) async fn request(
+        &self,
+        command: ControlCommand<C>,
+    ) -> anyhow::Result<ControlReply<C>> {
+        let (reply, rx) = oneshot::channel();
+        if self
+            .tx
+            .send(ControlRequest { command, reply })
+            .await
+            .is_err()
+        {
+            bail!("stream is not running");
+        }
+        rx.await.context("stream stopped before reply")
+    }
+}
+
+/// Change which waits for server's acknowledgements
+#[derive(Debug)]
+pub(crate) struct PendingChange<C> {
+    /// messages sent for the change and their channels, one per expected acknowledgement
+    pub(crate) waiting: Vec<(RequestId, C)>,
+    pub(crate) desired: DesiredSubscriptions<C>,
+    pub(crate) reply: oneshot::Sender<ControlReply<C>>,
+}
+
+/// Changes waiting for acknowledgements of their own messages.
+/// Acknowledgements of watchdog's or reconnect's messages don't count.
+#[derive(Debug)]
+pub(crate) struct PendingChanges<C> {
+    changes: Vec<PendingChange<C>>,
+}
+
+impl<C> Default for PendingChanges<C> {
+    fn default() -> Self {
+        Self { changes: vec![] }
+    }
+}
+
+impl<C: PartialEq> PendingChanges<C> {
+    pub(crate) fn push(&mut self, change: PendingChange<C>) {
+        if change.waiting.is_empty() {
+            // nothing to wait for, e.g. pair was already subscribed
+            let _ = change.reply.send(Ok(change.desired));
+        } else {
+            self.changes.push(change);
+        }
+    }
+
+    pub(crate) fn acknowledge(&mut self, request: RequestId, channel: C) {
+        // callers which gave up waiting don't need a reply
+        self.changes.retain(|change| !change.reply.is_closed());
+        let acknowledged = (request, channel);
+        let Some((index, position)) =
+            self.changes.iter().enumerate().find_map(|(index, change)| {
+                let position = change.waiting.iter().position(|w| *w == acknowledged)?;
+                Some((index, position))
+            })
+        else {
+            return;
+        };
+        let change = &mut self.changes[index];
+        change.waiting.remove(position);
+        if change.waiting.is_empty() {
+            let change = self.changes.remove(index);
+            let _ = change.reply.send(Ok(change.desired));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitsgap_poloniex::ws::channels::Channel;
+
+    use super::*;
+
+    fn pairs<const N: usize>(pairs: [&str; N]) -> BTreeSet<Pair> {
+        pairs
+            .into_iter()
+            .map(|pair| pair.parse().unwrap())
+            .collect()
+    }
+
+    fn channels<const N: usize>(channels: [&str; N]) -> BTreeSet<Channel> {
+        channels
+            .into_iter()
+            .map(|channel| channel.parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_apply_subscription_change() {
+        let mut desired = DesiredSubscriptions {
+            channels: channels(["trades"]),
+            pairs: pairs(["BTC/USDT"]),
+        };
+        let batches = desired.apply(
+            SubscriptionChange {
+                channels: channels(["candles_minute_1", "trades"]),
+                pairs: pairs(["ETH/USDT"]),
+            },
+            true,
+        );
+        assert_eq!(
+            batches,
+            [
+                SubscriptionBatch {
+                    channels: channels(["candles_minute_1"]).into_iter().collect(),
+                    pairs: pairs(["BTC/USDT", "ETH/USDT"]).into_iter().collect(),
+                },
+                SubscriptionBatch {
+                    channels: vec![Channel::Trades],
+                    pairs: pairs(["ETH/USDT"]).into_iter().collect(),
+                },
+            ]
+        );
+        assert_eq!(desired.channels, channels(["candles_minute_1", "trades"]));
+        assert_eq!(desired.pairs, pairs(["BTC/USDT", "ETH/USDT"]));
+
+        let batches = desired.apply(
+            SubscriptionChange {
+                channels: channels(["trades"]),
+                pairs: pairs(["ETH/USDT", "DOGE/USDT"]),
+            },
+            false,
+        );
+        assert_eq!(
+            batches,
+            [
+                SubscriptionBatch {
+                    channels: vec![Channel::Trades],
+                    pairs: pairs(["BTC/USDT", "ETH/USDT"]).into_iter().collect(),
+                },
+                SubscriptionBatch {
+                    channels: channels(["candles_minute_1"]).into_iter().collect(),
+                    pairs: pairs(["ETH/USDT"]).into_iter().collect(),
+                },
+            ]
+        );
+        assert_eq!(desired.channels, channels(["candles_minute_1"]));
+        assert_eq!(desired.pairs, pairs(["BTC/USDT"]));
+
+        assert!(
+            desired
+                .apply(SubscriptionChange::default(), true)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_acknowledge_own_requests_only() {
+        let desired = DesiredSubscriptions {
+            channels: channels(["trades"]),
+            pairs: pairs(["BTC/USDT"]),
+        };
+        let mut pending = PendingChanges::default();
+        let (reply, mut rx) = oneshot::channel();
+        pending.push(PendingChange {
+            waiting: vec![(2, Channel::Trades), (3, Channel::Trades)],
+            desired: desired.clone(),
+            reply,
+        });
+
+        // e.g. watchdog resubscribed the same channel
+        pending.acknowledge(1, Channel::Trades);
+        pending.acknowledge(4, Channel::Trades);
+        pending.acknowledge(2, Channel::Trades);
+        assert!(rx.try_recv().is_err());
+
+        pending.acknowledge(3, Channel::Trades);
+        assert_eq!(rx.try_recv().unwrap(), Ok(desired));
+        assert!(pending.changes.is_empty());
+    }
+}
@@ -0,0 +1,113 @@
+use anyhow::Context as _;
+use bitsgap_shared::{
+    exchange::Exchange,
+    validation::{ValidationPolicy, Validator},
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    health::Health,
+    storage::{DeadLetter, Storage},
+    stream::Pipeline,
+};
+
+#[derive(Debug, clap::Subcommand)]
+pub(crate) enum DeadLettersCommand {
+    /// Print stored dead letters, oldest first
+    List {
+        #[arg(long)]
+        limit: Option<i64>,
+    },
+    /// Parse and store dead letters again, e.g. after parser fix, exchange by exchange.
+    /// Letters which still fail are stored anew, with the same receive time
+    Reprocess {
+        /// Max number of letters of each exchange
+        #[arg(long)]
+        limit: Option<i64>,
+    },
+}
+
+pub(crate) async fn list(storage: &Storage, limit: Option<i64>) -> anyhow::Result<()> {
+    for DeadLetter {
+        id,
+        exchange,
+        raw,
+        channel,
+        errors,
+        received_at,
+    } in storage.dead_letters(None, limit).await?
+    {
+        let id = id.map(|id| id.to_hex()).unwrap_or_default();
+        println!(
+            "{id} {} [{exchange} {}] {}",
+            received_at.display(),
+            channel.as_deref().unwrap_or("-"),
+            errors.join(": ")
+        );
+        println!("    {raw}");
+    }
+    Ok(())
+}
+
+/// Dead letters of `exchange` only, the others need their own parser
+pub(crate) async fn reprocess<E: Exchange>(
+    exchange: &E,
+    limit: Option<i64>,
+    storage: &Storage,
+    validation_policy: ValidationPolicy,
+    shutdown: &CancellationToken,
+) -> anyhow::Result<()> {
+    let validator = Validator::new(validation_policy);
+    let health = Health::default();
+    let mut pipeline = Pipeline::new(exchange, storage, &validator, false, &health);
+    let dead_letters = storage.dead_letters(Some(exchange.name()), limit).await?;
+    let total = dead_letters.len();
+    let mut stored = 0;
+    for dead_letter in dead_letters {
+        if shutdown.is_cancelled() {
+            log::info!("Reprocessing is interrupted by shutdown");
+            break;
+        }
+        let id = dead_letter.id.context("dead letter has no id")?;
+        match reprocess_letter(&mut pipeline, dead_letter).await {
+            Ok(records) => stored += records,
+            Err(err) => {
+                log::error!("Can't reprocess dead letter {id}: {err:#}");
+                continue;
+            }
+        }
+        storage.delete_dead_letter(id).await?;
+    }
+    log::info!(
+        "Reprocessed {total} dead letters of {}, stored {stored} records, {} failed again; validation: {}",
+        exchange.name(),
+        pipeline.dead_letters(),
+        validator.counters()
+    );
+    Ok(())
+}
+
+async fn reprocess_letter<E: Exchange>(
+    pipeline: &mut Pipeline<'_, E>,
+    dead_letter: DeadLetter,
+) -> anyhow::Result<usize> {
+    let DeadLetter {
+        raw,
+        channel,
+        received_at,
+        ..
+    } = dead_letter;
+    let parsed_channel = channel.as_deref().map(str::parse).transpose();
+    match parsed_channel {
+        Ok(parsed_channel) => {
+            let events = pipeline.exchange.parse_events(&raw, parsed_channel);
+            pipeline.handle_events(events, received_at).await
+        }
+        Err(error) => {
+            pipeline
+                .dead_letter(raw, channel, error, received_at)
+                .await?;
+            Ok(0)
+        }
+    }
+}
@@ -1,78 +1,166 @@
+use std::time::Duration;
+
 use anyhow::Context as _;
-use bitsgap_poloniex::{context::PoloniexContext, rest::candles::CandlesRequest};
 use bitsgap_shared::{
-    ApiRequester,
+    exchange::Exchange,
     interval::DatabaseIntervals,
-    utils::{Has, time::timestamp_display},
+    pair::Pair,
+    utils::{Has, time::UnixMillis},
+    validation::Validator,
 };
+use tokio_util::sync::CancellationToken;
 
-use crate::storage::Storage;
+use crate::{health::Health, storage::Storage, validate};
 
-pub(crate) async fn poloniex_klines(
-    requester: &ApiRequester<PoloniexContext>,
-    storage: &Storage,
-    symbols: &[&str],
-    since: u64,
-    limit_per_request: u16,
-    limit_per_interval: Option<u32>,
-) -> anyhow::Result<()> {
-    // Download historic klines
-    log::info!("Downloading historic klines...");
-    let mut total_klines_downloaded = 0;
-    for symbol in symbols {
-        let intervals = requester.context().give(DatabaseIntervals).iter();
-        for (interval, interval_name) in intervals {
-            let mut klines_per_interval = 0;
-            let mut end_time = None;
-            // TODO: should use new "shared" request type, once I'll figure out proper abstraction between different exchanges
-            // TODO: should automatically convert to klines
-            loop {
-                let req = CandlesRequest {
-                    symbol,
-                    interval,
-                    // max value is 500
-                    limit: Some(limit_per_request),
-                    start_time: Some(since),
-                    end_time,
-                };
-                let responses = requester
-                    .get_response(&req)
-                    .await
-                    .context("get candles response from rest api")?;
-                let klines = responses
-                    .iter()
-                    .map(|response| response.kline(&req, requester.context()))
-                    .collect::<anyhow::Result<_>>()
-                    .context("convert candles to klines")?;
-                storage.insert_klines(klines).await?;
+/// Downloads history from REST API, page by page
+pub(crate) struct Downloader<'a, E> {
+    pub(crate) exchange: &'a E,
+    pub(crate) storage: &'a Storage,
+    pub(crate) validator: &'a Validator,
+    pub(crate) health: &'a Health,
+    pub(crate) shutdown: &'a CancellationToken,
+}
 
-                let count = responses.len();
-                if let Some((first, last)) = responses.first().zip(responses.last()) {
-                    log::info!(
-                        "Downloaded {count} klines, symbol: {symbol}, interval: {interval_name:?}, start: {}, end: {}",
-                        timestamp_display(first.start_time),
-                        timestamp_display(last.close_time)
-                    );
-                    end_time = Some(first.start_time - 1);
+impl<E: Exchange> Downloader<'_, E> {
+    /// Number of series `klines` downloads
+    pub(crate) fn klines_series(&self, pairs: &[Pair]) -> usize {
+        pairs.len()
+            * self
+                .exchange
+                .context()
+                .give(DatabaseIntervals)
+                .iter()
+                .count()
+    }
+
+    pub(crate) async fn klines(
+        &self,
+        pairs: &[Pair],
+        since: UnixMillis,
+        limit_per_interval: Option<u32>,
+    ) -> anyhow::Result<()> {
+        let Self {
+            exchange,
+            storage,
+            validator,
+            health,
+            shutdown,
+        } = self;
+        // Download historic klines
+        log::info!("Downloading historic klines of {}...", exchange.name());
+        let mut total_klines_downloaded = 0;
+        'pairs: for pair in pairs {
+            let intervals = exchange.context().give(DatabaseIntervals).iter();
+            for (interval, interval_name) in intervals {
+                let mut klines_per_interval = 0;
+                let mut end_time = None;
+                loop {
+                    // nothing is pending between pages, so in-flight request can be dropped
+                    let klines = tokio::select! {
+                        res = exchange.klines(pair, interval, since, end_time, E::KLINES_LIMIT) => res.context("download klines")?,
+                        _ = shutdown.cancelled() => break 'pairs,
+                    };
+                    let count = klines.len();
+                    if let Some((first, last)) = klines.first().zip(klines.last()) {
+                        log::info!(
+                            "Downloaded {count} klines of {}, pair: {pair}, interval: {interval_name:?}, start: {}, end: {}",
+                            exchange.name(),
+                            first.utc_begin.display(),
+                            last.utc_end.display()
+                        );
+                        end_time = Some(first.utc_begin.saturating_sub(Duration::from_millis(1)));
+                    }
+                    let klines = validate::accept(
+                        validator,
+                        storage,
+                        exchange.context(),
+                        klines.into_iter().collect(),
+                    )
+                    .await?;
+                    storage.insert_klines(klines).await?;
+
+                    klines_per_interval += count as u32;
+                    let series_done = count < E::KLINES_LIMIT as _
+                        || matches!(limit_per_interval, Some(limit_per_interval) if klines_per_interval >= limit_per_interval);
+                    health.backfill_progressed(series_done);
+                    if series_done {
+                        break;
+                    }
                 }
+                total_klines_downloaded += klines_per_interval;
+            }
+        }
 
-                klines_per_interval += count as u32;
-                if count < limit_per_request as _ {
+        if shutdown.is_cancelled() {
+            log::info!("Klines download is interrupted by shutdown");
+        }
+        let klines_in_storage = storage.count_klines(exchange.name()).await?;
+        log::info!(
+            "Downloaded {total_klines_downloaded} klines. Storage has {klines_in_storage} klines of {}. Validation: {}",
+            exchange.name(),
+            validator.counters()
+        );
+
+        Ok(())
+    }
+
+    pub(crate) async fn trades(&self, pairs: &[Pair], since: UnixMillis) -> anyhow::Result<()> {
+        let Self {
+            exchange,
+            storage,
+            validator,
+            health,
+            shutdown,
+        } = self;
+        // Exchanges have no cursor for public trades, so we can only take the latest ones.
+        // That's also why there is no paging loop like in `klines`.
+        log::info!("Downloading historic trades of {}...", exchange.name());
+        let mut total_trades_stored = 0;
+        for pair in pairs {
+            let trades = tokio::select! {
+                res = exchange.recent_trades(pair) => res.context("download recent trades")?,
+                _ = shutdown.cancelled() => {
+                    log::info!("Trades download is interrupted by shutdown");
                     break;
                 }
-                if matches!(limit_per_interval, Some(limit_per_interval) if klines_per_interval >= limit_per_interval)
-                {
-                    break;
+            };
+            let count = trades.len();
+            // trades are sorted from newest to oldest
+            let newest_oldest = trades.first().zip(trades.last()).map(|(newest, oldest)| {
+                (newest.timestamp.to_millis(), oldest.timestamp.to_millis())
+            });
+            let recent_trades = trades
+                .into_iter()
+                .filter(|trade| trade.timestamp.to_millis() >= since)
+                .collect();
+            let recent_trades =
+                validate::accept(validator, storage, exchange.context(), recent_trades).await?;
+            let stored = storage.insert_recent_trades(recent_trades).await?;
+            if let Some((newest, oldest)) = newest_oldest {
+                log::info!(
+                    "Downloaded {count} trades of {}, stored {stored} new, pair: {pair}, start: {}, end: {}",
+                    exchange.name(),
+                    oldest.display(),
+                    newest.display()
+                );
+                if oldest > since {
+                    log::warn!(
+                        "Trades of {pair} between {} and {} are beyond what {} exposes",
+                        since.display(),
+                        oldest.display(),
+                        exchange.name()
+                    );
                 }
             }
-            total_klines_downloaded += klines_per_interval;
+            total_trades_stored += stored;
+            health.backfill_progressed(true);
         }
-    }
+        log::info!(
+            "Stored {total_trades_stored} historic trades of {}. Validation: {}",
+            exchange.name(),
+            validator.counters()
+        );
 
-    let klines_in_storage = storage.count_klines().await?;
-    log::info!(
-        "Downloaded {total_klines_downloaded} klines. Storage has {klines_in_storage} klines."
-    );
-
-    Ok(())
+        Ok(())
+    }
 }
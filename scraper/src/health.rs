@@ -0,0 +1,325 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Mutex,
+    time::Duration,
+};
+
+use bitsgap_shared::utils::time::UnixMillis;
+
+/// State of backfill and stream of one exchange, reported by `/healthz` and `/readyz`
+#[derive(Debug, Default)]
+pub(crate) struct Health {
+    state: Mutex<HealthState>,
+}
+
+#[derive(Debug, Default, Clone)]
+struct HealthState {
+    backfill: Option<BackfillProgress>,
+    stream: Option<StreamState>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+struct BackfillProgress {
+    done: usize,
+    total: usize,
+    finished: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    updated_at: UnixMillis,
+}
+
+#[derive(Debug, Clone)]
+struct StreamState {
+    connected_at: Option<UnixMillis>,
+    channels: BTreeSet<String>,
+    subscribed: BTreeSet<String>,
+    last_message: BTreeMap<String, UnixMillis>,
+}
+
+/// Storage and every exchange, problems of exchanges are prefixed with its name
+#[derive(Debug, serde::Serialize)]
+pub(crate) struct ScraperReport {
+    pub(crate) storage_ok: bool,
+    exchanges: BTreeMap<&'static str, HealthReport>,
+    /// scraper is wedged and should be restarted
+    pub(crate) unhealthy: Vec<String>,
+    /// scraper is alive, but doesn't deliver data yet or anymore
+    pub(crate) not_ready: Vec<String>,
+}
+
+impl ScraperReport {
+    pub(crate) fn new(
+        storage_ok: bool,
+        reports: impl IntoIterator<Item = (&'static str, HealthReport)>,
+    ) -> Self {
+        let mut unhealthy = vec![];
+        let mut not_ready = vec![];
+        if !storage_ok {
+            not_ready.push("storage is unreachable".into());
+        }
+        let exchanges = reports
+            .into_iter()
+            .inspect(|(exchange, report)| {
+                let prefixed = |problem| format!("{exchange}: {problem}");
+                unhealthy.extend(report.unhealthy.iter().map(prefixed));
+                not_ready.extend(report.not_ready.iter().map(prefixed));
+            })
+            .collect();
+        Self {
+            storage_ok,
+            exchanges,
+            unhealthy,
+            not_ready,
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+pub(crate) struct HealthReport {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    backfill: Option<BackfillProgress>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream: Option<StreamReport>,
+    /// scraper is wedged and should be restarted
+    pub(crate) unhealthy: Vec<String>,
+    /// scraper is alive, but doesn't deliver data yet or anymore
+    pub(crate) not_ready: Vec<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct StreamReport {
+    connected: bool,
+    subscribed: BTreeSet<String>,
+    /// seconds since the last message of each channel
+    last_message_age: BTreeMap<String, Option<f64>>,
+}
+
+impl Health {
+    fn update(&self, f: impl FnOnce(&mut HealthState)) {
+        // state is always consistent, so poisoning doesn't matter
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        f(&mut state)
+    }
+
+    pub(crate) fn backfill_started(&self, total: usize) {
+        self.update(|state| {
+            state.backfill = Some(BackfillProgress {
+                done: 0,
+                total,
+                finished: false,
+                error: None,
+                updated_at: UnixMillis::now(),
+            })
+        });
+    }
+
+    /// Called on every downloaded page, so long series don't look wedged
+    pub(crate) fn backfill_progressed(&self, series_done: bool) {
+        self.update(|state| {
+            if let Some(backfill) = &mut state.backfill {
+                backfill.done += usize::from(series_done);
+                backfill.updated_at = UnixMillis::now();
+            }
+        });
+    }
+
+    pub(crate) fn backfill_finished(&self) {
+        self.update(|state| {
+            if let Some(backfill) = &mut state.backfill {
+                backfill.done = backfill.total;
+                backfill.finished = true;
+                backfill.updated_at = UnixMillis::now();
+            }
+        });
+    }
+
+    /// Backfill stopped, data stays incomplete until the next one
+    pub(crate) fn backfill_failed(&self, error: String) {
+        self.update(|state| {
+            if let Some(backfill) = &mut state.backfill {
+                backfill.finished = true;
+                backfill.error = Some(error);
+                backfill.updated_at = UnixMillis::now();
+            }
+        });
+    }
+
+    pub(crate) fn stream_connected(&self, channels: impl IntoIterator<Item = String>) {
+        let now = UnixMillis::now();
+        self.update(|state| {
+            state.stream = Some(StreamState {
+                connected_at: Some(now),
+                channels: channels.into_iter().collect(),
+                subscribed: Default::default(),
+                last_message: Default::default(),
+            })
+        });
+    }
+
+    /// Channels changed at runtime
+    pub(crate) fn set_channels(&self, channels: impl IntoIterator<Item = String>) {
+        self.update(|state| {
+            if let Some(stream) = &mut state.stream {
+                stream.channels = channels.into_iter().collect();
+            }
+        });
+    }
+
+    pub(crate) fn stream_disconnected(&self) {
+        self.update(|state| {
+            if let Some(stream) = &mut state.stream {
+                stream.connected_at = None;
+                stream.subscribed.clear();
+            }
+        });
+    }
+
+    pub(crate) fn subscribed(&self, channel: &str, subscribed: bool) {
+        self.update(|state| {
+            if let Some(stream) = &mut state.stream {
+                if subscribed {
+                    stream.subscribed.insert(channel.into());
+                } else {
+                    stream.subscribed.remove(channel);
+                }
+            }
+        });
+    }
+
+    pub(crate) fn set_subscriptions(&self, channels: impl IntoIterator<Item = String>) {
+        self.update(|state| {
+            if let Some(stream) = &mut state.stream {
+                stream.subscribed = channels.into_iter().collect();
+            }
+        });
+    }
+
+    pub(crate) fn message(&self, channel: &str, received_at: UnixMillis) {
+        self.update(|state| {
+            if let Some(stream) = &mut state.stream {
+                stream.last_message.insert(channel.into(), received_at);
+            }
+        });
+    }
+
+    pub(crate) fn report(&self, max_age: Duration, now: UnixMillis) -> HealthReport {
+        let state = self
+            .state
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone();
+        let is_stale = |since: UnixMillis| since.saturating_add(max_age) < now;
+        let age = |since: UnixMillis| (now.as_i64() - since.as_i64()) as f64 / 1000.0;
+        let mut unhealthy = vec![];
+        let mut not_ready = vec![];
+
+        if let Some(backfill) = &state.backfill {
+            if !backfill.finished && is_stale(backfill.updated_at) {
+                unhealthy.push(format!(
+                    "backfill has no progress for {}s",
+                    age(backfill.updated_at)
+                ));
+            }
+            if let Some(error) = &backfill.error {
+                not_ready.push(format!("backfill failed: {error}"));
+            }
+        }
+        let stream = state.stream.map(|stream| {
+            let connected = stream.connected_at.is_some();
+            if !connected {
+                not_ready.push("WS is disconnected".into());
+            }
+            let mut last_message_age = BTreeMap::new();
+            for channel in &stream.channels {
+                if connected && !stream.subscribed.contains(channel) {
+                    not_ready.push(format!("channel {channel} is not subscribed"));
+                }
+                let last_message = stream.last_message.get(channel).copied();
+                // channel without messages yet is stale since connection
+                if let Some(since) = last_message.or(stream.connected_at) {
+                    if is_stale(since) {
+                        let problem =
+                            format!("channel {channel} has no messages for {}s", age(since));
+                        not_ready.push(problem.clone());
+                        if connected {
+                            unhealthy.push(problem);
+                        }
+                    }
+                }
+                last_message_age.insert(channel.clone(), last_message.map(age));
+            }
+            StreamReport {
+                connected,
+                subscribed: stream.subscribed,
+                last_message_age,
+            }
+        });
+        HealthReport {
+            backfill: state.backfill,
+            stream,
+            unhealthy,
+            not_ready,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn test_health_report() {
+        let health = Health::default();
+        let now = UnixMillis::now();
+        let report = health.report(MINUTE, now);
+        assert!(report.unhealthy.is_empty() && report.not_ready.is_empty());
+
+        health.stream_connected(["trades".into(), "candles_minute_1".into()]);
+        health.subscribed("trades", true);
+        health.message("trades", now);
+        let report = health.report(MINUTE, now);
+        assert!(report.unhealthy.is_empty());
+        assert_eq!(
+            report.not_ready,
+            ["channel candles_minute_1 is not subscribed"]
+        );
+
+        health.subscribed("candles_minute_1", true);
+        let later = now.saturating_add(2 * MINUTE);
+        let report = health.report(MINUTE, later);
+        assert_eq!(report.unhealthy.len(), 2);
+        assert_eq!(report.not_ready.len(), 2);
+
+        let report = ScraperReport::new(false, [("poloniex", report)]);
+        assert_eq!(report.unhealthy.len(), 2);
+        assert_eq!(
+            report.not_ready[..2],
+            [
+                "storage is unreachable",
+                "poloniex: channel candles_minute_1 has no messages for 120s"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_failed_backfill_is_not_ready_only() {
+        let health = Health::default();
+        let now = UnixMillis::now();
+        health.backfill_started(3);
+        health.backfill_progressed(true);
+        health.backfill_failed("HTTP status 500".into());
+
+        let later = now.saturating_add(2 * MINUTE);
+        let report = health.report(MINUTE, later);
+        assert!(report.unhealthy.is_empty());
+        assert_eq!(report.not_ready, ["backfill failed: HTTP status 500"]);
+
+        // the next backfill starts over
+        health.backfill_started(1);
+        health.backfill_finished();
+        let report = health.report(MINUTE, later);
+        assert!(report.unhealthy.is_empty() && report.not_ready.is_empty());
+    }
+}
@@ -0,0 +1,226 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    time::Duration,
+};
+
+use anyhow::Context as _;
+use bitsgap_shared::{
+    exchange::Exchange,
+    pair::Pair,
+    records::kline::{Kline, KlineV1},
+    utils::time::UnixMillis,
+    validation::Validate,
+};
+use mongodb::Cursor;
+use serde::de::DeserializeOwned;
+
+use crate::storage::{KlineFilter, Storage};
+
+#[derive(Debug, clap::Args)]
+pub(crate) struct SeriesArgs {
+    /// Only this exchange, like "binance"
+    #[arg(long)]
+    exchange: Option<String>,
+    /// Only this pair, like "BTC/USDT"
+    #[arg(long)]
+    pair: Option<Pair>,
+    /// Only this time frame, like "15m"
+    #[arg(long)]
+    time_frame: Option<String>,
+    /// Only KL which begin at or after timestamp
+    #[arg(long)]
+    since: Option<UnixMillis>,
+    /// Only KL which begin at or before timestamp
+    #[arg(long)]
+    until: Option<UnixMillis>,
+}
+
+impl From<SeriesArgs> for KlineFilter {
+    fn from(args: SeriesArgs) -> Self {
+        let SeriesArgs {
+            exchange,
+            pair,
+            time_frame,
+            since,
+            until,
+        } = args;
+        Self {
+            exchange,
+            pair,
+            time_frame,
+            since,
+            until,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub(crate) enum ExportSchema {
+    /// KL as in the test task spec
+    V1,
+    /// KL as stored
+    V2,
+}
+
+#[derive(Debug, clap::Args)]
+pub(crate) struct ExportArgs {
+    #[clap(flatten)]
+    series: SeriesArgs,
+    #[arg(long, value_enum, default_value_t = ExportSchema::V2)]
+    schema: ExportSchema,
+    /// Write JSON lines to file instead of stdout
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+async fn next<T: DeserializeOwned + Send + Sync>(
+    cursor: &mut Cursor<T>,
+) -> anyhow::Result<Option<T>> {
+    if !cursor
+        .advance()
+        .await
+        .context("fetch record from storage")?
+    {
+        return Ok(None);
+    }
+    cursor
+        .deserialize_current()
+        .map(Some)
+        .context("parse record from storage")
+}
+
+/// Checks stored KL and RT of exchange with the same rules as incoming ones
+pub(crate) async fn verify<E: Exchange>(storage: &Storage, exchange: &E) -> anyhow::Result<()> {
+    let context = exchange.context();
+    let name = exchange.name();
+
+    let filter = KlineFilter {
+        exchange: Some(name.into()),
+        ..Default::default()
+    };
+    let mut cursor = storage.klines(&filter).await?;
+    let mut report = VerifyReport::default();
+    while let Some(kline) = next(&mut cursor).await? {
+        report.check(&kline, context);
+    }
+    report.print(&format!("{name} klines"));
+
+    let mut cursor = storage.recent_trades(Some(name)).await?;
+    let mut report = VerifyReport::default();
+    while let Some(recent_trade) = next(&mut cursor).await? {
+        report.check(&recent_trade, context);
+    }
+    report.print(&format!("{name} recent trades"));
+    Ok(())
+}
+
+#[derive(Default)]
+struct VerifyReport {
+    total: u64,
+    invalid: u64,
+    issues: BTreeMap<String, u64>,
+}
+
+impl VerifyReport {
+    fn check<C, T: Validate<C>>(&mut self, record: &T, context: &C) {
+        let mut issues = vec![];
+        record.validate(context, &mut issues);
+        self.total += 1;
+        if !issues.is_empty() {
+            self.invalid += 1;
+        }
+        for issue in issues {
+            *self.issues.entry(issue.to_string()).or_default() += 1;
+        }
+    }
+
+    fn print(&self, kind: &str) {
+        println!("{kind}: {} total, {} invalid", self.total, self.invalid);
+        for (issue, count) in &self.issues {
+            println!("    {issue}: {count}");
+        }
+    }
+}
+
+pub(crate) async fn export(storage: &Storage, args: ExportArgs) -> anyhow::Result<()> {
+    let ExportArgs {
+        series,
+        schema,
+        output,
+    } = args;
+    let mut out: Box<dyn Write> = match output {
+        Some(path) => Box::new(BufWriter::new(
+            File::create(&path).with_context(|| format!("create {}", path.display()))?,
+        )),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    let mut cursor = storage.klines(&series.into()).await?;
+    let mut exported = 0;
+    while let Some(kline) = next(&mut cursor).await? {
+        match schema {
+            ExportSchema::V1 => serde_json::to_writer(&mut out, &KlineV1::from(&kline)),
+            ExportSchema::V2 => serde_json::to_writer(&mut out, &kline),
+        }
+        .context("write kline as JSON")?;
+        out.write_all(b"\n").context("write line break")?;
+        exported += 1;
+    }
+    out.flush().context("flush export")?;
+    log::info!("Exported {exported} klines");
+    Ok(())
+}
+
+/// Prints ranges without KL between the first and the last stored KL of each exchange, pair and time frame
+pub(crate) async fn gaps(storage: &Storage, args: SeriesArgs) -> anyhow::Result<()> {
+    let mut cursor = storage.klines(&args.into()).await?;
+    let mut previous: Option<Kline> = None;
+    let mut total_gaps = 0;
+    while let Some(kline) = next(&mut cursor).await? {
+        if let Some(previous) = previous.as_ref().filter(|prev| {
+            prev.exchange == kline.exchange
+                && prev.pair == kline.pair
+                && prev.time_frame == kline.time_frame
+        }) {
+            // begin of the next KL follows end of the previous one, whatever the interval is
+            let expected = previous.utc_end.saturating_add(Duration::from_millis(1));
+            if kline.utc_begin > expected {
+                println!(
+                    "{} {} {}: {} - {}",
+                    kline.exchange,
+                    kline.pair,
+                    kline.time_frame,
+                    expected.display(),
+                    kline
+                        .utc_begin
+                        .saturating_sub(Duration::from_millis(1))
+                        .display()
+                );
+                total_gaps += 1;
+            }
+        }
+        previous = Some(kline);
+    }
+    log::info!("Found {total_gaps} gaps");
+    Ok(())
+}
+
+pub(crate) async fn stats(storage: &Storage) -> anyhow::Result<()> {
+    for (name, count) in storage.counts().await? {
+        println!("{name}: {count}");
+    }
+    for series in storage.kline_series().await? {
+        println!(
+            "{} {} {}: {} klines, {} - {}",
+            series.exchange,
+            series.pair,
+            series.time_frame,
+            series.count,
+            series.first.display(),
+            series.last.display()
+        );
+    }
+    Ok(())
+}
@@ -1,38 +1,190 @@
-use std::collections::BTreeMap;
+use std::{future::Future, path::PathBuf, pin::Pin, sync::Arc};
 
 use anyhow::Context;
-use bitsgap_poloniex::{
-    context::PoloniexContext,
-    ws::{channels::Channel, intervals::WsCandlesChannels},
-};
+use axum::Router;
+use bitsgap_binance::{context::BinanceContext, exchange::Binance};
+use bitsgap_poloniex::{context::PoloniexContext, exchange::Poloniex};
 use bitsgap_shared::{
-    ApiConfig, ApiFactory, AuthMethod, HttpConfig,
-    utils::{Has, time::timestamp_parse},
+    ApiConfig, ApiFactory, ApiRequester, AuthMethod, HttpConfig,
+    exchange::Exchange,
+    journal::JournalConfig,
+    utils::time::{SpanDuration, UnixMillis},
+    validation::{ValidationPolicy, Validator},
+    ws::Uri,
 };
-use clap::Parser;
+use clap::{Parser, ValueEnum as _};
+use control::{Control, ControlRequest, DesiredSubscriptions};
+use dead_letters::DeadLettersCommand;
+use download::Downloader;
+use health::Health;
+use inspect::{ExportArgs, SeriesArgs};
 use storage::Storage;
+use telemetry::ServeArgs;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use watchdog::WatchdogArgs;
 
+mod control;
+mod dead_letters;
 mod download;
+mod health;
+mod inspect;
+mod replay;
+mod shutdown;
 mod storage;
 mod stream;
+mod telemetry;
+mod validate;
+mod watchdog;
 
 #[derive(Debug, Parser)]
 struct Config {
-    #[arg(env, long)]
-    api_key: String,
-    #[arg(env, long)]
-    secret_key: String,
     /// MongoDB URI
     #[arg(env, long)]
     mongodb_uri: String,
+    /// After SIGINT or SIGTERM, how long to wait for current work to wind down
+    #[arg(long, default_value = "10s")]
+    shutdown_deadline: SpanDuration,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum Command {
+    /// Download historic KL (and RT) and exit
+    Backfill {
+        #[clap(flatten)]
+        api: ApiArgs,
+        #[clap(flatten)]
+        serve: ServeArgs,
+        #[clap(flatten)]
+        backfill: BackfillArgs,
+        #[clap(flatten)]
+        validation: ValidationArgs,
+    },
+    /// Stream real-time KL and RT until stopped
+    Stream {
+        #[clap(flatten)]
+        api: ApiArgs,
+        #[clap(flatten)]
+        serve: ServeArgs,
+        #[clap(flatten)]
+        stream: StreamArgs,
+        #[clap(flatten)]
+        validation: ValidationArgs,
+    },
+    /// Backfill, then stream
+    Run {
+        #[clap(flatten)]
+        api: ApiArgs,
+        #[clap(flatten)]
+        serve: ServeArgs,
+        #[clap(flatten)]
+        backfill: BackfillArgs,
+        #[clap(flatten)]
+        stream: StreamArgs,
+        #[clap(flatten)]
+        validation: ValidationArgs,
+    },
+    /// Check stored KL and RT of every exchange with the same rules as incoming ones
+    Verify,
+    /// Write stored KL as JSON lines
+    Export(ExportArgs),
+    /// Find missing KL between stored ones
+    Gaps(SeriesArgs),
+    /// Count stored records
+    Stats,
+    /// Messages which failed to parse or convert
+    DeadLetters {
+        #[command(subcommand)]
+        command: DeadLettersCommand,
+        #[clap(flatten)]
+        validation: ValidationArgs,
+    },
+    /// Feed journal of raw WS messages through the same pipeline as live data
+    Replay {
+        /// Exchange which wrote the journal, files of other exchanges are skipped
+        #[arg(long, value_enum, default_value_t = ExchangeName::Poloniex)]
+        exchange: ExchangeName,
+        /// Journal files or directories
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        #[clap(flatten)]
+        validation: ValidationArgs,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+enum ExchangeName {
+    Poloniex,
+    Binance,
+}
+
+#[derive(Debug, clap::Args)]
+struct ApiArgs {
+    /// Exchanges to scrape concurrently, each with its own HTTP client, WS connection and symbols
+    #[arg(
+        env,
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_value = "poloniex"
+    )]
+    exchanges: Vec<ExchangeName>,
+    /// Poloniex API key, public endpoints don't need it
+    #[arg(env, long, requires = "secret_key")]
+    api_key: Option<String>,
+    #[arg(env, long, requires = "api_key")]
+    secret_key: Option<String>,
+    #[clap(flatten)]
+    http_config: HttpConfig,
+}
+
+#[derive(Debug, clap::Args)]
+struct BackfillArgs {
     /// Download KL since timestamp
     #[arg(long)]
-    since: String,
+    since: UnixMillis,
     /// Download KL limit per interval
     #[arg(long = "download-limit")]
     download_limit_per_interval: Option<u32>,
+    /// Download latest historic RT since timestamp too
+    #[arg(long)]
+    backfill_trades: bool,
+    /// Drop KL, RT and quarantine from previous runs first
+    #[arg(long)]
+    reset: bool,
+}
+
+#[derive(Debug, clap::Args)]
+struct StreamArgs {
+    /// Replace KL closed in stream with the final one from REST API
+    #[arg(long)]
+    confirm_closed_klines: bool,
+    /// Stop after storing this many records from stream
+    #[arg(long)]
+    stop_after: Option<usize>,
     #[clap(flatten)]
-    http_config: HttpConfig,
+    journal: JournalConfig,
+    #[clap(flatten)]
+    watchdog: WatchdogArgs,
+    /// History of pairs subscribed at runtime is downloaded for this long
+    #[arg(long, default_value = "1d")]
+    backfill_added_pairs: SpanDuration,
+}
+
+/// What `backfill`, `stream` and `run` do with each exchange
+struct Scrape {
+    backfill: Option<BackfillArgs>,
+    stream: Option<StreamArgs>,
+    validation_policy: ValidationPolicy,
+}
+
+#[derive(Debug, clap::Args)]
+struct ValidationArgs {
+    /// What to do with KL and RT which failed validation
+    #[arg(long, value_enum, default_value_t = ValidationPolicy::Quarantine)]
+    validation_policy: ValidationPolicy,
 }
 
 #[tokio::main]
@@ -42,80 +194,439 @@ async fn main() -> anyhow::Result<()> {
         .parse_default_env()
         .init();
     let Config {
-        api_key,
-        secret_key,
         mongodb_uri,
-        since,
-        download_limit_per_interval,
-        http_config,
+        shutdown_deadline,
+        command,
     } = Config::parse();
-    let since = timestamp_parse(&since)?;
 
-    let storage = Storage::init(&mongodb_uri).await.context("init storage")?;
+    let shutdown = shutdown::on_signal();
+    let storage = Storage::connect(&mongodb_uri)
+        .await
+        .context("connect to storage")?;
 
-    scrap_poloniex(
-        api_key,
-        secret_key,
-        storage,
-        since,
-        download_limit_per_interval,
-        http_config,
+    let res = shutdown::with_deadline(
+        &shutdown,
+        shutdown_deadline.into(),
+        run_command(command, &storage, &shutdown),
     )
-    .await
+    .await;
+    if shutdown.is_cancelled() {
+        match &res {
+            Ok(()) => log::info!("Shut down gracefully"),
+            Err(err) => log::error!("Shut down with error: {err:#}"),
+        }
+    }
+    res
 }
 
-async fn scrap_poloniex(
-    api_key: String,
-    secret_key: String,
-    storage: Storage,
-    since: u64,
-    download_limit_per_interval: Option<u32>,
-    http_config: HttpConfig,
+async fn run_command(
+    command: Command,
+    storage: &Storage,
+    shutdown: &CancellationToken,
 ) -> anyhow::Result<()> {
-    // TODO: move to config
-    let base_url = "https://api.poloniex.com"
-        .try_into()
-        .context("parse exchange api url")?;
+    match command {
+        Command::Backfill {
+            api,
+            serve,
+            backfill,
+            validation,
+        } => {
+            let scrape = Scrape {
+                backfill: Some(backfill),
+                stream: None,
+                validation_policy: validation.validation_policy,
+            };
+            scrape_exchanges(api, serve, &scrape, storage, shutdown).await
+        }
+        Command::Stream {
+            api,
+            serve,
+            stream,
+            validation,
+        } => {
+            let scrape = Scrape {
+                backfill: None,
+                stream: Some(stream),
+                validation_policy: validation.validation_policy,
+            };
+            scrape_exchanges(api, serve, &scrape, storage, shutdown).await
+        }
+        Command::Run {
+            api,
+            serve,
+            backfill,
+            stream,
+            validation,
+        } => {
+            let scrape = Scrape {
+                backfill: Some(backfill),
+                stream: Some(stream),
+                validation_policy: validation.validation_policy,
+            };
+            scrape_exchanges(api, serve, &scrape, storage, shutdown).await
+        }
+        Command::Verify => {
+            for name in ExchangeName::value_variants() {
+                match name {
+                    ExchangeName::Poloniex => {
+                        inspect::verify(storage, &offline_poloniex().await?).await?
+                    }
+                    ExchangeName::Binance => {
+                        inspect::verify(storage, &offline_binance().await?).await?
+                    }
+                }
+            }
+            Ok(())
+        }
+        Command::Export(args) => inspect::export(storage, args).await,
+        Command::Gaps(args) => inspect::gaps(storage, args).await,
+        Command::Stats => inspect::stats(storage).await,
+        Command::DeadLetters {
+            command: DeadLettersCommand::List { limit },
+            ..
+        } => dead_letters::list(storage, limit).await,
+        Command::DeadLetters {
+            command: DeadLettersCommand::Reprocess { limit },
+            validation,
+        } => {
+            let policy = validation.validation_policy;
+            for name in ExchangeName::value_variants() {
+                match name {
+                    ExchangeName::Poloniex => {
+                        let exchange = offline_poloniex().await?;
+                        dead_letters::reprocess(&exchange, limit, storage, policy, shutdown).await?
+                    }
+                    ExchangeName::Binance => {
+                        let exchange = offline_binance().await?;
+                        dead_letters::reprocess(&exchange, limit, storage, policy, shutdown).await?
+                    }
+                }
+            }
+            Ok(())
+        }
+        Command::Replay {
+            exchange,
+            paths,
+            validation,
+        } => {
+            let policy = validation.validation_policy;
+            match exchange {
+                ExchangeName::Poloniex => {
+                    let exchange = offline_poloniex().await?;
+                    replay::run(&exchange, &paths, storage, policy, shutdown).await
+                }
+                ExchangeName::Binance => {
+                    let exchange = offline_binance().await?;
+                    replay::run(&exchange, &paths, storage, policy, shutdown).await
+                }
+            }
+        }
+    }
+}
 
-    let context = PoloniexContext::init(true).context("init poloniex context")?;
-    let requester = ApiFactory::init(http_config)?.make_requester(
-        ApiConfig {
-            base_url,
-            auth: AuthMethod::HmacSha256 {
-                api_key,
-                secret_key,
-            },
+type ScrapeFuture<'a> = Pin<Box<dyn Future<Output = anyhow::Result<()>> + 'a>>;
+
+/// Runs pipelines of all configured exchanges concurrently, writing into one storage
+async fn scrape_exchanges(
+    api: ApiArgs,
+    serve: ServeArgs,
+    scrape: &Scrape,
+    storage: &Storage,
+    shutdown: &CancellationToken,
+) -> anyhow::Result<()> {
+    // storage is shared, so it's reset once before any exchange writes into it
+    if scrape
+        .backfill
+        .as_ref()
+        .is_some_and(|backfill| backfill.reset)
+    {
+        storage.reset().await.context("reset storage")?;
+    }
+    // failed exchange stops the others, so the whole scraper is restarted
+    let stop = shutdown.child_token();
+    let mut names = api.exchanges.clone();
+    names.sort();
+    names.dedup();
+
+    let mut healths = vec![];
+    let mut subscriptions = Router::new();
+    let mut runs: Vec<ScrapeFuture<'_>> = vec![];
+    for name in names {
+        let run = match name {
+            ExchangeName::Poloniex => scrape_exchange(
+                poloniex(&api).await?,
+                scrape,
+                storage,
+                &stop,
+                &mut healths,
+                &mut subscriptions,
+            ),
+            ExchangeName::Binance => scrape_exchange(
+                binance(&api).await?,
+                scrape,
+                storage,
+                &stop,
+                &mut healths,
+                &mut subscriptions,
+            ),
+        };
+        runs.push(run);
+    }
+    telemetry::serve(
+        serve,
+        healths,
+        storage.clone(),
+        subscriptions,
+        shutdown.clone(),
+    )
+    .await?;
+
+    futures::future::join_all(runs).await.into_iter().collect()
+}
+
+/// Registers health and subscriptions of exchange, returns its backfill and stream to run
+fn scrape_exchange<'a, E: Exchange + 'static>(
+    exchange: E,
+    scrape: &'a Scrape,
+    storage: &'a Storage,
+    stop: &'a CancellationToken,
+    healths: &mut Vec<(&'static str, Arc<Health>)>,
+    subscriptions: &mut Router,
+) -> ScrapeFuture<'a> {
+    let health = Arc::new(Health::default());
+    healths.push((exchange.name(), health.clone()));
+    let requests = scrape.stream.as_ref().map(|_| {
+        let (control, requests) = Control::new();
+        let routes = telemetry::subscriptions_routes(&exchange, control);
+        *subscriptions = std::mem::take(subscriptions).merge(routes);
+        requests
+    });
+
+    Box::pin(async move {
+        let name = exchange.name();
+        let validator = Validator::new(scrape.validation_policy);
+        let downloader = Downloader {
+            exchange: &exchange,
+            storage,
+            validator: &validator,
+            health: &health,
+            shutdown: stop,
+        };
+        let mut res = Ok(());
+        if let Some(backfill) = &scrape.backfill {
+            res = backfill_history(&downloader, backfill).await;
+        }
+        if let Some((stream, requests)) = scrape.stream.as_ref().zip(requests) {
+            if res.is_ok() && !stop.is_cancelled() {
+                res = stream_live(&downloader, stream, requests).await;
+            }
+        }
+        if res.is_err() {
+            stop.cancel();
+        }
+        res.with_context(|| format!("scrape {name}"))
+    })
+}
+
+fn make_requester<C>(
+    api: &ApiArgs,
+    base_url: &str,
+    auth: AuthMethod,
+    context: C,
+) -> anyhow::Result<ApiRequester<C>> {
+    let base_url = base_url.try_into().context("parse exchange api url")?;
+    // every exchange has its own HTTP client
+    let factory = ApiFactory::init(api.http_config.clone())?;
+    Ok(factory.make_requester(ApiConfig { base_url, auth }, context))
+}
+
+// TODO: move to config
+const POLONIEX_API_URL: &str = "https://api.poloniex.com";
+const BINANCE_API_URL: &str = "https://api.binance.com";
+
+async fn poloniex(api: &ApiArgs) -> anyhow::Result<Poloniex> {
+    let auth = match (&api.api_key, &api.secret_key) {
+        (Some(api_key), Some(secret_key)) => AuthMethod::HmacSha256 {
+            api_key: api_key.clone(),
+            secret_key: secret_key.clone(),
         },
-        context,
-    );
+        _ => AuthMethod::None,
+    };
+    let context = PoloniexContext::init(true).context("init poloniex context")?;
+    let requester = make_requester(api, POLONIEX_API_URL, auth, context)?;
+    let mut poloniex = Poloniex::new(requester, poloniex_ws_uri()?);
+    // so subscriptions can be changed to pairs outside of test task
+    if let Err(err) = poloniex.load_symbols().await {
+        log::warn!("Only pairs of test task are known to poloniex: {err:#}");
+    }
+    Ok(poloniex)
+}
+
+async fn binance(api: &ApiArgs) -> anyhow::Result<Binance> {
+    let context = BinanceContext::init(true).context("init binance context")?;
+    let requester = make_requester(api, BINANCE_API_URL, AuthMethod::None, context)?;
+    let mut binance = Binance::new(requester, binance_ws_uri()?);
+    if let Err(err) = binance.load_symbols().await {
+        log::warn!("Only pairs of test task are known to binance: {err:#}");
+    }
+    Ok(binance)
+}
+
+/// Requester of public endpoints, for offline exchanges to know every market
+fn public_requester<C>(base_url: &str, context: C) -> anyhow::Result<ApiRequester<C>> {
+    let config = ApiConfig {
+        base_url: base_url.try_into().context("parse exchange api url")?,
+        auth: AuthMethod::None,
+    };
+    Ok(ApiFactory::init(HttpConfig::default())?.make_requester(config, context))
+}
+
+fn poloniex_ws_uri() -> anyhow::Result<Uri> {
+    bitsgap_poloniex::ws::PUBLIC_WS_URI
+        .parse()
+        .context("parse poloniex ws uri")
+}
+
+fn binance_ws_uri() -> anyhow::Result<Uri> {
+    bitsgap_binance::ws::PUBLIC_WS_URI
+        .parse()
+        .context("parse binance ws uri")
+}
 
-    let symbols = bitsgap_poloniex::TEST_TASK_SYMBOLS;
+/// Normalizes stored messages only, no API keys needed.
+/// Markets are still loaded, messages of pairs subscribed at runtime can't be normalized without them.
+async fn offline_poloniex() -> anyhow::Result<Poloniex> {
+    let init = || PoloniexContext::init(true).context("init poloniex context");
+    let mut context = init()?;
+    match bitsgap_poloniex::symbols::load_symbols(&public_requester(POLONIEX_API_URL, init()?)?)
+        .await
+    {
+        Ok(symbols) => context.set_exchange_symbols(symbols),
+        Err(err) => log::warn!("Only pairs of test task are known to poloniex: {err:#}"),
+    }
+    Ok(Poloniex::offline(context, poloniex_ws_uri()?))
+}
 
-    download::poloniex_klines(
-        &requester,
-        &storage,
-        symbols,
+async fn offline_binance() -> anyhow::Result<Binance> {
+    let init = || BinanceContext::init(true).context("init binance context");
+    let mut context = init()?;
+    match bitsgap_binance::symbols::load_symbols(&public_requester(BINANCE_API_URL, init()?)?).await
+    {
+        Ok(symbols) => context.set_exchange_symbols(symbols),
+        Err(err) => log::warn!("Only pairs of test task are known to binance: {err:#}"),
+    }
+    Ok(Binance::offline(context, binance_ws_uri()?))
+}
+
+async fn backfill_history<E: Exchange>(
+    downloader: &Downloader<'_, E>,
+    backfill: &BackfillArgs,
+) -> anyhow::Result<()> {
+    let &BackfillArgs {
         since,
-        500,
         download_limit_per_interval,
-    )
-    .await
-    .context("download klines")?;
-
-    let context = requester.context();
-
-    // TODO: use SortedVec?
-    let mut channels = BTreeMap::new();
-    channels.extend(
-        context
-            .give(WsCandlesChannels)
-            .iter()
-            .map(|(interval, alias)| (alias.to_string(), Channel::Candles(interval))),
-    );
-    channels.insert("trades".into(), Channel::Trades);
+        backfill_trades,
+        // storage of all exchanges is reset before
+        reset: _,
+    } = backfill;
+    let pairs = downloader.exchange.default_pairs();
+    let trades_series = if backfill_trades { pairs.len() } else { 0 };
+    downloader
+        .health
+        .backfill_started(downloader.klines_series(&pairs) + trades_series);
 
-    stream::dump_events(requester.context(), &storage, None, channels, symbols)
+    downloader
+        .klines(&pairs, since, download_limit_per_interval)
         .await
-        .context("dump events")?;
+        .context("download klines")?;
+
+    if backfill_trades && !downloader.shutdown.is_cancelled() {
+        downloader
+            .trades(&pairs, since)
+            .await
+            .context("download trades")?;
+    }
+    if !downloader.shutdown.is_cancelled() {
+        downloader.health.backfill_finished();
+    }
     Ok(())
 }
+
+async fn stream_live<E: Exchange>(
+    downloader: &Downloader<'_, E>,
+    stream: &StreamArgs,
+    requests: mpsc::Receiver<ControlRequest<E::Channel>>,
+) -> anyhow::Result<()> {
+    let StreamArgs {
+        confirm_closed_klines,
+        stop_after,
+        journal,
+        watchdog,
+        backfill_added_pairs,
+    } = stream;
+    let exchange = downloader.exchange;
+    let desired = match downloader
+        .storage
+        .desired_subscriptions(exchange.name())
+        .await?
+    {
+        Some(desired) => {
+            log::info!(
+                "Stream subscriptions of {} changed at runtime are restored: {desired:?}",
+                exchange.name()
+            );
+            desired
+        }
+        None => DesiredSubscriptions {
+            channels: exchange.channels().into_iter().collect(),
+            pairs: exchange.default_pairs().into_iter().collect(),
+        },
+    };
+    let mut pipeline = stream::Pipeline::new(
+        exchange,
+        downloader.storage,
+        downloader.validator,
+        *confirm_closed_klines,
+        downloader.health,
+    );
+    let (backfill, mut added_pairs) = mpsc::unbounded_channel();
+    let control = stream::StreamControl {
+        desired,
+        requests,
+        backfill,
+        pending: Default::default(),
+    };
+
+    // stream keeps going while added pairs are backfilled; channel is closed once stream stops
+    let backfill_added = async {
+        while let Some(pairs) = added_pairs.recv().await {
+            let since = UnixMillis::now().saturating_sub((*backfill_added_pairs).into());
+            downloader
+                .health
+                .backfill_started(downloader.klines_series(&pairs));
+            match downloader.klines(&pairs, since, None).await {
+                Ok(()) if !downloader.shutdown.is_cancelled() => {
+                    downloader.health.backfill_finished()
+                }
+                Ok(()) => {}
+                Err(err) => {
+                    log::error!("Can't backfill added pairs {pairs:?}: {err:#}");
+                    // stream is fine, so scraper isn't restarted over it
+                    downloader.health.backfill_failed(format!("{err:#}"));
+                }
+            }
+        }
+    };
+    let (res, ()) = tokio::join!(
+        stream::dump_events(
+            &mut pipeline,
+            *stop_after,
+            Some(journal.clone()),
+            watchdog,
+            control,
+            downloader.shutdown,
+        ),
+        backfill_added
+    );
+    res.context("dump events")
+}
@@ -0,0 +1,70 @@
+use std::path::PathBuf;
+
+use bitsgap_shared::{
+    exchange::{Exchange, MarketEvent},
+    journal::{JournalEntry, journal_files, read_journal},
+    validation::{ValidationPolicy, Validator},
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::{health::Health, storage::Storage, stream::Pipeline};
+
+pub(crate) async fn run<E: Exchange>(
+    exchange: &E,
+    paths: &[PathBuf],
+    storage: &Storage,
+    validation_policy: ValidationPolicy,
+    shutdown: &CancellationToken,
+) -> anyhow::Result<()> {
+    let validator = Validator::new(validation_policy);
+    let health = Health::default();
+    let mut pipeline = Pipeline::new(exchange, storage, &validator, false, &health);
+
+    let mut files = journal_files(paths)?;
+    // journal directory may be shared by streams of several exchanges
+    let prefix = format!("{}-", exchange.name());
+    files.retain(|file| {
+        let own = file
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with(&prefix));
+        if !own {
+            log::warn!("Skip journal file {} of another exchange", file.display());
+        }
+        own
+    });
+    let mut stored = 0;
+    'files: for file in &files {
+        log::info!("Replay journal file {}", file.display());
+        for entry in read_journal(file)? {
+            if shutdown.is_cancelled() {
+                log::info!("Replay is interrupted by shutdown");
+                break 'files;
+            }
+            let JournalEntry { received_at, raw } = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    log::error!("[{}] {err:#}", file.display());
+                    break;
+                }
+            };
+            // same normalization as live stream
+            let mut events = exchange.parse_events(&raw, None);
+            events.retain(|event| match event {
+                MarketEvent::Error { message, .. } => {
+                    log::warn!("Journal has error event: {message}");
+                    false
+                }
+                _ => true,
+            });
+            stored += pipeline.handle_events(events, received_at).await?;
+        }
+    }
+    log::info!(
+        "Replayed {} journal files of {}, stored {stored} records, {} dead letters; validation: {}",
+        files.len(),
+        exchange.name(),
+        pipeline.dead_letters(),
+        validator.counters()
+    );
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use std::{future::Future, time::Duration};
+
+use anyhow::Context as _;
+use tokio::time::timeout;
+use tokio_util::sync::CancellationToken;
+
+/// Token which is cancelled on SIGINT or SIGTERM
+pub(crate) fn on_signal() -> CancellationToken {
+    let token = CancellationToken::new();
+    tokio::spawn({
+        let token = token.clone();
+        async move {
+            match wait_signal().await {
+                Ok(signal) => log::info!("Received {signal}, shutting down..."),
+                Err(err) => {
+                    log::error!("Can't listen for shutdown signals: {err:#}");
+                    return;
+                }
+            }
+            token.cancel();
+        }
+    });
+    token
+}
+
+#[cfg(unix)]
+async fn wait_signal() -> anyhow::Result<&'static str> {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut sigterm = signal(SignalKind::terminate()).context("listen for SIGTERM")?;
+    tokio::select! {
+        res = tokio::signal::ctrl_c() => res.context("listen for SIGINT").map(|()| "SIGINT"),
+        _ = sigterm.recv() => Ok("SIGTERM"),
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_signal() -> anyhow::Result<&'static str> {
+    tokio::signal::ctrl_c()
+        .await
+        .context("listen for Ctrl-C")
+        .map(|()| "Ctrl-C")
+}
+
+/// Runs `fut` to completion, but once token is cancelled gives it only `deadline` to wind down
+pub(crate) async fn with_deadline<T>(
+    token: &CancellationToken,
+    deadline: Duration,
+    fut: impl Future<Output = anyhow::Result<T>>,
+) -> anyhow::Result<T> {
+    tokio::pin!(fut);
+    tokio::select! {
+        res = &mut fut => return res,
+        _ = token.cancelled() => {}
+    }
+    timeout(deadline, fut)
+        .await
+        .ok()
+        .context("shutdown deadline exceeded")?
+}
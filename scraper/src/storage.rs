@@ -1,20 +1,90 @@
+use std::time::Instant;
+
 use anyhow::Context as _;
-use bitsgap_shared::records::{kline::Kline, recent_trade::RecentTrade};
+use bitsgap_shared::{
+    pair::Pair,
+    records::{
+        kline::{Kline, KlineStatus},
+        recent_trade::RecentTrade,
+    },
+    utils::time::UnixMillis,
+    validation::Issue,
+};
 use mongodb::{
-    Collection, IndexModel,
-    bson::{self, doc},
+    Collection, Cursor, Database, IndexModel,
+    bson::{self, Document, doc, oid::ObjectId},
+    error::{ErrorKind, InsertManyError, WriteFailure},
     options::IndexOptions,
 };
 
+use crate::control::DesiredSubscriptions;
+
+#[derive(Clone)]
 pub(crate) struct Storage {
+    database: Database,
     klines: Collection<Kline>,
     recent_trades: Collection<RecentTrade>,
+    quarantine: Collection<Document>,
+    dead_letters: Collection<DeadLetter>,
+    // channels are typed per exchange, see `clone_with_type`
+    subscriptions: Collection<StoredSubscriptions<String>>,
 }
 pub(crate) type OneOrMany<T> = smallvec::SmallVec<[T; 1]>;
 
+const DUPLICATE_KEY_CODE: i32 = 11000;
+const NAMESPACE_NOT_FOUND_CODE: i32 = 26;
+const INDEX_NOT_FOUND_CODE: i32 = 27;
+
+/// Records stored before exchange was a part of them all came from Poloniex
+const LEGACY_EXCHANGE: &str = "poloniex";
+/// Recent trades stored before schema was a part of them, `amount` of Poloniex WS ones is in quote units
+const LEGACY_RECENT_TRADE_SCHEMA: u16 = 1;
+/// Pairs stored before `Pair` was introduced are Poloniex symbols like "BTC_USDT"
+const LEGACY_PAIR_SEPARATOR: char = '_';
+/// Collections and fields holding pairs, dead letters keep raw exchange messages and have none
+const LEGACY_PAIR_FIELDS: [(&str, &str); 3] = [
+    ("klines", "pair"),
+    ("recent_trades", "pair"),
+    ("quarantine", "record.pair"),
+];
+/// Unique indices without exchange, they would reject the same pair of another exchange
+const LEGACY_INDICES: [(&str, &str); 2] = [
+    ("klines", "pair_1_time_frame_1_utc_begin_1"),
+    ("recent_trades", "pair_1_tid_1"),
+];
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(bound(deserialize = "C: Ord + serde::Deserialize<'de>"))]
+struct StoredSubscriptions<C> {
+    /// public stream of exchange, like "poloniex-public"
+    #[serde(rename = "_id")]
+    id: String,
+    #[serde(flatten)]
+    desired: DesiredSubscriptions<C>,
+}
+
+fn desired_subscriptions_id(exchange: &str) -> String {
+    format!("{exchange}-public")
+}
+
+/// Message from exchange which couldn't be parsed or converted into a record
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub(crate) struct DeadLetter {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub(crate) id: Option<ObjectId>,
+    /// exchange which sent the message, like "poloniex"
+    pub(crate) exchange: String,
+    /// message text, or a single event of stream message if channel is set
+    pub(crate) raw: String,
+    pub(crate) channel: Option<String>,
+    /// error with its causes, outermost first
+    pub(crate) errors: Vec<String>,
+    pub(crate) received_at: UnixMillis,
+}
+
 // Mongo is temporary solution, no need for excessive abstractions and refactoring into shared crate
 impl Storage {
-    pub(crate) async fn init(mongodb_uri: &str) -> anyhow::Result<Self> {
+    pub(crate) async fn connect(mongodb_uri: &str) -> anyhow::Result<Self> {
         let mongo_options = mongodb::options::ClientOptions::parse(mongodb_uri)
             .await
             .context("parse and resolve mongodb uri")?;
@@ -25,51 +95,200 @@ impl Storage {
             .context("default database is not set")?;
         //let database = mongo_client.database("bitsgap_qthree_test");
 
-        // Clean up data from last run
-        database.drop().await.context("drop database")?;
+        let storage = Storage {
+            klines: database.collection("klines"),
+            recent_trades: database.collection("recent_trades"),
+            quarantine: database.collection("quarantine"),
+            dead_letters: database.collection("dead_letters"),
+            subscriptions: database.collection("subscriptions"),
+            database,
+        };
+        storage.migrate().await?;
+        storage.create_indices().await?;
+        Ok(storage)
+    }
 
+    /// Marks records of previous versions with the exchange and schema, drops indices without exchange
+    async fn migrate(&self) -> anyhow::Result<()> {
+        for name in ["klines", "recent_trades", "dead_letters"] {
+            let res = self
+                .database
+                .collection::<Document>(name)
+                .update_many(
+                    doc! {"exchange": {"$exists": false}},
+                    doc! {"$set": {"exchange": LEGACY_EXCHANGE}},
+                )
+                .await
+                .with_context(|| format!("set exchange of legacy {name}"))?;
+            if res.modified_count > 0 {
+                log::info!(
+                    "Set exchange of {} legacy {name} to {LEGACY_EXCHANGE}",
+                    res.modified_count
+                );
+            }
+        }
+        let res = self
+            .database
+            .collection::<Document>("recent_trades")
+            .update_many(
+                doc! {"schema": {"$exists": false}},
+                doc! {"$set": {"schema": i32::from(LEGACY_RECENT_TRADE_SCHEMA)}},
+            )
+            .await
+            .context("set schema of legacy recent_trades")?;
+        if res.modified_count > 0 {
+            log::info!(
+                "Set schema of {} legacy recent_trades to {LEGACY_RECENT_TRADE_SCHEMA}",
+                res.modified_count
+            );
+        }
+        for (name, field) in LEGACY_PAIR_FIELDS {
+            self.migrate_legacy_pairs(name, field).await?;
+        }
+        for (name, index) in LEGACY_INDICES {
+            let res = self
+                .database
+                .collection::<Document>(name)
+                .drop_index(index)
+                .await;
+            match res {
+                Ok(()) => log::info!("Dropped legacy index {index} of {name}"),
+                Err(err)
+                    if matches!(
+                        *err.kind,
+                        ErrorKind::Command(ref err)
+                            if matches!(err.code, NAMESPACE_NOT_FOUND_CODE | INDEX_NOT_FOUND_CODE)
+                    ) => {}
+                Err(err) => {
+                    return Err(err)
+                        .with_context(|| format!("drop legacy index {index} of {name}"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Rewrites pairs like "BTC_USDT" into "BTC/USDT".
+    /// Legacy duplicates of records which were already stored with the new pair are deleted.
+    async fn migrate_legacy_pairs(&self, name: &str, field: &str) -> anyhow::Result<()> {
+        let collection = self.database.collection::<Document>(name);
+        let mut cursor = collection
+            .find(doc! {field: {"$regex": LEGACY_PAIR_SEPARATOR.to_string()}})
+            .projection(doc! {field: 1})
+            .await
+            .with_context(|| format!("find legacy pairs of {name}"))?;
+        let (mut updated, mut deleted) = (0, 0);
+        while cursor
+            .advance()
+            .await
+            .with_context(|| format!("fetch legacy pair of {name}"))?
+        {
+            let legacy = cursor
+                .deserialize_current()
+                .with_context(|| format!("parse legacy {name}"))?;
+            let id = legacy
+                .get_object_id("_id")
+                .with_context(|| format!("legacy {name} without id"))?;
+            let symbol = legacy_symbol(&legacy, field)
+                .with_context(|| format!("legacy {name} {id} without {field}"))?;
+            let Some(pair) = legacy_pair(symbol) else {
+                log::warn!("Legacy {name} {id} has unknown pair {symbol:?}, skipped");
+                continue;
+            };
+            let res = collection
+                .update_one(doc! {"_id": id}, doc! {"$set": {field: pair.to_string()}})
+                .await;
+            match res {
+                Ok(_) => updated += 1,
+                Err(err)
+                    if matches!(
+                        *err.kind,
+                        ErrorKind::Write(WriteFailure::WriteError(ref err))
+                            if err.code == DUPLICATE_KEY_CODE
+                    ) =>
+                {
+                    collection
+                        .delete_one(doc! {"_id": id})
+                        .await
+                        .with_context(|| format!("delete duplicate legacy {name} {id}"))?;
+                    deleted += 1;
+                }
+                Err(err) => {
+                    return Err(err).with_context(|| format!("set pair of legacy {name} {id}"));
+                }
+            }
+        }
+        if updated + deleted > 0 {
+            log::info!(
+                "Rewrote pairs of {updated} legacy {name}, deleted {deleted} legacy duplicates"
+            );
+        }
+        Ok(())
+    }
+
+    async fn create_indices(&self) -> anyhow::Result<()> {
         // Index options
         let options = Some(IndexOptions::builder().unique(true).build());
 
-        // Create indices
-        let klines = database.collection("klines");
-        klines
+        self.klines
             .create_index(
                 IndexModel::builder()
-                    .keys(doc! { "pair": 1, "time_frame": 1, "utc_begin": 1})
+                    .keys(doc! { "exchange": 1, "pair": 1, "time_frame": 1, "utc_begin": 1})
                     .options(options.clone())
                     .build(),
             )
             .await
             .context("create klines index")?;
-        let recent_trades = database.collection("recent_trades");
-        recent_trades
+        self.recent_trades
             .create_index(
                 IndexModel::builder()
-                    .keys(doc! { "pair": 1, "tid": 1})
+                    .keys(doc! { "exchange": 1, "pair": 1, "tid": 1})
                     .options(options.clone())
                     .build(),
             )
             .await
             .context("create recent_trades index")?;
+        Ok(())
+    }
+
+    pub(crate) async fn ping(&self) -> anyhow::Result<()> {
+        self.database
+            .run_command(doc! {"ping": 1})
+            .await
+            .context("ping storage")?;
+        Ok(())
+    }
 
-        Ok(Storage {
-            klines,
-            recent_trades,
-        })
+    /// Cleans up data from last run, but keeps dead letters to reprocess them later
+    pub(crate) async fn reset(&self) -> anyhow::Result<()> {
+        for name in ["klines", "recent_trades", "quarantine"] {
+            self.database
+                .collection::<Document>(name)
+                .drop()
+                .await
+                .with_context(|| format!("drop {name} collection"))?;
+        }
+        // dropping collection drops its indices too
+        self.create_indices().await
     }
 
+    /// Idempotent: klines which are already stored (same exchange, pair, time frame and begin) are skipped.
+    /// Returns number of newly stored klines.
     pub(crate) async fn insert_klines(&self, klines: OneOrMany<Kline>) -> anyhow::Result<usize> {
         let len = klines.len();
-        self.klines
-            .insert_many(&klines)
-            .await
-            .context("insert klines into storage")?;
-        Ok(len)
+        if len == 0 {
+            return Ok(0);
+        }
+        let _timer = WriteTimer::start("insert_klines");
+        let res = self.klines.insert_many(&klines).ordered(false).await;
+        skip_duplicates(len, res).context("insert klines into storage")
     }
 
     pub(crate) async fn upsert_kline(&self, kline: Kline) -> anyhow::Result<usize> {
+        let _timer = WriteTimer::start("upsert_kline");
         let Kline {
+            schema,
+            exchange,
             pair,
             time_frame,
             o,
@@ -77,13 +296,29 @@ impl Storage {
             l,
             c,
             utc_begin,
+            utc_end,
+            utc_record,
+            status,
+            trade_count,
+            vwap,
             volume_bs,
         } = kline;
         let volume_bs = bson::to_bson(&volume_bs).context("volume_bs to bson")?;
+        let status = bson::to_bson(&status).context("status to bson")?;
         self.klines
             .update_one(
-                doc! {"pair": pair, "time_frame": time_frame, "utc_begin": utc_begin},
-                doc! {"$set": {"o": o, "h": h, "l": l, "c": c, "volume_bs": volume_bs}},
+                doc! {
+                    "exchange": exchange,
+                    "pair": pair.to_string(),
+                    "time_frame": time_frame,
+                    "utc_begin": utc_begin,
+                },
+                doc! {"$set": {
+                    "schema": i32::from(schema),
+                    "o": o, "h": h, "l": l, "c": c,
+                    "utc_end": utc_end, "utc_record": utc_record, "status": status,
+                    "trade_count": trade_count, "vwap": vwap, "volume_bs": volume_bs,
+                }},
             )
             .upsert(true)
             .await
@@ -91,22 +326,353 @@ impl Storage {
         Ok(1)
     }
 
+    /// Marks all in-progress klines of pair and time frame which began before `utc_begin` as closed.
+    /// Returns number of klines marked.
+    pub(crate) async fn close_klines_before(
+        &self,
+        exchange: &str,
+        pair: &Pair,
+        time_frame: &str,
+        utc_begin: UnixMillis,
+    ) -> anyhow::Result<u64> {
+        let _timer = WriteTimer::start("close_klines");
+        let in_progress = bson::to_bson(&KlineStatus::InProgress).context("status to bson")?;
+        let closed = bson::to_bson(&KlineStatus::Closed).context("status to bson")?;
+        let res = self
+            .klines
+            .update_many(
+                doc! {
+                    "exchange": exchange,
+                    "pair": pair.to_string(),
+                    "time_frame": time_frame,
+                    "utc_begin": {"$lt": utc_begin},
+                    "status": in_progress,
+                },
+                doc! {"$set": {"status": closed}},
+            )
+            .await
+            .context("mark klines as closed in storage")?;
+        Ok(res.modified_count)
+    }
+
+    /// Idempotent: trades which are already stored (same exchange, pair and tid) are skipped.
+    /// Returns number of newly stored trades.
     pub(crate) async fn insert_recent_trades(
         &self,
         recent_trades: OneOrMany<RecentTrade>,
     ) -> anyhow::Result<usize> {
         let len = recent_trades.len();
-        self.recent_trades
+        if len == 0 {
+            return Ok(0);
+        }
+        let _timer = WriteTimer::start("insert_recent_trades");
+        // unordered, so the rest of batch still gets inserted after duplicate key error
+        let res = self
+            .recent_trades
             .insert_many(&recent_trades)
+            .ordered(false)
+            .await;
+        skip_duplicates(len, res).context("insert recent trades into storage")
+    }
+
+    /// Stores invalid record apart from valid ones, together with validation issues
+    pub(crate) async fn quarantine<T: serde::Serialize>(
+        &self,
+        kind: &str,
+        record: &T,
+        issues: &[Issue],
+    ) -> anyhow::Result<()> {
+        let _timer = WriteTimer::start("quarantine");
+        let record = bson::to_document(record).context("record to bson")?;
+        let issues: Vec<_> = issues.iter().map(ToString::to_string).collect();
+        self.quarantine
+            .insert_one(doc! {
+                "kind": kind,
+                "record": record,
+                "issues": issues,
+                "quarantined_at": UnixMillis::now(),
+            })
             .await
-            .context("insert recent trades into storage")?;
-        Ok(len)
+            .context("insert record into quarantine")?;
+        Ok(())
     }
 
-    pub(crate) async fn count_klines(&self) -> anyhow::Result<u64> {
+    pub(crate) async fn insert_dead_letter(&self, dead_letter: &DeadLetter) -> anyhow::Result<()> {
+        let _timer = WriteTimer::start("insert_dead_letter");
+        self.dead_letters
+            .insert_one(dead_letter)
+            .await
+            .context("insert dead letter into storage")?;
+        Ok(())
+    }
+
+    /// Oldest dead letters first, of all exchanges if `exchange` is not set
+    pub(crate) async fn dead_letters(
+        &self,
+        exchange: Option<&str>,
+        limit: Option<i64>,
+    ) -> anyhow::Result<Vec<DeadLetter>> {
+        let mut find = self
+            .dead_letters
+            .find(exchange_filter(exchange))
+            .sort(doc! {"received_at": 1});
+        if let Some(limit) = limit {
+            find = find.limit(limit);
+        }
+        let mut cursor = find.await.context("find dead letters in storage")?;
+        let mut dead_letters = vec![];
+        while cursor.advance().await.context("fetch dead letter")? {
+            dead_letters.push(cursor.deserialize_current().context("parse dead letter")?);
+        }
+        Ok(dead_letters)
+    }
+
+    pub(crate) async fn delete_dead_letter(&self, id: ObjectId) -> anyhow::Result<()> {
+        self.dead_letters
+            .delete_one(doc! {"_id": id})
+            .await
+            .context("delete dead letter from storage")?;
+        Ok(())
+    }
+
+    pub(crate) async fn desired_subscriptions<C>(
+        &self,
+        exchange: &str,
+    ) -> anyhow::Result<Option<DesiredSubscriptions<C>>>
+    where
+        C: Ord + serde::de::DeserializeOwned + Send + Sync,
+    {
+        let stored = self
+            .subscriptions
+            .clone_with_type::<StoredSubscriptions<C>>()
+            .find_one(doc! {"_id": desired_subscriptions_id(exchange)})
+            .await
+            .context("find desired subscriptions in storage")?;
+        Ok(stored.map(|stored| stored.desired))
+    }
+
+    pub(crate) async fn save_desired_subscriptions<C>(
+        &self,
+        exchange: &str,
+        desired: &DesiredSubscriptions<C>,
+    ) -> anyhow::Result<()>
+    where
+        C: Clone + serde::Serialize + Send + Sync,
+    {
+        let _timer = WriteTimer::start("save_desired_subscriptions");
+        let id = desired_subscriptions_id(exchange);
+        let stored = StoredSubscriptions {
+            id: id.clone(),
+            desired: desired.clone(),
+        };
+        self.subscriptions
+            .clone_with_type::<StoredSubscriptions<C>>()
+            .replace_one(doc! {"_id": id}, &stored)
+            .upsert(true)
+            .await
+            .context("save desired subscriptions to storage")?;
+        Ok(())
+    }
+
+    pub(crate) async fn count_klines(&self, exchange: &str) -> anyhow::Result<u64> {
         self.klines
-            .count_documents(Default::default())
+            .count_documents(doc! {"exchange": exchange})
             .await
             .context("count klines in storage")
     }
+
+    /// Number of documents in each collection
+    pub(crate) async fn counts(&self) -> anyhow::Result<Vec<(&'static str, u64)>> {
+        let mut counts = vec![];
+        for name in ["klines", "recent_trades", "quarantine", "dead_letters"] {
+            let count = self
+                .database
+                .collection::<Document>(name)
+                .count_documents(doc! {})
+                .await
+                .with_context(|| format!("count {name} in storage"))?;
+            counts.push((name, count));
+        }
+        Ok(counts)
+    }
+
+    /// Klines sorted by exchange, pair, time frame and begin time
+    pub(crate) async fn klines(&self, filter: &KlineFilter) -> anyhow::Result<Cursor<Kline>> {
+        let KlineFilter {
+            exchange,
+            pair,
+            time_frame,
+            since,
+            until,
+        } = filter;
+        let mut query = exchange_filter(exchange.as_deref());
+        if let Some(pair) = pair {
+            query.insert("pair", pair.to_string());
+        }
+        if let Some(time_frame) = time_frame {
+            query.insert("time_frame", time_frame);
+        }
+        let mut utc_begin = doc! {};
+        if let Some(since) = since {
+            utc_begin.insert("$gte", *since);
+        }
+        if let Some(until) = until {
+            utc_begin.insert("$lte", *until);
+        }
+        if !utc_begin.is_empty() {
+            query.insert("utc_begin", utc_begin);
+        }
+        self.klines
+            .find(query)
+            .sort(doc! {"exchange": 1, "pair": 1, "time_frame": 1, "utc_begin": 1})
+            .await
+            .context("find klines in storage")
+    }
+
+    pub(crate) async fn recent_trades(
+        &self,
+        exchange: Option<&str>,
+    ) -> anyhow::Result<Cursor<RecentTrade>> {
+        self.recent_trades
+            .find(exchange_filter(exchange))
+            .await
+            .context("find recent trades in storage")
+    }
+
+    /// Stored klines grouped by exchange, pair and time frame
+    pub(crate) async fn kline_series(&self) -> anyhow::Result<Vec<KlineSeries>> {
+        let mut cursor = self
+            .klines
+            .aggregate([
+                doc! {"$group": {
+                    "_id": {"exchange": "$exchange", "pair": "$pair", "time_frame": "$time_frame"},
+                    "count": {"$sum": 1_i64},
+                    "first": {"$min": "$utc_begin"},
+                    "last": {"$max": "$utc_begin"},
+                }},
+                doc! {"$sort": {"_id.exchange": 1, "_id.pair": 1, "_id.time_frame": 1}},
+                doc! {"$replaceWith": {"$mergeObjects": ["$_id", "$$ROOT"]}},
+                doc! {"$unset": "_id"},
+            ])
+            .with_type::<KlineSeries>()
+            .await
+            .context("aggregate kline series in storage")?;
+        let mut series = vec![];
+        while cursor.advance().await.context("fetch kline series")? {
+            series.push(cursor.deserialize_current().context("parse kline series")?);
+        }
+        Ok(series)
+    }
+}
+
+/// Documents of one exchange, or of all of them
+fn exchange_filter(exchange: Option<&str>) -> Document {
+    match exchange {
+        Some(exchange) => doc! {"exchange": exchange},
+        None => doc! {},
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct KlineFilter {
+    pub(crate) exchange: Option<String>,
+    pub(crate) pair: Option<Pair>,
+    pub(crate) time_frame: Option<String>,
+    /// inclusive, by begin time
+    pub(crate) since: Option<UnixMillis>,
+    /// inclusive, by begin time
+    pub(crate) until: Option<UnixMillis>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub(crate) struct KlineSeries {
+    pub(crate) exchange: String,
+    pub(crate) pair: Pair,
+    pub(crate) time_frame: String,
+    pub(crate) count: i64,
+    /// begin time of the first kline
+    pub(crate) first: UnixMillis,
+    /// begin time of the last kline
+    pub(crate) last: UnixMillis,
+}
+
+/// Records duration of storage write on drop, whether it succeeded or not
+struct WriteTimer {
+    op: &'static str,
+    started: Instant,
+}
+
+impl WriteTimer {
+    fn start(op: &'static str) -> Self {
+        Self {
+            op,
+            started: Instant::now(),
+        }
+    }
+}
+
+impl Drop for WriteTimer {
+    fn drop(&mut self) {
+        metrics::histogram!("storage_write_duration_seconds", "op" => self.op)
+            .record(self.started.elapsed());
+    }
+}
+
+/// Value of dotted `field`, like "record.pair"
+fn legacy_symbol<'d>(doc: &'d Document, field: &str) -> Option<&'d str> {
+    let (parents, key) = match field.rsplit_once('.') {
+        Some((parents, key)) => (Some(parents), key),
+        None => (None, field),
+    };
+    let parent = parents
+        .into_iter()
+        .flat_map(|parents| parents.split('.'))
+        .try_fold(doc, |doc, key| doc.get_document(key).ok())?;
+    parent.get_str(key).ok()
+}
+
+/// Pair of legacy Poloniex symbol like "BTC_USDT"
+fn legacy_pair(symbol: &str) -> Option<Pair> {
+    let (base, quote) = symbol.split_once(LEGACY_PAIR_SEPARATOR)?;
+    (!base.is_empty() && !quote.is_empty() && !quote.contains(LEGACY_PAIR_SEPARATOR))
+        .then(|| Pair::new(base, quote))
+}
+
+/// Treats duplicate key errors of unordered `insert_many` as already stored documents
+fn skip_duplicates<T>(len: usize, res: mongodb::error::Result<T>) -> anyhow::Result<usize> {
+    let err = match res {
+        Ok(_) => return Ok(len),
+        Err(err) => err,
+    };
+    match *err.kind {
+        ErrorKind::InsertMany(InsertManyError {
+            write_errors: Some(ref write_errors),
+            write_concern_error: None,
+            ..
+        }) if write_errors
+            .iter()
+            .all(|err| err.code == DUPLICATE_KEY_CODE) =>
+        {
+            Ok(len - write_errors.len())
+        }
+        _ => Err(err.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_pairs() {
+        assert_eq!(legacy_pair("BTC_USDT"), Some(Pair::new("BTC", "USDT")));
+        assert_eq!(legacy_pair("BTC/USDT"), None);
+        assert_eq!(legacy_pair("_USDT"), None);
+        assert_eq!(legacy_pair("A_B_C"), None);
+
+        let doc = doc! {"pair": "BTC_USDT", "record": {"pair": "ETH_USDT"}};
+        assert_eq!(legacy_symbol(&doc, "pair"), Some("BTC_USDT"));
+        assert_eq!(legacy_symbol(&doc, "record.pair"), Some("ETH_USDT"));
+        assert_eq!(legacy_symbol(&doc, "record.tid"), None);
+    }
 }
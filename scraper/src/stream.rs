@@ -1,111 +1,638 @@
-use core::fmt;
 use std::collections::BTreeMap;
 
 use anyhow::{Context as _, bail};
-use bitsgap_poloniex::{
-    context::PoloniexContext,
-    ws::{
-        candles::CandlesMessage,
-        channels::Channel,
-        protocol::{ClientMsg, ServerEvent, ServerMsg, ServerStream},
-        public_ws,
-        trades::TradesMessage,
+use bitsgap_shared::{
+    exchange::{Exchange, MarketEvent, MarketStream, MarketUpdate},
+    interval::Interval,
+    journal::JournalConfig,
+    pair::{ExchangeSymbols, Pair},
+    records::kline::{Kline, KlineStatus},
+    utils::{Has, time::UnixMillis},
+    validation::Validator,
+};
+use tokio::{
+    sync::mpsc,
+    time::{Instant, MissedTickBehavior, interval_at},
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    control::{
+        ControlCommand, ControlRequest, DesiredSubscriptions, PendingChange, PendingChanges,
+        SubscriptionBatch, SubscriptionChange,
     },
+    health::Health,
+    storage::{DeadLetter, OneOrMany, Storage},
+    validate,
+    watchdog::{Watchdog, WatchdogArgs},
 };
 
-use crate::storage::{OneOrMany, Storage};
+/// Subscriptions which can be changed at runtime through `Control`
+pub(crate) struct StreamControl<C> {
+    pub(crate) desired: DesiredSubscriptions<C>,
+    pub(crate) requests: mpsc::Receiver<ControlRequest<C>>,
+    /// Pairs subscribed at runtime, their history is downloaded separately
+    pub(crate) backfill: mpsc::UnboundedSender<Vec<Pair>>,
+    pub(crate) pending: PendingChanges<C>,
+}
 
-// TODO: move partially to poloniex crate
-pub(crate) async fn dump_events(
-    context: &PoloniexContext,
-    storage: &Storage,
+pub(crate) async fn dump_events<E: Exchange>(
+    pipeline: &mut Pipeline<'_, E>,
     total_limit: Option<usize>,
-    channels: BTreeMap<String, Channel>,
-    symbols: &[&str],
+    journal: Option<JournalConfig>,
+    watchdog: &WatchdogArgs,
+    mut control: StreamControl<E::Channel>,
+    shutdown: &CancellationToken,
 ) -> anyhow::Result<()> {
-    let mut client = public_ws()
-        .await
-        .context("connect to poloniex public WebSocket server")?;
+    let exchange = pipeline.exchange.name();
+    let mut stream = pipeline.exchange.stream(journal).await?;
+    metrics::counter!("ws_connections_total", "exchange" => exchange).increment(1);
+    pipeline
+        .health
+        .stream_connected(control.desired.channels.iter().map(ToString::to_string));
 
     {
-        let symbols: Vec<_> = symbols.iter().copied().map(String::from).collect();
-        let channel = channels.keys().cloned().collect();
-        client
-            .send(ClientMsg::Subscribe { channel, symbols })
-            .await
-            .ok()
-            .context("send subscribe")?;
+        let channels: Vec<E::Channel> = control.desired.channels.iter().copied().collect();
+        let pairs: Vec<Pair> = control.desired.pairs.iter().cloned().collect();
+        pipeline.watchdog = Some(Watchdog::new(
+            watchdog,
+            channels.iter().copied(),
+            pairs.clone(),
+            UnixMillis::now(),
+        ));
+        // everything may have been unsubscribed at runtime
+        if !channels.is_empty() && !pairs.is_empty() {
+            stream.subscribe(&channels, &pairs).await?;
+        }
     }
+    let period = watchdog.interval();
+    let mut watchdog_tick = interval_at(Instant::now() + period, period);
+    watchdog_tick.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
     let mut total_stream_messages = 0;
     loop {
-        let msg = client
-            .recv()
-            .await
-            .context("receive message from WS server")?;
-        match msg {
-            ServerMsg::Stream(ServerStream { channel, data }) => {
-                let Some(ch) = channels.get(&channel) else {
-                    log::error!("Unknown channel: {channel}");
-                    continue;
-                };
-                // TODO: compare event's symbol with desired symbols, just in case
-                match ch {
-                    Channel::Candles(interval) => {
-                        let klines: OneOrMany<_> = data
-                            .into_events()
-                            .map(|res| {
-                                res.and_then(|msg: CandlesMessage| {
-                                    msg.kline(*interval, context)
-                                        .context("convert candle to kline")
-                                })
-                            })
-                            .filter_map(log_err(&channel))
-                            .collect();
-                        log::info!("New klines: {klines:?}");
-                        for kline in klines {
-                            total_stream_messages += storage
-                                .upsert_kline(kline)
-                                .await
-                                .context("save kline from stream to storage")?;
+        tokio::select! {
+            update = stream.next() => {
+                let MarketUpdate { received_at, events } = update?;
+                let mut subscriptions = None;
+                for event in &events {
+                    match event {
+                        MarketEvent::Subscribed {
+                            channel,
+                            request: Some(request),
+                        }
+                        | MarketEvent::Unsubscribed {
+                            channel,
+                            request: Some(request),
+                        } => control.pending.acknowledge(*request, *channel),
+                        MarketEvent::Subscriptions(channels) => {
+                            subscriptions = Some(channels.clone())
                         }
+                        MarketEvent::Reconnected => {
+                            metrics::counter!("ws_connections_total", "exchange" => exchange)
+                                .increment(1);
+                            pipeline.health.stream_connected(
+                                control.desired.channels.iter().map(ToString::to_string),
+                            );
+                        }
+                        _ => {}
                     }
-                    Channel::Trades => {
-                        let recent_trades = data
-                            .into_events()
-                            .filter_map(log_err(&channel))
-                            .map(|msg: TradesMessage| msg.recent_trade())
-                            .collect();
-                        log::info!("New recent trades: {recent_trades:?}");
-                        total_stream_messages += storage
-                            .insert_recent_trades(recent_trades)
-                            .await
-                            .context("save recent trades from stream to storage")?;
+                }
+                total_stream_messages += pipeline.handle_events(events, received_at).await?;
+                let watchdog = pipeline.watchdog.as_ref();
+                if let Some((subscriptions, watchdog)) = subscriptions.zip(watchdog) {
+                    let missing = watchdog.missing(&subscriptions);
+                    if !missing.is_empty() {
+                        log::warn!(
+                            "[{exchange}] WS server has no subscriptions of {missing:?}, resubscribe"
+                        );
+                        count_resubscriptions(exchange, &missing, "missing");
+                        stream.subscribe(&missing, watchdog.pairs()).await?;
                     }
                 }
+                if matches!(
+                    total_limit,
+                    Some(total_limit) if total_stream_messages >= total_limit
+                ) {
+                    break;
+                }
             }
-            ServerMsg::Event(ServerEvent::Error { message }) => {
-                bail!("WS server sent error event: {message:?}");
-            }
-            ServerMsg::Event(event) => {
-                log::info!("WS server sent event: {event:?}");
+            _ = watchdog_tick.tick() => {
+                let stale = match &mut pipeline.watchdog {
+                    Some(watchdog) => watchdog.take_stale(UnixMillis::now()),
+                    None => Default::default(),
+                };
+                for (channel, pairs) in stale {
+                    log::warn!(
+                        "[{exchange} {channel}] No events of {pairs:?} for too long, resubscribe"
+                    );
+                    count_resubscriptions(exchange, &[channel], "stale");
+                    // server may still think stale ones are fine, so they're unsubscribed first
+                    stream.resubscribe(&[channel], &pairs).await?;
+                }
+                // reply is reconciled with channels we track
+                stream.list_subscriptions().await?;
             }
-            ServerMsg::Subscriptions { subscriptions } => {
-                log::info!("WS server sent subscriptions: {subscriptions:?}");
+            Some(request) = control.requests.recv() => {
+                change_subscriptions(pipeline, &mut stream, &mut control, request).await?;
             }
+            _ = shutdown.cancelled() => break,
         }
-        if matches!(total_limit, Some(total_limit) if total_stream_messages >= total_limit) {
-            break;
+    }
+
+    // records are stored as they come, so only connection needs to be wound down
+    stream.close().await;
+    pipeline.health.stream_disconnected();
+    log::info!(
+        "Stream of {exchange} is stopped, stored {total_stream_messages} records, {} dead letters; validation: {}",
+        pipeline.dead_letters(),
+        pipeline.validator.counters()
+    );
+    Ok(())
+}
+
+fn count_resubscriptions<C: ToString>(
+    exchange: &'static str,
+    channels: &[C],
+    reason: &'static str,
+) {
+    for channel in channels {
+        metrics::counter!(
+            "ws_resubscriptions_total",
+            "exchange" => exchange,
+            "channel" => channel.to_string(),
+            "reason" => reason
+        )
+        .increment(1);
+    }
+}
+
+/// Channels have to be supported and pairs listed by exchange, not only the default ones
+fn check_change<E: Exchange>(
+    exchange: &E,
+    change: &SubscriptionChange<E::Channel>,
+) -> Result<(), String> {
+    let supported = exchange.channels();
+    if let Some(channel) = change
+        .channels
+        .iter()
+        .find(|channel| !supported.contains(channel))
+    {
+        return Err(format!("channel {channel} is not supported"));
+    }
+    let exchange_symbols = exchange.context().give(ExchangeSymbols);
+    for pair in &change.pairs {
+        exchange_symbols
+            .to_symbol(pair)
+            .map_err(|err| format!("{err:#}"))?;
+    }
+    Ok(())
+}
+
+/// Applies runtime change of subscriptions; invalid change is rejected without touching the stream
+async fn change_subscriptions<E: Exchange>(
+    pipeline: &mut Pipeline<'_, E>,
+    stream: &mut E::Stream<'_>,
+    control: &mut StreamControl<E::Channel>,
+    ControlRequest { command, reply }: ControlRequest<E::Channel>,
+) -> anyhow::Result<()> {
+    let (change, subscribe) = match command {
+        ControlCommand::List => {
+            let _ = reply.send(Ok(control.desired.clone()));
+            return Ok(());
         }
+        ControlCommand::Subscribe(change) => (change, true),
+        ControlCommand::Unsubscribe(change) => (change, false),
+    };
+    log::info!(
+        "Change subscriptions of {}, subscribe: {subscribe}, {change:?}",
+        pipeline.exchange.name()
+    );
+    if let Err(err) = check_change(pipeline.exchange, &change) {
+        let _ = reply.send(Err(err));
+        return Ok(());
+    }
+
+    let mut desired = control.desired.clone();
+    let batches = desired.apply(change, subscribe);
+    // desired set survives restarts, so it's saved before server is asked
+    if let Err(err) = pipeline
+        .storage
+        .save_desired_subscriptions(pipeline.exchange.name(), &desired)
+        .await
+    {
+        let _ = reply.send(Err(format!("{err:#}")));
+        return Ok(());
+    }
+    let added_pairs: Vec<Pair> = desired
+        .pairs
+        .difference(&control.desired.pairs)
+        .cloned()
+        .collect();
+    control.desired = desired;
+
+    pipeline
+        .health
+        .set_channels(control.desired.channels.iter().map(ToString::to_string));
+    if let Some(watchdog) = &mut pipeline.watchdog {
+        watchdog.set(
+            control.desired.channels.iter().copied(),
+            control.desired.pairs.iter().cloned().collect(),
+            UnixMillis::now(),
+        );
+    }
+
+    let mut waiting = vec![];
+    for SubscriptionBatch { channels, pairs } in batches {
+        let request = if subscribe {
+            stream.subscribe(&channels, &pairs).await?
+        } else {
+            stream.unsubscribe(&channels, &pairs).await?
+        };
+        waiting.extend(channels.iter().map(|channel| (request, *channel)));
+    }
+    if !added_pairs.is_empty() && control.backfill.send(added_pairs).is_err() {
+        log::warn!("Backfill of added pairs is not running");
     }
+    control.pending.push(PendingChange {
+        waiting,
+        desired: control.desired.clone(),
+        reply,
+    });
     Ok(())
 }
 
-fn log_err<T>(context: &impl fmt::Display) -> impl '_ + Fn(anyhow::Result<T>) -> Option<T> {
-    move |res| match res {
-        Ok(ok) => Some(ok),
-        Err(err) => {
-            log::error!("[{context}] {err:#}");
-            None
+/// Converts WS messages into records and stores them, same way for live and reprocessed messages
+pub(crate) struct Pipeline<'a, E: Exchange> {
+    pub(crate) exchange: &'a E,
+    pub(crate) storage: &'a Storage,
+    pub(crate) validator: &'a Validator,
+    /// Replace klines closed in stream with the final ones from REST API
+    pub(crate) confirm_closed: bool,
+    pub(crate) health: &'a Health,
+    /// Only live stream has one, replayed messages aren't tracked
+    pub(crate) watchdog: Option<Watchdog<E::Channel>>,
+    buckets: KlineBuckets,
+    dead_letters: usize,
+}
+
+impl<'a, E: Exchange> Pipeline<'a, E> {
+    pub(crate) fn new(
+        exchange: &'a E,
+        storage: &'a Storage,
+        validator: &'a Validator,
+        confirm_closed: bool,
+        health: &'a Health,
+    ) -> Self {
+        Self {
+            exchange,
+            storage,
+            validator,
+            confirm_closed,
+            health,
+            watchdog: None,
+            buckets: Default::default(),
+            dead_letters: 0,
         }
     }
+
+    /// Number of messages sent to dead letters so far
+    pub(crate) fn dead_letters(&self) -> usize {
+        self.dead_letters
+    }
+
+    /// Events of one server message. Returns number of stored records.
+    pub(crate) async fn handle_events(
+        &mut self,
+        events: Vec<MarketEvent<E::Channel>>,
+        received_at: UnixMillis,
+    ) -> anyhow::Result<usize> {
+        // stream message has events of a single channel
+        let mut klines = OneOrMany::new();
+        let mut kline_interval = None;
+        let mut recent_trades = OneOrMany::new();
+        let exchange = self.exchange.name();
+        for event in events {
+            if let Some(channel) = event.channel() {
+                metrics::counter!(
+                    "ws_events_total",
+                    "exchange" => exchange,
+                    "channel" => channel.to_string()
+                )
+                .increment(1);
+                self.health.message(&channel.to_string(), received_at);
+            }
+            match event {
+                MarketEvent::Kline {
+                    channel,
+                    interval,
+                    kline,
+                } => {
+                    self.observe_record(channel, &kline.pair, kline.utc_record, received_at);
+                    kline_interval = Some(interval);
+                    klines.push(kline);
+                }
+                MarketEvent::Trade {
+                    channel,
+                    recent_trade,
+                } => {
+                    self.observe_record(
+                        channel,
+                        &recent_trade.pair,
+                        recent_trade.timestamp.to_millis(),
+                        received_at,
+                    );
+                    recent_trades.push(recent_trade);
+                }
+                MarketEvent::Invalid {
+                    raw,
+                    channel,
+                    error,
+                } => {
+                    let channel = channel.map(|channel| channel.to_string());
+                    self.dead_letter(raw, channel, error, received_at).await?;
+                }
+                MarketEvent::Error {
+                    message,
+                    harmless: true,
+                } => log::warn!("{exchange} WS server sent error event: {message}"),
+                MarketEvent::Error { message, .. } => {
+                    bail!("{exchange} WS server sent error event: {message}")
+                }
+                MarketEvent::Subscribed { channel, .. } => {
+                    log::info!("{exchange} WS server subscribed {channel}");
+                    self.health.subscribed(&channel.to_string(), true)
+                }
+                MarketEvent::Unsubscribed { channel, .. } => {
+                    log::info!("{exchange} WS server unsubscribed {channel}");
+                    self.health.subscribed(&channel.to_string(), false)
+                }
+                MarketEvent::UnsubscribedAll => {
+                    log::info!("{exchange} WS server unsubscribed all");
+                    self.health.set_subscriptions([])
+                }
+                MarketEvent::Subscriptions(subscriptions) => {
+                    log::info!("{exchange} WS server sent subscriptions: {subscriptions:?}");
+                    self.health
+                        .set_subscriptions(subscriptions.iter().map(ToString::to_string));
+                }
+                MarketEvent::Reconnected => log::info!("{exchange} WS server is reconnected"),
+                MarketEvent::Pong => {}
+            }
+        }
+
+        let mut stored = 0;
+        if let Some(interval) = kline_interval {
+            log::info!("New klines: {klines:?}");
+            let klines = validate::accept(
+                self.validator,
+                self.storage,
+                self.exchange.context(),
+                klines,
+            )
+            .await?;
+            let confirm_closed = self.confirm_closed.then_some(self.exchange);
+            for mut kline in klines {
+                self.buckets
+                    .track(
+                        &mut kline,
+                        interval,
+                        self.storage,
+                        self.validator,
+                        confirm_closed,
+                    )
+                    .await
+                    .context("track kline bucket")?;
+                stored += self
+                    .storage
+                    .upsert_kline(kline)
+                    .await
+                    .context("save kline from stream to storage")?;
+            }
+        }
+        if !recent_trades.is_empty() {
+            log::info!("New recent trades: {recent_trades:?}");
+            let recent_trades = validate::accept(
+                self.validator,
+                self.storage,
+                self.exchange.context(),
+                recent_trades,
+            )
+            .await?;
+            stored += self
+                .storage
+                .insert_recent_trades(recent_trades)
+                .await
+                .context("save recent trades from stream to storage")?;
+        }
+        Ok(stored)
+    }
+
+    /// Lag between exchange and us, and time of the last update, to alert when pair stops updating
+    fn observe_record(
+        &mut self,
+        channel: E::Channel,
+        pair: &Pair,
+        record_time: UnixMillis,
+        received_at: UnixMillis,
+    ) {
+        if let Some(watchdog) = &mut self.watchdog {
+            watchdog.event(channel, pair, received_at);
+        }
+        let exchange = self.exchange.name();
+        let channel = channel.to_string();
+        let lag_ms = received_at.as_i64() - record_time.as_i64();
+        metrics::histogram!(
+            "stream_lag_seconds",
+            "exchange" => exchange,
+            "channel" => channel.clone()
+        )
+        .record(lag_ms as f64 / 1000.0);
+        metrics::gauge!(
+            "stream_last_update_timestamp_seconds",
+            "exchange" => exchange,
+            "channel" => channel,
+            "pair" => pair.to_string()
+        )
+        .set(received_at.as_i64() as f64 / 1000.0);
+    }
+
+    /// Stores message which failed to parse or convert, so it can be reprocessed later
+    pub(crate) async fn dead_letter(
+        &mut self,
+        raw: String,
+        channel: Option<String>,
+        error: anyhow::Error,
+        received_at: UnixMillis,
+    ) -> anyhow::Result<()> {
+        let exchange = self.exchange.name();
+        let channel_label = channel.as_deref().unwrap_or("-");
+        log::error!("[{exchange} {channel_label}] {error:#}");
+        metrics::counter!(
+            "dead_letters_total",
+            "exchange" => exchange,
+            "channel" => channel_label.to_owned()
+        )
+        .increment(1);
+        self.storage
+            .insert_dead_letter(&DeadLetter {
+                id: None,
+                exchange: exchange.into(),
+                raw,
+                channel,
+                errors: error.chain().map(ToString::to_string).collect(),
+                received_at,
+            })
+            .await?;
+        self.dead_letters += 1;
+        Ok(())
+    }
+}
+
+/// Current bucket (start and close time) of each pair and time frame seen in stream.
+/// WS never says that a candle is final, so it's closed once a newer bucket arrives.
+#[derive(Default)]
+struct KlineBuckets {
+    current: BTreeMap<(Pair, String), (UnixMillis, UnixMillis)>,
+}
+
+impl KlineBuckets {
+    async fn track<E: Exchange>(
+        &mut self,
+        kline: &mut Kline,
+        interval: Interval,
+        storage: &Storage,
+        validator: &Validator,
+        confirm_closed: Option<&E>,
+    ) -> anyhow::Result<()> {
+        let key = (kline.pair.clone(), kline.time_frame.clone());
+        let bucket = (kline.utc_begin, kline.utc_end);
+        let previous = match self.current.get(&key) {
+            Some(&current) if current.0 == bucket.0 => return Ok(()),
+            Some(&current) if current.0 > bucket.0 => {
+                // late update of already closed bucket
+                kline.status = KlineStatus::Closed;
+                return Ok(());
+            }
+            previous => previous.copied(),
+        };
+        self.current.insert(key, bucket);
+
+        // also closes in-progress klines left by REST download or previous run
+        let closed = storage
+            .close_klines_before(
+                &kline.exchange,
+                &kline.pair,
+                &kline.time_frame,
+                kline.utc_begin,
+            )
+            .await?;
+        log::debug!(
+            "New bucket of {} {}, closed {closed} klines",
+            kline.pair,
+            kline.time_frame
+        );
+
+        if let Some((exchange, previous)) = confirm_closed.zip(previous) {
+            if let Err(err) = confirm_kline(
+                exchange,
+                storage,
+                validator,
+                &kline.pair,
+                interval,
+                previous,
+            )
+            .await
+            {
+                log::error!(
+                    "[{} {}] Can't confirm closed kline: {err:#}",
+                    kline.pair,
+                    kline.time_frame
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Replaces closed kline from stream with the REST one, which also has proper buy/sell volumes
+async fn confirm_kline<E: Exchange>(
+    exchange: &E,
+    storage: &Storage,
+    validator: &Validator,
+    pair: &Pair,
+    interval: Interval,
+    (utc_begin, utc_end): (UnixMillis, UnixMillis),
+) -> anyhow::Result<()> {
+    let klines = exchange
+        .klines(pair, interval, utc_begin, Some(utc_end), 1)
+        .await
+        .context("download kline")?;
+    let Some(kline) = klines.into_iter().next() else {
+        bail!("exchange has no kline at {}", utc_begin.display());
+    };
+    if kline.status != KlineStatus::Closed {
+        bail!(
+            "exchange hasn't finalized kline at {} yet",
+            utc_begin.display()
+        );
+    }
+    let klines = validate::accept(validator, storage, exchange.context(), [kline].into()).await?;
+    for kline in klines {
+        storage
+            .upsert_kline(kline)
+            .await
+            .context("save confirmed kline to storage")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use bitsgap_poloniex::{
+        context::PoloniexContext, exchange::Poloniex, rest::markets::MarketResponse,
+        symbols::markets_symbols, ws::channels::Channel,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_check_change_of_pair_outside_defaults() {
+        let markets: Vec<MarketResponse> = ["BTC_USDT", "SOL_USDT"]
+            .into_iter()
+            .map(|symbol| {
+                let (base, quote) = symbol.split_once('_').unwrap();
+                serde_json::from_value(serde_json::json!({
+                    "symbol": symbol,
+                    "baseCurrencyName": base,
+                    "quoteCurrencyName": quote,
+                    "state": "NORMAL",
+                    "symbolTradeLimit": {
+                        "priceScale": 2,
+                        "quantityScale": 6,
+                        "amountScale": 2,
+                        "minQuantity": "0.000001",
+                        "minAmount": "1"
+                    }
+                }))
+                .unwrap()
+            })
+            .collect();
+        let mut context = PoloniexContext::init(true).unwrap();
+        context.set_exchange_symbols(markets_symbols(&markets).unwrap());
+        let uri = bitsgap_poloniex::ws::PUBLIC_WS_URI.parse().unwrap();
+        let poloniex = Poloniex::offline(context, uri);
+        let sol = Pair::new("SOL", "USDT");
+        assert!(!poloniex.default_pairs().contains(&sol));
+
+        let change = |pair: Pair| SubscriptionChange {
+            channels: [Channel::Trades].into(),
+            pairs: [pair].into(),
+        };
+        assert_eq!(check_change(&poloniex, &change(sol)), Ok(()));
+        assert_eq!(
+            check_change(&poloniex, &change(Pair::new("FOO", "USDT"))),
+            Err("pair FOO/USDT is unknown to exchange".into())
+        );
+    }
 }
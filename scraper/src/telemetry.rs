@@ -0,0 +1,194 @@
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use anyhow::Context as _;
+use axum::{Json, Router, extract::State, http::StatusCode, routing::get};
+use bitsgap_shared::{
+    exchange::Exchange,
+    utils::time::{SpanDuration, UnixMillis},
+};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use tokio::time::timeout;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    control::{Control, ControlCommand, DesiredSubscriptions, SubscriptionChange},
+    health::{Health, ScraperReport},
+    storage::Storage,
+};
+
+#[derive(Debug, clap::Args)]
+pub(crate) struct ServeArgs {
+    /// Address of HTTP server with `/metrics`, `/healthz`, `/readyz` and `/subscriptions/{exchange}` endpoints
+    #[arg(long, default_value = "127.0.0.1:9464")]
+    serve_addr: SocketAddr,
+    /// Backfill without progress or WS channel without messages for this long is reported as a problem
+    #[arg(long, default_value = "2m")]
+    max_message_age: SpanDuration,
+}
+
+#[derive(Clone)]
+struct AppState {
+    metrics: PrometheusHandle,
+    /// health of each exchange by its name
+    healths: Vec<(&'static str, Arc<Health>)>,
+    storage: Storage,
+    max_message_age: Duration,
+}
+
+const STORAGE_PING_TIMEOUT: Duration = Duration::from_secs(2);
+// server acknowledges subscriptions within a second or so
+const SUBSCRIPTIONS_TIMEOUT: Duration = Duration::from_secs(10);
+
+// seconds, from fast storage writes to slow REST requests with retries
+const DURATION_BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+// seconds, between exchange record time and receive time
+const LAG_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 300.0];
+
+/// Installs global metrics recorder and spawns HTTP server with metrics and health, which runs until shutdown.
+/// `subscriptions` are routes of running streams, see `subscriptions_routes`.
+pub(crate) async fn serve(
+    args: ServeArgs,
+    healths: Vec<(&'static str, Arc<Health>)>,
+    storage: Storage,
+    subscriptions: Router,
+    shutdown: CancellationToken,
+) -> anyhow::Result<()> {
+    let ServeArgs {
+        serve_addr,
+        max_message_age,
+    } = args;
+    let handle = PrometheusBuilder::new()
+        .set_buckets_for_metric(Matcher::Suffix("duration_seconds".into()), DURATION_BUCKETS)
+        .context("set duration buckets")?
+        .set_buckets_for_metric(Matcher::Suffix("lag_seconds".into()), LAG_BUCKETS)
+        .context("set lag buckets")?
+        .install_recorder()
+        .context("install prometheus recorder")?;
+
+    let app = Router::new()
+        .route("/metrics", get(render_metrics))
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .with_state(AppState {
+            metrics: handle,
+            healths,
+            storage,
+            max_message_age: max_message_age.into(),
+        })
+        .merge(subscriptions);
+    let listener = tokio::net::TcpListener::bind(serve_addr)
+        .await
+        .with_context(|| format!("bind HTTP server to {serve_addr}"))?;
+    log::info!("Serving metrics and health on http://{serve_addr}");
+
+    tokio::spawn(async move {
+        let res = axum::serve(listener, app)
+            .with_graceful_shutdown(shutdown.cancelled_owned())
+            .await;
+        if let Err(err) = res {
+            log::error!("HTTP server stopped with error: {err:#}");
+        }
+    });
+    Ok(())
+}
+
+/// Subscriptions of exchange's stream can be changed at `/subscriptions/{exchange}` while it's running
+pub(crate) fn subscriptions_routes<E: Exchange + 'static>(
+    exchange: &E,
+    control: Control<E::Channel>,
+) -> Router {
+    Router::new().route(
+        &format!("/subscriptions/{}", exchange.name()),
+        get(list_subscriptions::<E>)
+            .post(subscribe::<E>)
+            .delete(unsubscribe::<E>)
+            .with_state(control),
+    )
+}
+
+async fn render_metrics(State(state): State<AppState>) -> String {
+    state.metrics.render()
+}
+
+async fn report(state: &AppState) -> ScraperReport {
+    let storage_ok = match timeout(STORAGE_PING_TIMEOUT, state.storage.ping()).await {
+        Ok(Ok(())) => true,
+        Ok(Err(err)) => {
+            log::warn!("Health check: {err:#}");
+            false
+        }
+        Err(_) => {
+            log::warn!("Health check: storage ping timed out");
+            false
+        }
+    };
+    let now = UnixMillis::now();
+    let reports = state
+        .healths
+        .iter()
+        .map(|(exchange, health)| (*exchange, health.report(state.max_message_age, now)));
+    ScraperReport::new(storage_ok, reports)
+}
+
+/// Liveness: fails only if scraper is wedged, restart won't help with unreachable storage
+async fn healthz(State(state): State<AppState>) -> (StatusCode, Json<ScraperReport>) {
+    let report = report(&state).await;
+    let status = if report.unhealthy.is_empty() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(report))
+}
+
+async fn readyz(State(state): State<AppState>) -> (StatusCode, Json<ScraperReport>) {
+    let report = report(&state).await;
+    let status = if report.unhealthy.is_empty() && report.not_ready.is_empty() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(report))
+}
+
+type SubscriptionsResponse<C> = Result<Json<DesiredSubscriptions<C>>, (StatusCode, String)>;
+
+async fn change_subscriptions<E: Exchange>(
+    control: &Control<E::Channel>,
+    command: ControlCommand<E::Channel>,
+) -> SubscriptionsResponse<E::Channel> {
+    match timeout(SUBSCRIPTIONS_TIMEOUT, control.request(command)).await {
+        Ok(Ok(Ok(desired))) => Ok(Json(desired)),
+        Ok(Ok(Err(err))) => Err((StatusCode::BAD_REQUEST, err)),
+        Ok(Err(err)) => Err((StatusCode::SERVICE_UNAVAILABLE, format!("{err:#}"))),
+        // change is saved and sent, watchdog resubscribes if server missed it
+        Err(_) => Err((
+            StatusCode::GATEWAY_TIMEOUT,
+            "server hasn't acknowledged subscriptions in time".into(),
+        )),
+    }
+}
+
+/// Desired channels and pairs of the stream
+async fn list_subscriptions<E: Exchange>(
+    State(control): State<Control<E::Channel>>,
+) -> SubscriptionsResponse<E::Channel> {
+    change_subscriptions::<E>(&control, ControlCommand::List).await
+}
+
+/// Adds channels and pairs, new pairs are backfilled. Replies once server acknowledged them.
+async fn subscribe<E: Exchange>(
+    State(control): State<Control<E::Channel>>,
+    Json(change): Json<SubscriptionChange<E::Channel>>,
+) -> SubscriptionsResponse<E::Channel> {
+    change_subscriptions::<E>(&control, ControlCommand::Subscribe(change)).await
+}
+
+async fn unsubscribe<E: Exchange>(
+    State(control): State<Control<E::Channel>>,
+    Json(change): Json<SubscriptionChange<E::Channel>>,
+) -> SubscriptionsResponse<E::Channel> {
+    change_subscriptions::<E>(&control, ControlCommand::Unsubscribe(change)).await
+}
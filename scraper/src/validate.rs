@@ -0,0 +1,58 @@
+use core::fmt;
+
+use bitsgap_shared::{
+    records::{kline::Kline, recent_trade::RecentTrade},
+    validation::{IssuesDisplay, Validate, Validator, Verdict},
+};
+
+use crate::storage::{OneOrMany, Storage};
+
+pub(crate) trait RecordKind {
+    /// how record is named in quarantine
+    const KIND: &'static str;
+}
+
+impl RecordKind for Kline {
+    const KIND: &'static str = "kline";
+}
+
+impl RecordKind for RecentTrade {
+    const KIND: &'static str = "recent_trade";
+}
+
+/// Returns records which can be stored, quarantining or dropping the rest according to policy
+pub(crate) async fn accept<C, T>(
+    validator: &Validator,
+    storage: &Storage,
+    context: &C,
+    records: OneOrMany<T>,
+) -> anyhow::Result<OneOrMany<T>>
+where
+    T: Validate<C> + RecordKind + serde::Serialize + fmt::Debug,
+{
+    let mut accepted = OneOrMany::with_capacity(records.len());
+    for record in records {
+        let verdict = validator.check(&record, context);
+        let verdict_label = match verdict {
+            Verdict::Accept => "accept",
+            Verdict::Reject(_) => "reject",
+            Verdict::Quarantine(_) => "quarantine",
+        };
+        metrics::counter!("records_validated_total", "kind" => T::KIND, "verdict" => verdict_label)
+            .increment(1);
+        match verdict {
+            Verdict::Accept => accepted.push(record),
+            Verdict::Reject(issues) => {
+                log::error!(
+                    "Rejected {} {record:?}: {}",
+                    T::KIND,
+                    IssuesDisplay(&issues)
+                );
+            }
+            Verdict::Quarantine(issues) => {
+                storage.quarantine(T::KIND, &record, &issues).await?;
+            }
+        }
+    }
+    Ok(accepted)
+}
@@ -0,0 +1,147 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use bitsgap_shared::{
+    pair::Pair,
+    utils::time::{SpanDuration, UnixMillis},
+};
+
+#[derive(Debug, Clone, clap::Args)]
+pub(crate) struct WatchdogArgs {
+    /// Resubscribe channel of pair which had no events for this long
+    #[arg(long, default_value = "5m")]
+    stale_after: SpanDuration,
+    /// How often to look for stale subscriptions and to reconcile them with server's list
+    #[arg(long, default_value = "1m")]
+    watchdog_interval: SpanDuration,
+}
+
+impl WatchdogArgs {
+    pub(crate) fn interval(&self) -> Duration {
+        self.watchdog_interval.into()
+    }
+}
+
+/// Tracks last event of each channel and pair, to resubscribe only those which went quiet
+#[derive(Debug)]
+pub(crate) struct Watchdog<C> {
+    stale_after: Duration,
+    pairs: Vec<Pair>,
+    // channel -> pair -> time of last event or resubscription
+    last_event: BTreeMap<C, BTreeMap<Pair, UnixMillis>>,
+}
+
+impl<C: Copy + Ord> Watchdog<C> {
+    pub(crate) fn new(
+        args: &WatchdogArgs,
+        channels: impl IntoIterator<Item = C>,
+        pairs: Vec<Pair>,
+        now: UnixMillis,
+    ) -> Self {
+        let mut watchdog = Self {
+            stale_after: args.stale_after.into(),
+            pairs: vec![],
+            last_event: BTreeMap::new(),
+        };
+        watchdog.set(channels, pairs, now);
+        watchdog
+    }
+
+    /// Tracks new channels and pairs since now, forgets removed ones
+    pub(crate) fn set(
+        &mut self,
+        channels: impl IntoIterator<Item = C>,
+        pairs: Vec<Pair>,
+        now: UnixMillis,
+    ) {
+        let mut previous = std::mem::take(&mut self.last_event);
+        for channel in channels {
+            let known = previous.remove(&channel).unwrap_or_default();
+            let last_event = pairs
+                .iter()
+                .map(|pair| (pair.clone(), known.get(pair).copied().unwrap_or(now)))
+                .collect();
+            self.last_event.insert(channel, last_event);
+        }
+        self.pairs = pairs;
+    }
+
+    pub(crate) fn pairs(&self) -> &[Pair] {
+        &self.pairs
+    }
+
+    pub(crate) fn event(&mut self, channel: C, pair: &Pair, at: UnixMillis) {
+        // events of pairs we didn't subscribe to are ignored
+        if let Some(last) = self
+            .last_event
+            .get_mut(&channel)
+            .and_then(|pairs| pairs.get_mut(pair))
+        {
+            *last = (*last).max(at);
+        }
+    }
+
+    /// Pairs of each channel which had no events for too long.
+    /// Their timers are reset, so they have time to recover after resubscription.
+    pub(crate) fn take_stale(&mut self, now: UnixMillis) -> BTreeMap<C, Vec<Pair>> {
+        let mut stale = BTreeMap::new();
+        for (channel, pairs) in &mut self.last_event {
+            let mut stale_pairs = vec![];
+            for (pair, last) in pairs {
+                if last.saturating_add(self.stale_after) < now {
+                    stale_pairs.push(pair.clone());
+                    *last = now;
+                }
+            }
+            if !stale_pairs.is_empty() {
+                stale.insert(*channel, stale_pairs);
+            }
+        }
+        stale
+    }
+
+    /// Channels we track, which are absent in server's list of subscriptions
+    pub(crate) fn missing(&self, subscriptions: &[C]) -> Vec<C> {
+        self.last_event
+            .keys()
+            .filter(|channel| !subscriptions.contains(channel))
+            .copied()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitsgap_poloniex::ws::channels::Channel;
+
+    use super::*;
+
+    #[test]
+    fn test_watchdog() {
+        let candles: Channel = "candles_minute_1".parse().unwrap();
+        let btc = Pair::new("BTC", "USDT");
+        let eth = Pair::new("ETH", "USDT");
+        let args = WatchdogArgs {
+            stale_after: Duration::from_secs(60).into(),
+            watchdog_interval: Duration::from_secs(10).into(),
+        };
+        let start = UnixMillis(1648057140000);
+        let mut watchdog = Watchdog::new(
+            &args,
+            [candles, Channel::Trades],
+            vec![btc.clone(), eth.clone()],
+            start,
+        );
+        let later = start.saturating_add(Duration::from_secs(30));
+        watchdog.event(candles, &btc, later);
+        watchdog.event(candles, &eth, later);
+        watchdog.event(Channel::Trades, &btc, later);
+        watchdog.event(Channel::Trades, &Pair::new("DOGE", "USDT"), later);
+
+        let now = start.saturating_add(Duration::from_secs(75));
+        let stale = watchdog.take_stale(now);
+        assert_eq!(stale, BTreeMap::from([(Channel::Trades, vec![eth])]));
+        assert!(watchdog.take_stale(now).is_empty());
+
+        assert_eq!(watchdog.missing(&[candles]), [Channel::Trades]);
+    }
+}
@@ -4,7 +4,12 @@ use anyhow::{Context as _, bail};
 use reqwest::Url;
 
 use super::AuthMethod;
-use crate::utils::time::timestamp_now;
+use crate::utils::time::UnixMillis;
+
+/// Body takes place of query params when signed, query of such request is ignored
+fn timestamped_body_params(body: &str, timestamp: &str) -> String {
+    format!("requestBody={body}&signTimestamp={timestamp}")
+}
 
 // TODO: optimize by making query sorted in the first place, in send_request?
 // But other auth methods maybe don't want this
@@ -37,28 +42,71 @@ mod hmac_sha256 {
     }
 }
 
+/// Method, path and params of request, params are either sorted query or body
+fn signed_payload(req: &reqwest::Request, timestamp: &str) -> anyhow::Result<String> {
+    let method = req.method().as_str();
+
+    // url without authority, domain and query
+    let path = req.url().path();
+
+    let body = match req.body() {
+        Some(body) => {
+            let Some(bytes) = body.as_bytes() else {
+                bail!("streamed body can't be signed");
+            };
+            std::str::from_utf8(bytes).context("request body is not UTF-8")?
+        }
+        None => "",
+    };
+    let params = if body.is_empty() {
+        timestamped_sorted_params(req.url(), timestamp)
+    } else {
+        timestamped_body_params(body, timestamp)
+    };
+
+    Ok(format!("{method}\n{path}\n{params}"))
+}
+
+/// Signature of request without query and body, e.g. of WebSocket authentication
+#[derive(Debug, Clone)]
+pub struct Signature {
+    pub api_key: String,
+    pub signature: String,
+    pub timestamp: UnixMillis,
+}
+
 impl AuthMethod {
+    /// Signs `method` and `path` the same way as REST requests are signed
+    pub fn sign(&self, method: &str, path: &str) -> anyhow::Result<Signature> {
+        let AuthMethod::HmacSha256 {
+            api_key,
+            secret_key,
+        } = self
+        else {
+            bail!("API keys aren't configured");
+        };
+        let timestamp = UnixMillis::now();
+        let payload = format!("{method}\n{path}\nsignTimestamp={}", timestamp.as_i64());
+        Ok(Signature {
+            api_key: api_key.clone(),
+            signature: hmac_sha256::sign_payload(&payload, secret_key),
+            timestamp,
+        })
+    }
+
     pub(super) fn apply(&self, req: &mut reqwest::Request) -> anyhow::Result<()> {
+        self.apply_at(req, UnixMillis::now())
+    }
+
+    fn apply_at(&self, req: &mut reqwest::Request, timestamp: UnixMillis) -> anyhow::Result<()> {
         match self {
+            AuthMethod::None => {}
             AuthMethod::HmacSha256 {
                 api_key,
                 secret_key,
             } => {
-                let method = req.method().as_str();
-
-                // TODO: implement POST, PUT, DELETE
-                if method != "GET" {
-                    bail!("only GET method implemented for HmacSha256 authentication mode yet");
-                }
-
-                // url without authority, domain and query
-                let path = req.url().path();
-
-                let timestamp = timestamp_now().to_string();
-
-                let params = timestamped_sorted_params(req.url(), &timestamp);
-
-                let payload = format!("{method}\n{path}\n{params}");
+                let timestamp = timestamp.as_i64().to_string();
+                let payload = signed_payload(req, &timestamp)?;
 
                 let sign = hmac_sha256::sign_payload(&payload, secret_key);
 
@@ -82,3 +130,91 @@ impl AuthMethod {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use reqwest::{Client, Method};
+
+    use super::*;
+
+    const TIMESTAMP: UnixMillis = UnixMillis(1631018760000);
+
+    fn keys() -> AuthMethod {
+        AuthMethod::HmacSha256 {
+            api_key: "key".into(),
+            secret_key: "secret".into(),
+        }
+    }
+
+    fn signed(method: Method, url: &str, body: Option<&str>) -> (String, reqwest::Request) {
+        let mut req = Client::new().request(method, url);
+        if let Some(body) = body {
+            req = req.body(body.to_string());
+        }
+        let mut req = req.build().unwrap();
+        let payload = signed_payload(&req, &TIMESTAMP.as_i64().to_string()).unwrap();
+        keys().apply_at(&mut req, TIMESTAMP).unwrap();
+        (payload, req)
+    }
+
+    fn header<'r>(req: &'r reqwest::Request, name: &str) -> &'r str {
+        req.headers()[name].to_str().unwrap()
+    }
+
+    // expected signatures are computed with python hmac
+    #[test]
+    fn test_sign_get_with_query() {
+        let (payload, req) = signed(
+            Method::GET,
+            "https://api.poloniex.com/orders?symbol=ETH_USDT&limit=5",
+            None,
+        );
+        assert_eq!(
+            payload,
+            "GET\n/orders\nlimit=5&signTimestamp=1631018760000&symbol=ETH_USDT"
+        );
+        assert_eq!(
+            header(&req, "signature"),
+            "QXejC+PdlpizJTZRGoI10GRbh8IIiYQVcWQcx7BKXZ4="
+        );
+        assert_eq!(header(&req, "key"), "key");
+        assert_eq!(header(&req, "signTimestamp"), "1631018760000");
+        // query itself is sent as is
+        assert_eq!(req.url().query(), Some("symbol=ETH_USDT&limit=5"));
+    }
+
+    #[test]
+    fn test_sign_delete_without_body() {
+        let (payload, req) = signed(
+            Method::DELETE,
+            "https://api.poloniex.com/orders/32487004629499904",
+            None,
+        );
+        assert_eq!(
+            payload,
+            "DELETE\n/orders/32487004629499904\nsignTimestamp=1631018760000"
+        );
+        assert_eq!(
+            header(&req, "signature"),
+            "MGWYT/zev7TWxaxYgzSeIo2baHRhbUGqxDUDaRRIhWA="
+        );
+    }
+
+    #[test]
+    fn test_sign_post_with_body() {
+        let body = r#"{"symbol":"ETH_USDT","side":"BUY"}"#;
+        let (payload, req) = signed(
+            Method::POST,
+            "https://api.poloniex.com/orders?ignored=1",
+            Some(body),
+        );
+        assert_eq!(
+            payload,
+            format!("POST\n/orders\nrequestBody={body}&signTimestamp=1631018760000")
+        );
+        assert_eq!(
+            header(&req, "signature"),
+            "/jJg5Uh3UDGeAZGL7l7edArAjOv0YULPbIgyLj4T3yI="
+        );
+    }
+}
@@ -0,0 +1,91 @@
+use std::{borrow::Cow, convert::Infallible, fmt, marker::PhantomData, str::FromStr};
+
+use anyhow::Context as _;
+
+use crate::{
+    interval::{Interval, IntervalsDict},
+    utils::{Has, ValueLabel},
+};
+
+/// Channel names of an exchange, usually a unit struct
+pub trait ChannelNames: Copy + Ord + fmt::Debug + Send + Sync + 'static {
+    /// Label of klines channels of supported intervals, kept in context
+    type Supported: ValueLabel<Value = IntervalsDict>;
+
+    /// Lowercase name of exchange, for errors
+    const EXCHANGE: &'static str;
+    /// Name of trades channel
+    const TRADES: &'static str;
+    const SUPPORTED: Self::Supported;
+
+    /// Names of all klines channels, to convert channel without context
+    fn klines() -> &'static IntervalsDict;
+}
+
+/// Public channel, (de)serialized as exchange's own channel name, like "candles_minute_1" or "trades"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Channel<N> {
+    Klines(Interval),
+    Trades,
+    /// can't be constructed, only ties channel to names of its exchange
+    #[doc(hidden)]
+    Names(Infallible, PhantomData<N>),
+}
+
+impl<N: ChannelNames> Channel<N> {
+    pub fn name(&self) -> anyhow::Result<&'static str> {
+        match self {
+            Self::Klines(interval) => N::klines()
+                .to_alias(*interval)
+                .with_context(|| format!("{} has no klines channel of {interval:?}", N::EXCHANGE)),
+            Self::Trades => Ok(N::TRADES),
+            Self::Names(never, _) => match *never {},
+        }
+    }
+
+    /// Channels of the context, i.e. klines channels of supported intervals only
+    pub fn supported<C: Has<N::Supported>>(context: &C) -> impl Iterator<Item = Self> + '_ {
+        context
+            .give(N::SUPPORTED)
+            .iter()
+            .map(|(interval, _)| Self::Klines(interval))
+            .chain([Self::Trades])
+    }
+}
+
+impl<N: ChannelNames> fmt::Display for Channel<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Ok(name) => f.write_str(name),
+            // can't happen for parsed channels
+            Err(_) => write!(f, "{self:?}"),
+        }
+    }
+}
+
+impl<N: ChannelNames> FromStr for Channel<N> {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == N::TRADES {
+            return Ok(Self::Trades);
+        }
+        N::klines()
+            .to_interval(s)
+            .map(Self::Klines)
+            .with_context(|| format!("channel {s:?} is unknown"))
+    }
+}
+
+impl<N: ChannelNames> serde::Serialize for Channel<N> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name().map_err(serde::ser::Error::custom)?)
+    }
+}
+
+impl<'de, N: ChannelNames> serde::Deserialize<'de> for Channel<N> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <Cow<'de, str>>::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
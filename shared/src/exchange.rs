@@ -0,0 +1,177 @@
+use std::{fmt, future::Future, str::FromStr};
+
+use crate::{
+    interval::{DatabaseIntervals, Interval},
+    journal::JournalConfig,
+    pair::{ExchangeSymbols, Pair},
+    records::{kline::Kline, recent_trade::RecentTrade},
+    utils::{Has, time::UnixMillis},
+};
+
+/// Market data of an exchange: history from REST API and normalized live stream
+pub trait Exchange {
+    /// Channel of live stream, like klines of some interval or trades.
+    /// (De)serialized and displayed as exchange's own channel name.
+    type Channel: Copy
+        + Ord
+        + fmt::Debug
+        + fmt::Display
+        + FromStr<Err = anyhow::Error>
+        + serde::Serialize
+        + serde::de::DeserializeOwned
+        + Send
+        + Sync
+        + 'static;
+    /// Intervals and symbols, records are validated against it
+    type Context: Has<DatabaseIntervals> + Has<ExchangeSymbols>;
+    type Stream<'a>: MarketStream<Channel = Self::Channel>
+    where
+        Self: 'a;
+
+    /// Max number of klines `klines` returns at once
+    const KLINES_LIMIT: u16;
+
+    /// Lowercase name, like "poloniex"
+    fn name(&self) -> &'static str;
+
+    fn context(&self) -> &Self::Context;
+
+    /// Pairs which are traded on exchange, subscriptions can be changed to any of them
+    fn listed_pairs(&self) -> Vec<Pair> {
+        self.context()
+            .give(ExchangeSymbols)
+            .listed()
+            .cloned()
+            .collect()
+    }
+
+    /// Pairs which are backfilled and streamed unless subscriptions were changed
+    fn default_pairs(&self) -> Vec<Pair>;
+
+    /// Channels which live stream can be subscribed to
+    fn channels(&self) -> Vec<Self::Channel>;
+
+    /// Latest klines which begin between `since` and `until`, at most `limit`, oldest first
+    fn klines(
+        &self,
+        pair: &Pair,
+        interval: Interval,
+        since: UnixMillis,
+        until: Option<UnixMillis>,
+        limit: u16,
+    ) -> impl Future<Output = anyhow::Result<Vec<Kline>>>;
+
+    /// Latest trades, as many as exchange exposes, newest first
+    fn recent_trades(&self, pair: &Pair) -> impl Future<Output = anyhow::Result<Vec<RecentTrade>>>;
+
+    /// Connects to live stream without subscriptions
+    fn stream(
+        &self,
+        journal: Option<JournalConfig>,
+    ) -> impl Future<Output = anyhow::Result<Self::Stream<'_>>>;
+
+    /// Normalizes raw message of live stream, e.g. from journal.
+    /// With `channel`, raw is a single event of stream message, like in dead letters.
+    fn parse_events(
+        &self,
+        raw: &str,
+        channel: Option<Self::Channel>,
+    ) -> Vec<MarketEvent<Self::Channel>>;
+}
+
+/// Id of subscribe or unsubscribe message, unique within a stream, acknowledgements carry it
+pub type RequestId = u64;
+
+/// Live stream, which reconnects and restores subscriptions by itself
+pub trait MarketStream {
+    type Channel;
+
+    /// Every channel of every pair, returns id of sent message
+    fn subscribe(
+        &mut self,
+        channels: &[Self::Channel],
+        pairs: &[Pair],
+    ) -> impl Future<Output = anyhow::Result<RequestId>>;
+
+    fn unsubscribe(
+        &mut self,
+        channels: &[Self::Channel],
+        pairs: &[Pair],
+    ) -> impl Future<Output = anyhow::Result<RequestId>>;
+
+    /// Unsubscribes and subscribes again, e.g. if server stopped sending events without a reason
+    fn resubscribe(
+        &mut self,
+        channels: &[Self::Channel],
+        pairs: &[Pair],
+    ) -> impl Future<Output = anyhow::Result<()>>;
+
+    /// Server replies with `MarketEvent::Subscriptions`
+    fn list_subscriptions(&mut self) -> impl Future<Output = anyhow::Result<()>>;
+
+    /// Waits for the next server message. Cancel safe: lost connection is kept in the stream,
+    /// reconnection and restoring subscriptions resume on the next call.
+    fn next(&mut self) -> impl Future<Output = anyhow::Result<MarketUpdate<Self::Channel>>>;
+
+    /// Unsubscribes from everything and closes connection
+    fn close(self) -> impl Future<Output = ()>;
+}
+
+/// Normalized event of live market data
+#[derive(Debug)]
+pub enum MarketEvent<C> {
+    /// new or updated kline
+    Kline {
+        channel: C,
+        interval: Interval,
+        kline: Kline,
+    },
+    Trade {
+        channel: C,
+        recent_trade: RecentTrade,
+    },
+    /// acknowledgement of channel, with id of message it belongs to if it's known
+    Subscribed {
+        channel: C,
+        request: Option<RequestId>,
+    },
+    Unsubscribed {
+        channel: C,
+        request: Option<RequestId>,
+    },
+    UnsubscribedAll,
+    /// reply to `MarketStream::list_subscriptions`
+    Subscriptions(Vec<C>),
+    Pong,
+    /// error reported by server, harmless one (like subscribing twice) doesn't break the stream
+    Error {
+        message: String,
+        harmless: bool,
+    },
+    /// message, or single event of stream if channel is known, which couldn't be parsed or converted
+    Invalid {
+        raw: String,
+        channel: Option<C>,
+        error: anyhow::Error,
+    },
+    /// connection was lost and is established again, with all subscriptions restored
+    Reconnected,
+}
+
+impl<C: Copy> MarketEvent<C> {
+    /// Channel of stream event
+    pub fn channel(&self) -> Option<C> {
+        match self {
+            Self::Kline { channel, .. } | Self::Trade { channel, .. } => Some(*channel),
+            Self::Invalid { channel, .. } => *channel,
+            _ => None,
+        }
+    }
+}
+
+/// Events of one server message, so they can be stored in one batch
+#[derive(Debug)]
+pub struct MarketUpdate<C> {
+    pub received_at: UnixMillis,
+    pub events: Vec<MarketEvent<C>>,
+}
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use anyhow::bail;
 
 // dict to convert string interval from exchange to inner representation and back
@@ -31,6 +33,25 @@ pub struct Interval {
     pub value: u8,
 }
 
+impl Interval {
+    /// Every bucket of interval starts at multiple of this duration since UNIX epoch
+    /// Buckets of a day or longer are aligned only to days, because weeks and months don't start with epoch
+    pub fn alignment(&self) -> Duration {
+        const MINUTE: u64 = 60;
+        const HOUR: u64 = 60 * MINUTE;
+        const DAY: u64 = 24 * HOUR;
+        let value = u64::from(self.value);
+        Duration::from_secs(match self.kind {
+            IntervalKind::Second => value,
+            IntervalKind::Minute => value * MINUTE,
+            IntervalKind::Hour => value * HOUR,
+            IntervalKind::Day | IntervalKind::Week | IntervalKind::Month | IntervalKind::Year => {
+                DAY
+            }
+        })
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 #[repr(u8)]
 pub enum IntervalKind {
@@ -0,0 +1,227 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use anyhow::Context as _;
+use flate2::{Compression, read::MultiGzDecoder, write::GzEncoder};
+
+use crate::{
+    utils::time::UnixMillis,
+    ws::{CodecIn, CodecOut, Message},
+};
+
+const EXTENSION: &str = "jsonl.gz";
+// gzip is flushed at least that often, so killed process loses only the last moment
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Raw inbound WS frame, one JSON line in a journal file
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct JournalEntry {
+    pub received_at: UnixMillis,
+    pub raw: String,
+}
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct JournalConfig {
+    /// Directory to append raw WS messages to, journal is disabled if not set
+    #[arg(long = "journal-dir")]
+    pub dir: Option<PathBuf>,
+    /// Start new journal file after this many uncompressed bytes
+    #[arg(long = "journal-rotate-bytes", default_value_t = 256 * 1024 * 1024)]
+    pub rotate_bytes: u64,
+}
+
+impl JournalConfig {
+    /// New journal of files named with `prefix`, if journal is enabled
+    pub fn writer(&self, prefix: &str) -> anyhow::Result<Option<JournalWriter>> {
+        self.dir
+            .clone()
+            .map(|dir| JournalWriter::new(dir, prefix, self.rotate_bytes))
+            .transpose()
+    }
+}
+
+/// Append-only journal of gzipped JSON lines.
+/// Each file is named after the time of its first entry, so files sort in order of writing.
+pub struct JournalWriter {
+    dir: PathBuf,
+    prefix: String,
+    rotate_bytes: u64,
+    file: Option<GzEncoder<BufWriter<File>>>,
+    // uncompressed bytes written to the current file
+    written: u64,
+    flushed_at: Instant,
+}
+
+impl JournalWriter {
+    pub fn new(dir: PathBuf, prefix: impl Into<String>, rotate_bytes: u64) -> anyhow::Result<Self> {
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("create journal directory {}", dir.display()))?;
+        Ok(Self {
+            dir,
+            prefix: prefix.into(),
+            rotate_bytes,
+            file: None,
+            written: 0,
+            flushed_at: Instant::now(),
+        })
+    }
+
+    pub fn append(&mut self, entry: &JournalEntry) -> anyhow::Result<()> {
+        let mut line = serde_json::to_vec(entry).context("serialize journal entry as JSON")?;
+        line.push(b'\n');
+
+        if self.written >= self.rotate_bytes {
+            self.finish()?;
+        }
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => {
+                let path = self.dir.join(format!(
+                    "{}-{:013}.{EXTENSION}",
+                    self.prefix,
+                    entry.received_at.as_i64()
+                ));
+                log::info!("Start journal file {}", path.display());
+                let file = File::create_new(&path)
+                    .with_context(|| format!("create journal file {}", path.display()))?;
+                self.written = 0;
+                self.file
+                    .insert(GzEncoder::new(BufWriter::new(file), Compression::default()))
+            }
+        };
+        file.write_all(&line).context("write journal entry")?;
+        self.written += line.len() as u64;
+
+        if self.flushed_at.elapsed() >= FLUSH_INTERVAL {
+            file.flush().context("flush journal file")?;
+            self.flushed_at = Instant::now();
+        }
+        Ok(())
+    }
+
+    /// Completes current file, next entry goes to a new one
+    pub fn finish(&mut self) -> anyhow::Result<()> {
+        if let Some(file) = self.file.take() {
+            file.finish()
+                .and_then(|mut file| file.flush())
+                .context("finish journal file")?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for JournalWriter {
+    fn drop(&mut self) {
+        if let Err(err) = self.finish() {
+            log::error!("Can't finish journal: {err:#}");
+        }
+    }
+}
+
+/// Codec wrapper which appends every inbound text frame to the journal before decoding it.
+/// Without journal it's just `inner`.
+pub struct JournalingCodec<C> {
+    pub inner: C,
+    pub journal: Option<JournalWriter>,
+}
+
+impl<C: CodecIn<MSG>, MSG> CodecIn<MSG> for JournalingCodec<C> {
+    fn process_in(&mut self, msg: Message) -> anyhow::Result<Option<MSG>> {
+        if let (Message::Text(text), Some(journal)) = (&msg, &mut self.journal) {
+            let entry = JournalEntry {
+                received_at: UnixMillis::now(),
+                raw: text.as_str().into(),
+            };
+            // losing journal shouldn't stop live data
+            if let Err(err) = journal.append(&entry) {
+                log::error!("Can't append WS message to journal: {err:#}");
+            }
+        }
+        self.inner.process_in(msg)
+    }
+}
+
+impl<C: CodecOut<MSG>, MSG> CodecOut<MSG> for JournalingCodec<C> {
+    fn process_out(&mut self, msg: MSG) -> anyhow::Result<Option<Message>> {
+        self.inner.process_out(msg)
+    }
+}
+
+/// Journal files in order of writing. Directories are expanded, files are taken as is.
+pub fn journal_files(paths: &[PathBuf]) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for path in paths {
+        if !path.is_dir() {
+            files.push(path.clone());
+            continue;
+        }
+        let mut dir_files = vec![];
+        for entry in fs::read_dir(path)
+            .with_context(|| format!("read journal directory {}", path.display()))?
+        {
+            let path = entry.context("read journal directory entry")?.path();
+            if path.to_string_lossy().ends_with(EXTENSION) {
+                dir_files.push(path);
+            }
+        }
+        dir_files.sort();
+        files.extend(dir_files);
+    }
+    Ok(files)
+}
+
+/// Reads entries of a journal file.
+/// File which wasn't finished (e.g. process was killed) ends with an error after the last flushed entry.
+pub fn read_journal(
+    path: &Path,
+) -> anyhow::Result<impl Iterator<Item = anyhow::Result<JournalEntry>>> {
+    let file = File::open(path).with_context(|| format!("open journal file {}", path.display()))?;
+    let reader = BufReader::new(MultiGzDecoder::new(BufReader::new(file)));
+    Ok(reader.lines().map(|line| {
+        let line = line.map_err(|err| match err.kind() {
+            io::ErrorKind::UnexpectedEof => {
+                anyhow::Error::new(err).context("journal file is truncated")
+            }
+            _ => anyhow::Error::new(err).context("read journal line"),
+        })?;
+        serde_json::from_str(&line).context("parse journal entry")
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_journal_rotation_and_read() {
+        let dir = std::env::temp_dir().join(format!("journal-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let entries: Vec<_> = (0..5)
+            .map(|i| JournalEntry {
+                received_at: UnixMillis(1648057140000 + i),
+                raw: format!(r#"{{"event":"pong","i":{i}}}"#),
+            })
+            .collect();
+        let mut writer = JournalWriter::new(dir.clone(), "test", 100).unwrap();
+        for entry in &entries {
+            writer.append(entry).unwrap();
+        }
+        drop(writer);
+
+        let files = journal_files(std::slice::from_ref(&dir)).unwrap();
+        assert_eq!(files.len(), 3);
+        let read: Vec<_> = files
+            .iter()
+            .flat_map(|file| read_journal(file).unwrap())
+            .collect::<anyhow::Result<_>>()
+            .unwrap();
+        assert_eq!(read, entries);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,7 +1,13 @@
-use std::{any::type_name, sync::Arc, time::Duration};
+use std::{
+    any::type_name,
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use anyhow::Context;
-use reqwest::{Url, header::CONTENT_TYPE};
+use anyhow::{Context, bail};
+pub use reqwest::Method;
+use reqwest::{StatusCode, Url, header::CONTENT_TYPE};
 use serde::de::DeserializeOwned;
 use utils::{
     time::SpanDuration,
@@ -9,9 +15,14 @@ use utils::{
 };
 
 pub mod auth;
+pub mod channel;
+pub mod exchange;
 pub mod interval;
+pub mod journal;
+pub mod pair;
 pub mod records;
 pub mod utils;
+pub mod validation;
 pub mod ws;
 
 #[derive(Debug)]
@@ -54,7 +65,12 @@ impl Default for HttpConfig {
 
 #[derive(Debug)]
 pub enum AuthMethod {
-    HmacSha256 { api_key: String, secret_key: String },
+    /// public endpoints only
+    None,
+    HmacSha256 {
+        api_key: String,
+        secret_key: String,
+    },
 }
 
 impl ApiFactory {
@@ -98,21 +114,34 @@ impl<C> ApiRequester<C> {
         &self.context
     }
 
+    /// E.g. to replace hardcoded symbols with ones loaded from exchange
+    pub fn context_mut(&mut self) -> &mut C {
+        &mut self.context
+    }
+
+    /// E.g. to authenticate WebSocket connection with the same keys
+    pub fn auth(&self) -> &AuthMethod {
+        &self.config.auth
+    }
+
     pub fn build_url<B: BuildUrl<C>>(&self, with: &B) -> anyhow::Result<Url> {
         UrlBuilder::build(&self.config.base_url, with, &self.context)
     }
 
     async fn send_request(
         &self,
-        method: reqwest::Method,
+        method: Method,
         url: Url,
+        body: Option<String>,
     ) -> anyhow::Result<reqwest::Response> {
         let mut req = self
             .client
             .request(method, url)
-            .header(CONTENT_TYPE, "application/json")
-            .build()
-            .context("build request")?;
+            .header(CONTENT_TYPE, "application/json");
+        if let Some(body) = body {
+            req = req.body(body);
+        }
+        let mut req = req.build().context("build request")?;
         self.config
             .auth
             .apply(&mut req)
@@ -120,47 +149,249 @@ impl<C> ApiRequester<C> {
         self.client.execute(req).await.context("execute request")
     }
 
-    async fn get_string(&self, url: Url) -> anyhow::Result<String> {
-        let res = self.send_request(reqwest::Method::GET, url).await?;
+    async fn request_string(
+        &self,
+        method: Method,
+        url: Url,
+        body: Option<String>,
+    ) -> anyhow::Result<String> {
+        let res = self.send_request(method, url, body).await?;
+        let status = res.status();
         // we don't use Response::json to separate different kinds of errors
-        res.text().await.context("receive JSON response")
+        let text = res.text().await.context("receive JSON response")?;
+        if !status.is_success() {
+            return Err(HttpStatusError { status, body: text }.into());
+        }
+        Ok(text)
     }
 
     pub async fn get_json<T: DeserializeOwned, B: BuildUrl<C>>(
         &self,
         build_url: &B,
+    ) -> anyhow::Result<T> {
+        self.get_json_of(build_url, UNTYPED_ENDPOINT).await
+    }
+
+    // `endpoint` is a label of request metrics
+    async fn get_json_of<T: DeserializeOwned, B: BuildUrl<C>>(
+        &self,
+        build_url: &B,
+        endpoint: &'static str,
     ) -> anyhow::Result<T> {
         let url = self.build_url(build_url).context("build url")?;
 
         let mut attempts = self.http_config.http_retry_attempts;
         let retry_after = self.http_config.http_retry_after;
         let json = loop {
-            match self.get_string(url.clone()).await {
+            let started = Instant::now();
+            let res = self.request_string(Method::GET, url.clone(), None).await;
+            observe_request(endpoint, started, res.is_ok());
+            match res {
                 Ok(ok) => break ok,
-                Err(err) if attempts > 0 => {
+                Err(err) if attempts > 0 && is_retryable(&err) => {
                     log::error!("HTTP GET {url:?}: {err:#}");
                     log::info!("{attempts} attempts left, waiting for {retry_after} before retry");
                     attempts -= 1;
+                    tokio::time::sleep(retry_after.into()).await;
                     continue;
                 }
                 Err(err) => return Err(err),
             }
         };
-        let res = serde_json::from_str(&json);
-        if res.is_err() {
-            log::error!("Can't parse json as {}: {json}", type_name::<T>());
-        }
-        res.context("parse response as JSON")
+        parse_json(&json, endpoint)
     }
 
     pub async fn get_response<R: Request<Response: serde::de::DeserializeOwned> + BuildUrl<C>>(
         &self,
         build_url: &R,
     ) -> anyhow::Result<R::Response> {
-        self.get_json(build_url).await
+        self.get_json_of(build_url, R::ENDPOINT).await
+    }
+
+    /// Sends request with its method and body once, without retries:
+    /// e.g. order placed twice is worse than a failed placement
+    pub async fn send<R>(&self, request: &R) -> anyhow::Result<R::Response>
+    where
+        R: Request<Response: DeserializeOwned> + BuildUrl<C> + BuildBody<C>,
+    {
+        let url = self.build_url(request).context("build url")?;
+        let body = request.build_body(&self.context).context("build body")?;
+        let started = Instant::now();
+        let res = self.request_string(R::METHOD, url.clone(), body).await;
+        observe_request(R::ENDPOINT, started, res.is_ok());
+        let json = res.with_context(|| format!("HTTP {} {url}", R::METHOD))?;
+        parse_json(&json, R::ENDPOINT)
     }
 }
 
+/// REST API of an exchange, or only its context if no requests can be made
+#[derive(Debug)]
+pub enum Api<C> {
+    Rest(ApiRequester<C>),
+    /// no requests can be made, e.g. to replay journal
+    Offline(C),
+}
+
+impl<C> Api<C> {
+    pub fn context(&self) -> &C {
+        match self {
+            Self::Rest(requester) => requester.context(),
+            Self::Offline(context) => context,
+        }
+    }
+
+    pub fn requester(&self) -> anyhow::Result<&ApiRequester<C>> {
+        match self {
+            Self::Rest(requester) => Ok(requester),
+            Self::Offline(_) => bail!("REST API isn't configured"),
+        }
+    }
+
+    pub fn requester_mut(&mut self) -> anyhow::Result<&mut ApiRequester<C>> {
+        match self {
+            Self::Rest(requester) => Ok(requester),
+            Self::Offline(_) => bail!("REST API isn't configured"),
+        }
+    }
+}
+
+/// Response with unsuccessful status.
+/// Exchange explains what's wrong in the body, like rejected order.
+#[derive(Debug)]
+pub struct HttpStatusError {
+    pub status: StatusCode,
+    pub body: String,
+}
+
+impl HttpStatusError {
+    /// Rate limit or server failure, the same request may succeed later
+    pub fn is_retryable(&self) -> bool {
+        self.status == StatusCode::TOO_MANY_REQUESTS || self.status.is_server_error()
+    }
+}
+
+impl fmt::Display for HttpStatusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "HTTP status {}: {}", self.status, self.body)
+    }
+}
+
+impl std::error::Error for HttpStatusError {}
+
+fn is_retryable(err: &anyhow::Error) -> bool {
+    match err.downcast_ref::<HttpStatusError>() {
+        Some(err) => err.is_retryable(),
+        // no response at all, like timeout or lost connection
+        None => true,
+    }
+}
+
+fn observe_request(endpoint: &'static str, started: Instant, ok: bool) {
+    metrics::histogram!("rest_request_duration_seconds", "endpoint" => endpoint)
+        .record(started.elapsed());
+    let outcome = if ok { "ok" } else { "error" };
+    metrics::counter!("rest_requests_total", "endpoint" => endpoint, "outcome" => outcome)
+        .increment(1);
+}
+
+fn parse_json<T: DeserializeOwned>(json: &str, endpoint: &'static str) -> anyhow::Result<T> {
+    let res = serde_json::from_str(json);
+    if res.is_err() {
+        metrics::counter!("rest_parse_errors_total", "endpoint" => endpoint).increment(1);
+        log::error!("Can't parse json as {}: {json}", type_name::<T>());
+    }
+    res.context("parse response as JSON")
+}
+
+// label of requests made with `get_json` instead of `get_response`
+const UNTYPED_ENDPOINT: &str = "untyped";
+
 pub trait Request {
     type Response;
+    /// path template of endpoint, label of request metrics
+    const ENDPOINT: &'static str;
+    const METHOD: Method = Method::GET;
+}
+
+/// JSON body of request, sent by `ApiRequester::send`
+pub trait BuildBody<C> {
+    /// no body by default, like cancellation of a single order
+    fn build_body(&self, _context: &C) -> anyhow::Result<Option<String>> {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use axum::{Router, routing::get};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// Replies with `status` to the first `failures` requests and with `[1]` afterwards, counts requests
+    async fn stand_in_requester(status: u16, failures: u32) -> (ApiRequester<()>, Arc<AtomicU32>) {
+        let requests = Arc::new(AtomicU32::new(0));
+        let counter = requests.clone();
+        let rest = Router::new().route(
+            "/flaky",
+            get(move || async move {
+                let status = match counter.fetch_add(1, Ordering::SeqCst) < failures {
+                    true => axum::http::StatusCode::from_u16(status).unwrap(),
+                    false => axum::http::StatusCode::OK,
+                };
+                (status, "[1]")
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, rest).await.unwrap() });
+
+        let http_config = HttpConfig {
+            http_read_timeout: Duration::from_secs(5).into(),
+            http_retry_after: Duration::from_millis(10).into(),
+            http_retry_attempts: 2,
+        };
+        let config = ApiConfig {
+            base_url: base_url.as_str().try_into().unwrap(),
+            auth: AuthMethod::None,
+        };
+        let requester = ApiFactory::init(http_config)
+            .unwrap()
+            .make_requester(config, ());
+        (requester, requests)
+    }
+
+    #[tokio::test]
+    async fn test_retry_server_errors() {
+        let (requester, requests) = stand_in_requester(503, 2).await;
+        let res: Vec<u8> = requester.get_json(&"flaky").await.unwrap();
+        assert_eq!(res, [1]);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+
+        let (requester, requests) = stand_in_requester(429, 3).await;
+        let err = requester
+            .get_json::<Vec<u8>, _>(&"flaky")
+            .await
+            .unwrap_err();
+        let status = err.downcast_ref::<HttpStatusError>().unwrap().status;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_no_retry_of_client_errors() {
+        let (requester, requests) = stand_in_requester(400, 1).await;
+        let err = requester
+            .get_json::<Vec<u8>, _>(&"flaky")
+            .await
+            .unwrap_err();
+        assert!(
+            !err.downcast_ref::<HttpStatusError>()
+                .unwrap()
+                .is_retryable()
+        );
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
 }
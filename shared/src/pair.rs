@@ -0,0 +1,185 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{Context as _, bail};
+
+use crate::utils::{
+    ValueLabel,
+    sorted_vec::{Entry, SortedVec},
+};
+
+// dict to convert exchange symbol to our pair and back
+pub struct ExchangeSymbols;
+impl ValueLabel for ExchangeSymbols {
+    type Value = SymbolsDict;
+}
+
+/// Exchange-neutral currency pair, serialized as "BASE/QUOTE"
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
+pub struct Pair {
+    pub base: String,
+    pub quote: String,
+}
+
+impl Pair {
+    pub const SEPARATOR: char = '/';
+
+    pub fn new(base: impl Into<String>, quote: impl Into<String>) -> Self {
+        Self {
+            base: base.into(),
+            quote: quote.into(),
+        }
+    }
+}
+
+impl fmt::Display for Pair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}", self.base, Self::SEPARATOR, self.quote)
+    }
+}
+
+impl FromStr for Pair {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (base, quote) = s
+            .split_once(Self::SEPARATOR)
+            .with_context(|| format!("pair {s:?} has no separator"))?;
+        if base.is_empty() || quote.is_empty() {
+            bail!("pair {s:?} has empty asset");
+        }
+        Ok(Self::new(base, quote))
+    }
+}
+
+impl serde::Serialize for Pair {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Pair {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+// Two-way dictionary of exchange symbols and our pairs, same approach as `IntervalsDict`
+// Asset renames are applied while adding symbols, so exchange's old and new names map to the same pair
+#[derive(Debug, Default)]
+pub struct SymbolsDict {
+    symbols: SortedVec<String, Pair>,
+    pairs: SortedVec<Pair, String>,
+    // exchange asset name -> our asset name
+    renamed_assets: SortedVec<String, String>,
+    // pairs which can't be traded or subscribed anymore, but still can be found in history
+    delisted: SortedVec<Pair, ()>,
+}
+
+impl SymbolsDict {
+    pub fn rename_asset(
+        &mut self,
+        exchange_asset: impl Into<String>,
+        our_asset: impl Into<String>,
+    ) -> anyhow::Result<()> {
+        let exchange_asset = exchange_asset.into();
+        let our_asset = our_asset.into();
+        match self.renamed_assets.entry(exchange_asset) {
+            Entry::Occupied(entry) => {
+                let (exchange_asset, stored) = entry.key_value();
+                bail!(
+                    "asset rename is already stored; asset: {exchange_asset:?}, old name: {stored:?}, new name: {our_asset:?}"
+                );
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(our_asset);
+            }
+        }
+        Ok(())
+    }
+
+    fn our_asset<'a>(&'a self, exchange_asset: &'a str) -> &'a str {
+        self.renamed_assets
+            .get(exchange_asset)
+            .map_or(exchange_asset, String::as_str)
+    }
+
+    /// Adds symbol with explicitly specified exchange assets, for exchanges without separator in symbols
+    pub fn add(&mut self, symbol: String, base: &str, quote: &str) -> anyhow::Result<()> {
+        let pair = Pair::new(self.our_asset(base), self.our_asset(quote));
+        if let Some(stored_pair) = self.symbols.get(symbol.as_str()) {
+            bail!(
+                "symbol is already stored; symbol: {symbol:?}, old pair: {stored_pair}, new pair: {pair}"
+            );
+        }
+        // old and new names of renamed asset can be listed both, first one is used for requests
+        if let Entry::Vacant(entry) = self.pairs.entry(pair.clone()) {
+            entry.insert(symbol.clone());
+        }
+        if let Entry::Vacant(entry) = self.symbols.entry(symbol) {
+            entry.insert(pair);
+        }
+        Ok(())
+    }
+
+    /// Adds symbol like "BTC_USDT"
+    pub fn add_separated(&mut self, symbol: &str, separator: char) -> anyhow::Result<()> {
+        let (base, quote) = symbol
+            .split_once(separator)
+            .with_context(|| format!("symbol {symbol:?} has no separator {separator:?}"))?;
+        self.add(symbol.into(), base, quote)
+    }
+
+    pub fn with_separated<'s>(
+        mut self,
+        separator: char,
+        symbols: impl IntoIterator<Item = &'s str>,
+    ) -> anyhow::Result<Self> {
+        for symbol in symbols {
+            self.add_separated(symbol, separator)?;
+        }
+        Ok(self)
+    }
+
+    pub fn with_renamed_assets<'s>(
+        mut self,
+        renames: impl IntoIterator<Item = (&'s str, &'s str)>,
+    ) -> anyhow::Result<Self> {
+        for (exchange_asset, our_asset) in renames {
+            self.rename_asset(exchange_asset, our_asset)?;
+        }
+        Ok(self)
+    }
+
+    pub fn with_delisted(mut self, pairs: impl IntoIterator<Item = Pair>) -> Self {
+        for pair in pairs {
+            self.delisted.get_mut_or_insert_default(pair);
+        }
+        self
+    }
+
+    pub fn is_delisted(&self, pair: &Pair) -> bool {
+        self.delisted.get(pair).is_some()
+    }
+
+    pub fn to_symbol(&self, pair: &Pair) -> anyhow::Result<&str> {
+        if self.is_delisted(pair) {
+            bail!("pair {pair} is delisted");
+        }
+        self.pairs
+            .get(pair)
+            .map(String::as_str)
+            .with_context(|| format!("pair {pair} is unknown to exchange"))
+    }
+
+    pub fn to_pair(&self, symbol: &str) -> anyhow::Result<&Pair> {
+        self.symbols
+            .get(symbol)
+            .with_context(|| format!("symbol {symbol:?} is unknown"))
+    }
+
+    /// Pairs which can be requested or subscribed
+    pub fn listed(&self) -> impl Iterator<Item = &Pair> {
+        self.pairs.keys().filter(|pair| !self.is_delisted(pair))
+    }
+}
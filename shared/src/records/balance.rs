@@ -0,0 +1,16 @@
+use crate::utils::time::UnixMillis;
+
+/// Баланс одной валюты на спотовом счёте
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Balance {
+    /// биржа, с которой получена запись (poloniex, binance)
+    pub exchange: String,
+    /// валюта (как у нас, как в названии пары)
+    pub currency: String,
+    /// доступно для новых ордеров и вывода
+    pub available: String,
+    /// заблокировано открытыми ордерами
+    pub hold: String,
+    /// время unix, когда баланс получен с биржи
+    pub timestamp: UnixMillis,
+}
@@ -0,0 +1,16 @@
+use crate::utils::time::UnixMillis;
+
+/// Текущий уровень комиссий аккаунта
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct FeeTier {
+    /// биржа, с которой получена запись (poloniex, binance)
+    pub exchange: String,
+    /// доля комиссии за сделку мейкера (0.001 = 0.1%)
+    pub maker_rate: String,
+    /// доля комиссии за сделку тейкера
+    pub taker_rate: String,
+    /// объём торгов за 30 дней в USD, по которому биржа определила уровень
+    pub volume_30d: String,
+    /// время unix, когда уровень получен с биржи
+    pub timestamp: UnixMillis,
+}
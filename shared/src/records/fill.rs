@@ -0,0 +1,32 @@
+use crate::{pair::Pair, utils::time::UnixNanos};
+
+/// Исполнение (полное или частичное) нашего ордера
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Fill {
+    /// id исполнения на бирже
+    pub id: String,
+    /// биржа, с которой получена запись (poloniex, binance)
+    pub exchange: String,
+    /// id ордера на бирже
+    pub order_id: String,
+    /// наш id ордера, пустой если ордер выставлен без него
+    pub client_order_id: String,
+    /// название валютной пары (как у нас)
+    pub pair: Pair,
+    /// сторона нашего ордера (buy или sell)
+    pub side: String,
+    /// maker или taker
+    pub role: String,
+    /// цена исполнения
+    pub price: String,
+    /// объём исполнения в базовой валюте
+    pub amount: String,
+    /// объём исполнения в котируемой валюте
+    pub quote_amount: String,
+    /// комиссия
+    pub fee: String,
+    /// валюта комиссии
+    pub fee_currency: String,
+    /// время UTC UnixNano
+    pub timestamp: UnixNanos,
+}
@@ -1,8 +1,14 @@
-/// Структура KL как в ТЗ тестового задания
-#[derive(Debug, serde::Serialize)]
+use crate::{pair::Pair, utils::time::UnixMillis};
+
+/// Расширенная структура KL, надмножество [`KlineV1`]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct Kline {
+    /// версия схемы записи, всегда [`Kline::SCHEMA_VERSION`]
+    pub schema: u16,
+    /// биржа, с которой получена запись (poloniex, binance)
+    pub exchange: String,
     /// название пары как у нас
-    pub pair: String,
+    pub pair: Pair,
     /// период формирования свечи (1m, 15m, 1h, 1d)
     pub time_frame: String,
     /// open - цена открытия
@@ -14,11 +20,91 @@ pub struct Kline {
     /// close - цена закрытия
     pub c: f64,
     /// время unix начала формирования свечки
-    pub utc_begin: i64,
+    pub utc_begin: UnixMillis,
+    /// время unix окончания формирования свечки (включительно)
+    pub utc_end: UnixMillis,
+    /// время unix, когда биржа сформировала запись
+    pub utc_record: UnixMillis,
+    /// сформирована ли свечка окончательно
+    pub status: KlineStatus,
+    /// количество сделок
+    pub trade_count: u32,
+    /// средневзвешенная по объёму цена, 0 если сделок не было
+    pub vwap: f64,
     pub volume_bs: VBS,
 }
 
-#[derive(Debug, serde::Serialize, Default)]
+impl Kline {
+    pub const SCHEMA_VERSION: u16 = 3;
+}
+
+/// Структура KL как в ТЗ тестового задания
+#[derive(Debug, serde::Serialize)]
+pub struct KlineV1<'a> {
+    /// название пары как у нас
+    pub pair: &'a Pair,
+    /// период формирования свечи (1m, 15m, 1h, 1d)
+    pub time_frame: &'a str,
+    /// open - цена открытия
+    pub o: f64,
+    /// high - максимальная цена
+    pub h: f64,
+    /// low - минимальная цена
+    pub l: f64,
+    /// close - цена закрытия
+    pub c: f64,
+    /// время unix начала формирования свечки
+    pub utc_begin: UnixMillis,
+    pub volume_bs: &'a VBS,
+}
+
+impl<'a> From<&'a Kline> for KlineV1<'a> {
+    fn from(kline: &'a Kline) -> Self {
+        let Kline {
+            pair,
+            time_frame,
+            o,
+            h,
+            l,
+            c,
+            utc_begin,
+            volume_bs,
+            ..
+        } = kline;
+        Self {
+            pair,
+            time_frame,
+            o: *o,
+            h: *h,
+            l: *l,
+            c: *c,
+            utc_begin: *utc_begin,
+            volume_bs,
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KlineStatus {
+    /// bucket is still open, values may change with next update
+    InProgress,
+    /// bucket is finalized, safe to act upon
+    Closed,
+}
+
+impl KlineStatus {
+    /// Exchange snapshot is final only if it was taken after the bucket has ended
+    pub fn from_record_time(record_time: UnixMillis, close_time: UnixMillis) -> Self {
+        if record_time > close_time {
+            Self::Closed
+        } else {
+            Self::InProgress
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Default)]
 pub struct VBS {
     /// объём покупок в базовой валюте
     pub buy_base: f64,
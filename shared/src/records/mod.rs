@@ -1,2 +1,5 @@
+pub mod balance;
+pub mod fee_tier;
+pub mod fill;
 pub mod kline;
 pub mod recent_trade;
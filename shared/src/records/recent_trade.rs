@@ -1,16 +1,27 @@
+use crate::{pair::Pair, utils::time::UnixNanos};
+
 /// Структура RT как в ТЗ тестового задания
-#[derive(Debug, serde::Serialize)]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct RecentTrade {
+    /// версия схемы записи, всегда [`RecentTrade::SCHEMA_VERSION`]
+    pub schema: u16,
     /// id транзакции
     pub tid: String,
+    /// биржа, с которой получена запись (poloniex, binance)
+    pub exchange: String,
     /// название валютной пары (как у нас)
-    pub pair: String,
+    pub pair: Pair,
     /// цена транзакции
     pub price: String,
-    /// объём транзакции в базовой валюте
+    /// объём транзакции в базовой валюте (в схеме 1 у записей из WS Poloniex был в котируемой)
     pub amount: String,
     /// как биржа засчитала эту сделку (как buy или как sell)
     pub side: String,
     /// время UTC UnixNano
-    pub timestamp: i64,
+    pub timestamp: UnixNanos,
+}
+
+impl RecentTrade {
+    /// 2: `amount` is in base units for every source
+    pub const SCHEMA_VERSION: u16 = 2;
 }
@@ -73,7 +73,7 @@ impl<K: Ord, V> SortedVec<K, V> {
         self.entry_inner(key).get(&self.inner)
     }
 
-    pub fn entry_ref<'q, Q>(&mut self, key: &'q Q) -> Entry<K, V, &'q Q>
+    pub fn entry_ref<'q, Q>(&mut self, key: &'q Q) -> Entry<'_, K, V, &'q Q>
     where
         Q: ?Sized + Comparable<K>,
         &'q Q: Into<K>,
@@ -81,7 +81,7 @@ impl<K: Ord, V> SortedVec<K, V> {
         self.entry_inner(key).into_entry(&mut self.inner, key)
     }
 
-    pub fn entry<Q>(&mut self, key: Q) -> Entry<K, V, Q>
+    pub fn entry<Q>(&mut self, key: Q) -> Entry<'_, K, V, Q>
     where
         Q: Into<K> + Comparable<K>,
     {
@@ -122,7 +122,7 @@ struct EntryInner {
 }
 
 impl EntryInner {
-    fn into_entry<K, V, Q>(self, vec: &mut Vec<(K, V)>, key: Q) -> Entry<K, V, Q> {
+    fn into_entry<K, V, Q>(self, vec: &mut Vec<(K, V)>, key: Q) -> Entry<'_, K, V, Q> {
         let EntryInner { pos, occupied } = self;
         if occupied {
             Entry::Occupied(OccupiedEntry { pos, vec })
@@ -3,35 +3,184 @@ use std::{fmt, str::FromStr, time::Duration};
 use anyhow::Context;
 use jiff::{Span, Timestamp};
 
-/// return UNIX timestamp in milliseconds since 'Thu Jan 01 1970 00:00:00.000'
-/// u64 is enough for another ~585 million years
-fn millis_since_unix_epoch(ts: Timestamp) -> u64 {
-    let duration = ts.duration_since(Timestamp::UNIX_EPOCH);
-    duration.as_millis().clamp(0, u64::MAX as _) as _
+const NANOS_PER_MILLI: i64 = 1_000_000;
+
+/// UNIX timestamp in milliseconds since 'Thu Jan 01 1970 00:00:00.000'
+/// i64 is what BSON and most exchanges use, enough for another ~292 million years
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[serde(transparent)]
+pub struct UnixMillis(pub i64);
+
+/// UNIX timestamp in nanoseconds since 'Thu Jan 01 1970 00:00:00.000'
+/// i64 is enough until year 2262
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[serde(transparent)]
+pub struct UnixNanos(pub i64);
+
+impl UnixMillis {
+    pub fn now() -> Self {
+        Timestamp::now().into()
+    }
+
+    pub fn as_i64(self) -> i64 {
+        self.0
+    }
+
+    pub fn to_nanos(self) -> anyhow::Result<UnixNanos> {
+        self.0
+            .checked_mul(NANOS_PER_MILLI)
+            .map(UnixNanos)
+            .context("milliseconds timestamp overflows nanoseconds")
+    }
+
+    pub fn saturating_add(self, duration: Duration) -> Self {
+        let millis = duration.as_millis().try_into().unwrap_or(i64::MAX);
+        Self(self.0.saturating_add(millis))
+    }
+
+    pub fn saturating_sub(self, duration: Duration) -> Self {
+        let millis = duration.as_millis().try_into().unwrap_or(i64::MAX);
+        Self(self.0.saturating_sub(millis))
+    }
+
+    /// Human readable, like "2025-02-06T22:05:59.999Z"
+    pub fn display(self) -> impl fmt::Display {
+        Timestamp::try_from(self).map_or_else(|_| format!("{}ms", self.0), |ts| ts.to_string())
+    }
+
+    // Converts "2025-02-06T22:05:59.999Z" to "2025-02-06T22:06:00.000Z"
+    // Because polonies likes round start_time
+    // {
+    //    "code" : 24105,
+    //    "message" : "Invalid start time!"
+    // }
+    // Alternatively, we can pass our Interval, to round up more smartly, not just to next second
+    pub fn ceil_second(self) -> Self {
+        Self((self.0 + 1) / 1000 * 1000)
+    }
 }
 
-pub fn timestamp_now() -> u64 {
-    millis_since_unix_epoch(Timestamp::now())
+impl UnixNanos {
+    pub fn now() -> Self {
+        // can't overflow until year 2262
+        Timestamp::now().try_into().unwrap_or(Self(i64::MAX))
+    }
+
+    pub fn as_i64(self) -> i64 {
+        self.0
+    }
+
+    /// Truncates to whole milliseconds
+    pub fn to_millis(self) -> UnixMillis {
+        UnixMillis(self.0.div_euclid(NANOS_PER_MILLI))
+    }
+
+    /// Human readable, like "2025-02-06T22:05:59.999999999Z"
+    pub fn display(self) -> impl fmt::Display {
+        Timestamp::try_from(self).map_or_else(|_| format!("{}ns", self.0), |ts| ts.to_string())
+    }
+}
+
+impl From<UnixMillis> for i64 {
+    fn from(value: UnixMillis) -> Self {
+        value.0
+    }
 }
 
-pub fn timestamp_parse(ts: &str) -> anyhow::Result<u64> {
-    let ts: Timestamp = ts.parse().context("parse timestamp")?;
-    Ok(millis_since_unix_epoch(ts))
+impl From<UnixNanos> for i64 {
+    fn from(value: UnixNanos) -> Self {
+        value.0
+    }
 }
 
-pub fn timestamp_display(ts: u64) -> impl fmt::Display {
-    Timestamp::UNIX_EPOCH.saturating_add(Duration::from_millis(ts))
+impl TryFrom<u64> for UnixMillis {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u64) -> Result<Self, Self::Error> {
+        value
+            .try_into()
+            .map(Self)
+            .context("milliseconds timestamp overflows i64")
+    }
 }
 
-// Converts "2025-02-06T22:05:59.999Z" to "2025-02-06T22:06:00.000Z"
-// Because polonies likes round start_time
-// {
-//    "code" : 24105,
-//    "message" : "Invalid start time!"
-// }
-// Alternatively, we can pass our Interval, to round up more smartly, not just to next second
-pub fn timestamp_ceil(ts: u64) -> u64 {
-    (ts + 1) / 1000 * 1000
+impl TryFrom<UnixMillis> for UnixNanos {
+    type Error = anyhow::Error;
+
+    fn try_from(value: UnixMillis) -> Result<Self, Self::Error> {
+        value.to_nanos()
+    }
+}
+
+impl From<UnixNanos> for UnixMillis {
+    fn from(value: UnixNanos) -> Self {
+        value.to_millis()
+    }
+}
+
+impl From<Timestamp> for UnixMillis {
+    fn from(value: Timestamp) -> Self {
+        Self(value.as_millisecond())
+    }
+}
+
+impl TryFrom<Timestamp> for UnixNanos {
+    type Error = anyhow::Error;
+
+    fn try_from(value: Timestamp) -> Result<Self, Self::Error> {
+        value
+            .as_nanosecond()
+            .try_into()
+            .map(Self)
+            .context("timestamp overflows i64 nanoseconds")
+    }
+}
+
+impl TryFrom<UnixMillis> for Timestamp {
+    type Error = anyhow::Error;
+
+    fn try_from(value: UnixMillis) -> Result<Self, Self::Error> {
+        Timestamp::from_millisecond(value.0).context("milliseconds timestamp is out of range")
+    }
+}
+
+impl TryFrom<UnixNanos> for Timestamp {
+    type Error = anyhow::Error;
+
+    fn try_from(value: UnixNanos) -> Result<Self, Self::Error> {
+        Timestamp::from_nanosecond(value.0.into()).context("nanoseconds timestamp is out of range")
+    }
+}
+
+#[cfg(feature = "bson")]
+mod bson_impls {
+    use super::{UnixMillis, UnixNanos};
+
+    impl From<UnixMillis> for bson::Bson {
+        fn from(value: UnixMillis) -> Self {
+            bson::Bson::Int64(value.0)
+        }
+    }
+
+    impl From<UnixNanos> for bson::Bson {
+        fn from(value: UnixNanos) -> Self {
+            bson::Bson::Int64(value.0)
+        }
+    }
+}
+
+impl FromStr for UnixMillis {
+    type Err = anyhow::Error;
+
+    /// Parses RFC 3339 timestamp, like "2024-12-01T00:00:00Z"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let ts: Timestamp = s.parse().context("parse timestamp")?;
+        Ok(ts.into())
+    }
 }
 
 /// `std::time::Duration` with `impl FromStr` of `jiff::Span`
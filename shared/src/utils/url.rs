@@ -120,7 +120,7 @@ impl<'a> UrlBuilder<'a> {
         self.res.set(url)
     }
 
-    pub fn query_builder(&mut self) -> anyhow::Result<UrlQueryBuilder> {
+    pub fn query_builder(&mut self) -> anyhow::Result<UrlQueryBuilder<'_>> {
         let url = self.res.get_mut()?;
         Ok(UrlQueryBuilder {
             encoder: url.query_pairs_mut(),
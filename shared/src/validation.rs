@@ -0,0 +1,360 @@
+use std::{
+    fmt,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use crate::{
+    interval::DatabaseIntervals,
+    records::{kline::Kline, recent_trade::RecentTrade},
+    utils::{Has, time::UnixMillis},
+};
+
+/// Sanity checks of a record, received from exchange
+pub trait Validate<C> {
+    fn validate(&self, context: &C, issues: &mut Vec<Issue>);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Issue {
+    NotFinite {
+        field: &'static str,
+    },
+    NotPositive {
+        field: &'static str,
+    },
+    Negative {
+        field: &'static str,
+    },
+    Unparsable {
+        field: &'static str,
+    },
+    /// `l <= o, c <= h` doesn't hold
+    OutOfRange {
+        field: &'static str,
+    },
+    /// `utc_begin` isn't multiple of interval
+    Misaligned,
+    UnknownTimeFrame,
+    UnknownSide,
+    /// too old or in the future
+    InsaneTimestamp,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFinite { field } => write!(f, "{field} is not finite"),
+            Self::NotPositive { field } => write!(f, "{field} is not positive"),
+            Self::Negative { field } => write!(f, "{field} is negative"),
+            Self::Unparsable { field } => write!(f, "{field} is not a number"),
+            Self::OutOfRange { field } => write!(f, "{field} is out of low-high range"),
+            Self::Misaligned => write!(f, "begin time is not aligned to interval"),
+            Self::UnknownTimeFrame => write!(f, "time frame is unknown"),
+            Self::UnknownSide => write!(f, "side is unknown"),
+            Self::InsaneTimestamp => write!(f, "timestamp is too old or in the future"),
+        }
+    }
+}
+
+/// What to do with invalid records
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ValidationPolicy {
+    /// drop invalid records
+    Reject,
+    /// store invalid records separately from valid ones
+    Quarantine,
+    /// store invalid records as valid ones, only log issues
+    Log,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Verdict {
+    Accept,
+    Reject(Vec<Issue>),
+    Quarantine(Vec<Issue>),
+}
+
+#[derive(Debug, Default)]
+pub struct ValidationCounters {
+    pub valid: AtomicU64,
+    pub rejected: AtomicU64,
+    pub quarantined: AtomicU64,
+    /// invalid, but accepted because of `ValidationPolicy::Log`
+    pub logged: AtomicU64,
+}
+
+impl fmt::Display for ValidationCounters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "valid: {}, rejected: {}, quarantined: {}, logged: {}",
+            self.valid.load(Ordering::Relaxed),
+            self.rejected.load(Ordering::Relaxed),
+            self.quarantined.load(Ordering::Relaxed),
+            self.logged.load(Ordering::Relaxed),
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct Validator {
+    policy: ValidationPolicy,
+    counters: ValidationCounters,
+}
+
+impl Validator {
+    pub fn new(policy: ValidationPolicy) -> Self {
+        Self {
+            policy,
+            counters: Default::default(),
+        }
+    }
+
+    pub fn counters(&self) -> &ValidationCounters {
+        &self.counters
+    }
+
+    pub fn check<C, T: Validate<C> + fmt::Debug>(&self, record: &T, context: &C) -> Verdict {
+        let mut issues = vec![];
+        record.validate(context, &mut issues);
+        if issues.is_empty() {
+            self.counters.valid.fetch_add(1, Ordering::Relaxed);
+            return Verdict::Accept;
+        }
+        log::warn!("Invalid record {record:?}: {}", IssuesDisplay(&issues));
+        let counter = match self.policy {
+            ValidationPolicy::Reject => &self.counters.rejected,
+            ValidationPolicy::Quarantine => &self.counters.quarantined,
+            ValidationPolicy::Log => &self.counters.logged,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        match self.policy {
+            ValidationPolicy::Reject => Verdict::Reject(issues),
+            ValidationPolicy::Quarantine => Verdict::Quarantine(issues),
+            ValidationPolicy::Log => Verdict::Accept,
+        }
+    }
+}
+
+pub struct IssuesDisplay<'a>(pub &'a [Issue]);
+
+impl fmt::Display for IssuesDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, issue) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            issue.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+// Bitcoin genesis block, nothing was traded before
+const OLDEST_SANE_TIMESTAMP: UnixMillis = UnixMillis(1231006505000);
+// exchange clocks can be slightly ahead of ours
+const MAX_CLOCK_SKEW: Duration = Duration::from_secs(5 * 60);
+
+fn is_sane_timestamp(ts: UnixMillis) -> bool {
+    ts >= OLDEST_SANE_TIMESTAMP && ts <= UnixMillis::now().saturating_add(MAX_CLOCK_SKEW)
+}
+
+fn check_price(value: f64, field: &'static str, issues: &mut Vec<Issue>) -> bool {
+    if !value.is_finite() {
+        issues.push(Issue::NotFinite { field });
+    } else if value <= 0.0 {
+        issues.push(Issue::NotPositive { field });
+    } else {
+        return true;
+    }
+    false
+}
+
+fn check_volume(value: f64, field: &'static str, issues: &mut Vec<Issue>) {
+    if !value.is_finite() {
+        issues.push(Issue::NotFinite { field });
+    } else if value < 0.0 {
+        issues.push(Issue::Negative { field });
+    }
+}
+
+impl<C: Has<DatabaseIntervals>> Validate<C> for Kline {
+    fn validate(&self, context: &C, issues: &mut Vec<Issue>) {
+        let Kline {
+            time_frame,
+            o,
+            h,
+            l,
+            c,
+            utc_begin,
+            utc_end,
+            vwap,
+            volume_bs,
+            ..
+        } = self;
+
+        let prices = [("o", *o), ("h", *h), ("l", *l), ("c", *c)];
+        let mut prices_ok = true;
+        for (field, value) in prices {
+            prices_ok &= check_price(value, field, issues);
+        }
+        if prices_ok {
+            for (field, value) in [("o", *o), ("c", *c)] {
+                if value < *l || value > *h {
+                    issues.push(Issue::OutOfRange { field });
+                }
+            }
+        }
+
+        check_volume(*vwap, "vwap", issues);
+        check_volume(volume_bs.buy_base, "buy_base", issues);
+        check_volume(volume_bs.sell_base, "sell_base", issues);
+        check_volume(volume_bs.buy_quote, "buy_quote", issues);
+        check_volume(volume_bs.sell_quote, "sell_quote", issues);
+
+        if !is_sane_timestamp(*utc_begin) || utc_end < utc_begin {
+            issues.push(Issue::InsaneTimestamp);
+        }
+        match context.give(DatabaseIntervals).to_interval(time_frame) {
+            Some(interval) => {
+                let alignment = interval.alignment().as_millis() as i64;
+                if alignment > 0 && utc_begin.as_i64() % alignment != 0 {
+                    issues.push(Issue::Misaligned);
+                }
+            }
+            None => issues.push(Issue::UnknownTimeFrame),
+        }
+    }
+}
+
+impl<C> Validate<C> for RecentTrade {
+    fn validate(&self, _context: &C, issues: &mut Vec<Issue>) {
+        let RecentTrade {
+            price,
+            amount,
+            side,
+            timestamp,
+            ..
+        } = self;
+        for (field, value) in [("price", price), ("amount", amount)] {
+            match value.parse() {
+                Ok(value) => {
+                    check_price(value, field, issues);
+                }
+                Err(_) => issues.push(Issue::Unparsable { field }),
+            }
+        }
+        if !matches!(side.as_str(), "buy" | "sell") {
+            issues.push(Issue::UnknownSide);
+        }
+        if !is_sane_timestamp(timestamp.to_millis()) {
+            issues.push(Issue::InsaneTimestamp);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        interval::{IntervalsDict, database_intervals},
+        pair::Pair,
+        records::kline::{KlineStatus, VBS},
+        utils::time::UnixNanos,
+    };
+
+    struct TestContext(IntervalsDict);
+
+    impl Has<DatabaseIntervals> for TestContext {
+        fn give(&self, _label: DatabaseIntervals) -> &IntervalsDict {
+            &self.0
+        }
+    }
+
+    fn kline() -> Kline {
+        Kline {
+            schema: Kline::SCHEMA_VERSION,
+            exchange: "poloniex".into(),
+            pair: Pair::new("BTC", "USDT"),
+            time_frame: "15m".into(),
+            o: 100.0,
+            h: 110.0,
+            l: 90.0,
+            c: 95.0,
+            utc_begin: UnixMillis(1648057500000),
+            utc_end: UnixMillis(1648058399999),
+            utc_record: UnixMillis(1648058400100),
+            status: KlineStatus::Closed,
+            trade_count: 2,
+            vwap: 100.0,
+            volume_bs: VBS::default(),
+        }
+    }
+
+    fn issues<T: Validate<TestContext>>(record: &T) -> Vec<Issue> {
+        let context = TestContext(database_intervals().unwrap());
+        let mut issues = vec![];
+        record.validate(&context, &mut issues);
+        issues
+    }
+
+    #[test]
+    fn test_kline_validation() {
+        assert_eq!(issues(&kline()), vec![]);
+
+        let mut invalid = kline();
+        invalid.o = 120.0;
+        invalid.c = f64::NAN;
+        invalid.volume_bs.sell_base = -1.0;
+        invalid.utc_begin = UnixMillis(1648057560000);
+        assert_eq!(
+            issues(&invalid),
+            vec![
+                Issue::NotFinite { field: "c" },
+                Issue::Negative { field: "sell_base" },
+                Issue::Misaligned,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_recent_trade_validation() {
+        let recent_trade = RecentTrade {
+            schema: RecentTrade::SCHEMA_VERSION,
+            tid: "1".into(),
+            exchange: "poloniex".into(),
+            pair: Pair::new("BTC", "USDT"),
+            price: "100".into(),
+            amount: "0".into(),
+            side: "short".into(),
+            // milliseconds mistaken for nanoseconds
+            timestamp: UnixNanos(1648057141081),
+        };
+        assert_eq!(
+            issues(&recent_trade),
+            vec![
+                Issue::NotPositive { field: "amount" },
+                Issue::UnknownSide,
+                Issue::InsaneTimestamp,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validator_policy() {
+        let context = TestContext(database_intervals().unwrap());
+        let mut invalid = kline();
+        invalid.h = 0.0;
+
+        let validator = Validator::new(ValidationPolicy::Quarantine);
+        assert_eq!(validator.check(&kline(), &context), Verdict::Accept);
+        assert!(matches!(
+            validator.check(&invalid, &context),
+            Verdict::Quarantine(_)
+        ));
+        let validator = Validator::new(ValidationPolicy::Log);
+        assert_eq!(validator.check(&invalid, &context), Verdict::Accept);
+        assert_eq!(validator.counters().logged.load(Ordering::Relaxed), 1);
+    }
+}
@@ -0,0 +1,233 @@
+//! Public market data of any exchange, which reconnects and restores subscriptions by itself
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    future::Future,
+    marker::PhantomData,
+};
+
+use anyhow::Context as _;
+
+use super::{
+    Parsed, SimpleJsonCodec, Uri, WsClient,
+    reconnecting::{Connector, Received, ReconnectingClient},
+};
+use crate::{
+    exchange::{MarketEvent, MarketStream, MarketUpdate, RequestId},
+    journal::{JournalConfig, JournalingCodec},
+    pair::{ExchangeSymbols, Pair},
+    utils::{Has, time::UnixMillis},
+};
+
+/// Public server of an exchange. Keeps requests, which wait for replies, so replies are matched with them.
+pub trait MarketProtocol: Default {
+    type Channel: Copy + Ord;
+    /// Symbols of pairs, messages are normalized with it
+    type Context: Has<ExchangeSymbols>;
+    type ClientMsg;
+    type ServerMsg;
+
+    /// Journal file names start with it
+    const JOURNAL_PREFIX: &'static str;
+
+    fn connect(
+        uri: Uri,
+        codec: JournalingCodec<SimpleJsonCodec>,
+    ) -> impl Future<Output = anyhow::Result<WsClient<Self::ClientMsg, Parsed<Self::ServerMsg>>>>;
+
+    /// Message which subscribes, or unsubscribes, every channel of every symbol, with its request id
+    fn request(
+        &mut self,
+        subscribe: bool,
+        channels: &[Self::Channel],
+        symbols: Vec<String>,
+    ) -> anyhow::Result<(RequestId, Self::ClientMsg)>;
+
+    /// Server replies with `MarketEvent::Subscriptions`
+    fn list_subscriptions(&mut self) -> Self::ClientMsg;
+
+    /// Sent before connection is closed, if server needs it
+    fn unsubscribe_all(&self) -> Option<Self::ClientMsg>;
+
+    /// Normalizes server message, replies are matched with requests
+    fn events(
+        &mut self,
+        msg: Parsed<Self::ServerMsg>,
+        context: &Self::Context,
+    ) -> Vec<MarketEvent<Self::Channel>>;
+
+    /// Requests of lost connection are never replied, ids stay unique
+    fn forget_requests(&mut self);
+}
+
+struct PublicConnector<P> {
+    uri: Uri,
+    journal: Option<JournalConfig>,
+    protocol: PhantomData<fn() -> P>,
+}
+
+impl<P: MarketProtocol> Connector for PublicConnector<P> {
+    type Tx = P::ClientMsg;
+    type Rx = Parsed<P::ServerMsg>;
+
+    /// New journal file on every connection
+    async fn connect(&self) -> anyhow::Result<WsClient<P::ClientMsg, Parsed<P::ServerMsg>>> {
+        let journal = match &self.journal {
+            Some(journal) => journal.writer(P::JOURNAL_PREFIX).context("open journal")?,
+            None => None,
+        };
+        let codec = JournalingCodec {
+            inner: SimpleJsonCodec,
+            journal,
+        };
+        P::connect(self.uri.clone(), codec).await
+    }
+}
+
+/// Public market data of an exchange, which reconnects and restores subscriptions by itself
+pub struct MarketDataStream<'a, P: MarketProtocol> {
+    context: &'a P::Context,
+    client: ReconnectingClient<PublicConnector<P>>,
+    protocol: P,
+    subscriptions: BTreeMap<P::Channel, BTreeSet<Pair>>,
+}
+
+impl<'a, P: MarketProtocol> MarketDataStream<'a, P> {
+    /// Connects without subscriptions.
+    /// Raw messages are appended to journal, if it's configured, new file on every connection.
+    pub async fn connect(
+        context: &'a P::Context,
+        uri: Uri,
+        journal: Option<JournalConfig>,
+    ) -> anyhow::Result<Self> {
+        let connector = PublicConnector {
+            uri,
+            journal,
+            protocol: PhantomData,
+        };
+        Ok(Self {
+            context,
+            client: ReconnectingClient::connect(connector).await?,
+            protocol: P::default(),
+            subscriptions: BTreeMap::new(),
+        })
+    }
+
+    pub fn context(&self) -> &'a P::Context {
+        self.context
+    }
+
+    /// False while connection is lost, `next` reconnects
+    pub fn is_connected(&self) -> bool {
+        self.client.is_connected()
+    }
+
+    async fn request(
+        &mut self,
+        subscribe: bool,
+        channels: &[P::Channel],
+        pairs: &[Pair],
+    ) -> anyhow::Result<RequestId> {
+        let exchange_symbols = self.context.give(ExchangeSymbols);
+        let symbols = pairs
+            .iter()
+            .map(|pair| exchange_symbols.to_symbol(pair).map(String::from))
+            .collect::<anyhow::Result<_>>()
+            .context("convert pairs to exchange symbols")?;
+        let (id, msg) = self.protocol.request(subscribe, channels, symbols)?;
+        self.client.send(msg).await;
+        Ok(id)
+    }
+
+    /// Subscribes new connection to everything. If it's interrupted, it's started over,
+    /// subscriptions which were already restored are harmless errors.
+    async fn restore_subscriptions(&mut self) -> anyhow::Result<()> {
+        // channels with the same pairs are restored with one message
+        let mut groups: BTreeMap<&BTreeSet<Pair>, Vec<P::Channel>> = BTreeMap::new();
+        for (channel, pairs) in &self.subscriptions {
+            groups.entry(pairs).or_default().push(*channel);
+        }
+        let groups: Vec<(Vec<Pair>, Vec<P::Channel>)> = groups
+            .into_iter()
+            .map(|(pairs, channels)| (pairs.iter().cloned().collect(), channels))
+            .collect();
+        for (pairs, channels) in groups {
+            self.request(true, &channels, &pairs).await?;
+        }
+        Ok(())
+    }
+}
+
+impl<P: MarketProtocol> MarketStream for MarketDataStream<'_, P> {
+    type Channel = P::Channel;
+
+    async fn subscribe(
+        &mut self,
+        channels: &[P::Channel],
+        pairs: &[Pair],
+    ) -> anyhow::Result<RequestId> {
+        let id = self.request(true, channels, pairs).await?;
+        for channel in channels {
+            self.subscriptions
+                .entry(*channel)
+                .or_default()
+                .extend(pairs.iter().cloned());
+        }
+        Ok(id)
+    }
+
+    async fn unsubscribe(
+        &mut self,
+        channels: &[P::Channel],
+        pairs: &[Pair],
+    ) -> anyhow::Result<RequestId> {
+        let id = self.request(false, channels, pairs).await?;
+        for channel in channels {
+            if let Some(subscribed) = self.subscriptions.get_mut(channel) {
+                for pair in pairs {
+                    subscribed.remove(pair);
+                }
+                if subscribed.is_empty() {
+                    self.subscriptions.remove(channel);
+                }
+            }
+        }
+        Ok(id)
+    }
+
+    async fn resubscribe(&mut self, channels: &[P::Channel], pairs: &[Pair]) -> anyhow::Result<()> {
+        self.request(false, channels, pairs).await?;
+        self.request(true, channels, pairs).await?;
+        Ok(())
+    }
+
+    async fn list_subscriptions(&mut self) -> anyhow::Result<()> {
+        let msg = self.protocol.list_subscriptions();
+        self.client.send(msg).await;
+        Ok(())
+    }
+
+    async fn next(&mut self) -> anyhow::Result<MarketUpdate<P::Channel>> {
+        let events = match self.client.recv().await {
+            Received::Msg(msg) => self.protocol.events(msg, self.context),
+            Received::Reconnected => {
+                self.protocol.forget_requests();
+                self.restore_subscriptions().await?;
+                self.client.restored();
+                vec![MarketEvent::Reconnected]
+            }
+        };
+        Ok(MarketUpdate {
+            received_at: UnixMillis::now(),
+            events,
+        })
+    }
+
+    /// Unsubscribes from everything, if server needs it, and closes connection, also finishes journal
+    async fn close(mut self) {
+        if let Some(msg) = self.protocol.unsubscribe_all() {
+            self.client.send(msg).await;
+        }
+        self.client.close().await;
+    }
+}
@@ -10,6 +10,9 @@ pub use tokio_tungstenite::tungstenite::{Message, http::Uri};
 
 use crate::utils::Strict;
 
+pub mod market_data;
+pub mod reconnecting;
+
 pub struct WsConfig<C> {
     pub ping: Message,
     pub ping_interval: Duration,
@@ -44,6 +47,14 @@ impl<TX, RX> WsClient<TX, RX> {
     pub async fn send(&mut self, msg: TX) -> Result<(), TX> {
         self.connection.tx.send(msg).await.map_err(|err| err.0)
     }
+
+    /// Sends close frame to server and waits until connection is closed.
+    /// Messages received meanwhile are dropped.
+    pub async fn close(self) {
+        let MpscDuplex { mut rx, tx } = self.connection;
+        drop(tx);
+        while rx.recv().await.is_some() {}
+    }
 }
 
 struct MpscDuplex<TX, RX> {
@@ -58,6 +69,8 @@ impl<TX, RX> MpscDuplex<TX, RX> {
     }
 }
 
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
 type WebSocketStream =
     tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
 
@@ -112,7 +125,7 @@ where
                             ping = None;
                         };
                     } else {
-                        break;
+                        return self.close().await;
                     }
                 }
                 _ = ping_fut => {
@@ -135,6 +148,23 @@ where
         }
         Ok(())
     }
+
+    // User dropped its side, so close handshake is done without forwarding messages to user
+    async fn close(mut self) -> anyhow::Result<()> {
+        self.wss
+            .close(None)
+            .await
+            .context("send close message to server")?;
+        let drain = async {
+            while let Some(res) = self.wss.next().await {
+                res.context("recive WebSocket message from server")?;
+            }
+            anyhow::Ok(())
+        };
+        timeout(CLOSE_TIMEOUT, drain)
+            .await
+            .context("wait for server to close WebSocket connection")?
+    }
 }
 
 pub trait CodecIn<MSG> {
@@ -156,19 +186,52 @@ impl<MSG: serde::Serialize> CodecOut<MSG> for SimpleJsonCodec {
 
 impl<MSG: serde::de::DeserializeOwned> CodecIn<MSG> for SimpleJsonCodec {
     fn process_in(&mut self, msg: Message) -> anyhow::Result<Option<MSG>> {
+        match CodecIn::<Parsed<MSG>>::process_in(self, msg)? {
+            Some(Parsed::Msg(msg)) => Ok(Some(msg)),
+            Some(Parsed::Unparsed(Unparsed { raw, error })) => {
+                log::error!("WebSocket server sent invalid JSON: {raw:#?}");
+                Err(error)
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// Lets user keep unparsable messages, instead of closing the connection
+impl<MSG: serde::de::DeserializeOwned> CodecIn<Parsed<MSG>> for SimpleJsonCodec {
+    fn process_in(&mut self, msg: Message) -> anyhow::Result<Option<Parsed<MSG>>> {
         match msg {
             Message::Text(text) => {
-                let res = serde_json::from_str(text.as_str());
-                if res.is_err() {
-                    log::error!("WebSocket server sent invalid JSON: {text:#?}");
-                }
-                return res.context("deserialize JSON from WebSocket text message");
+                let res = serde_json::from_str(text.as_str())
+                    .context("deserialize JSON from WebSocket text message");
+                return Ok(Some(match res {
+                    Ok(msg) => Parsed::Msg(msg),
+                    Err(error) => Parsed::Unparsed(Unparsed {
+                        raw: text.as_str().into(),
+                        error,
+                    }),
+                }));
             }
             Message::Close(close) => {
                 log::debug!("WebSocket server sent WS close message: {close:?}")
             }
+            // pings are answered by tungstenite itself
+            Message::Ping(_) | Message::Pong(_) => {}
             msg => log::warn!("WebSocket server sent unsupported WS message: {msg:?}"),
         }
         Ok(None)
     }
 }
+
+#[derive(Debug)]
+pub enum Parsed<MSG> {
+    Msg(MSG),
+    Unparsed(Unparsed),
+}
+
+/// Text message from server which codec couldn't parse
+#[derive(Debug)]
+pub struct Unparsed {
+    pub raw: String,
+    pub error: anyhow::Error,
+}
@@ -0,0 +1,136 @@
+//! Connection which is established again whenever it's lost
+
+use std::{future::Future, time::Duration};
+
+use tokio::time::{Instant, sleep_until};
+
+use super::WsClient;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// Establishes connections of `ReconnectingClient`, e.g. with journal or authentication
+pub trait Connector {
+    type Tx;
+    type Rx;
+
+    fn connect(&self) -> impl Future<Output = anyhow::Result<WsClient<Self::Tx, Self::Rx>>>;
+}
+
+/// Message of `ReconnectingClient`
+#[derive(Debug)]
+pub enum Received<RX> {
+    Msg(RX),
+    /// connection was lost and is established again, messages in between are lost.
+    /// It's received until `ReconnectingClient::restored` is called, so restoring can be interrupted.
+    Reconnected,
+}
+
+enum Connection<TX, RX> {
+    Open {
+        client: WsClient<TX, RX>,
+        /// user restored state of connection, e.g. subscriptions
+        restored: bool,
+    },
+    Lost {
+        delay: Duration,
+        retry_at: Instant,
+    },
+}
+
+impl<TX, RX> Connection<TX, RX> {
+    fn lost(delay: Duration) -> Self {
+        Self::Lost {
+            delay,
+            retry_at: Instant::now() + delay,
+        }
+    }
+}
+
+/// Client which connects again when connection is lost, delay between attempts grows until it succeeds.
+/// Connection is kept in the client, so cancelled `recv` resumes reconnection on the next call.
+pub struct ReconnectingClient<C: Connector> {
+    connector: C,
+    connection: Connection<C::Tx, C::Rx>,
+}
+
+impl<C: Connector> ReconnectingClient<C> {
+    pub async fn connect(connector: C) -> anyhow::Result<Self> {
+        let client = connector.connect().await?;
+        Ok(Self {
+            connector,
+            connection: Connection::Open {
+                client,
+                restored: true,
+            },
+        })
+    }
+
+    pub fn connector(&self) -> &C {
+        &self.connector
+    }
+
+    pub fn is_connected(&self) -> bool {
+        matches!(self.connection, Connection::Open { .. })
+    }
+
+    /// Message is dropped if connection is lost, it's noticed and restored by `recv`
+    pub async fn send(&mut self, msg: C::Tx) {
+        let sent = match &mut self.connection {
+            Connection::Open { client, .. } => client.send(msg).await.is_ok(),
+            Connection::Lost { .. } => false,
+        };
+        if !sent {
+            log::warn!("WS connection is closed, message is dropped");
+        }
+    }
+
+    /// Waits for the next server message, or for new connection if it's lost. Cancel safe.
+    pub async fn recv(&mut self) -> Received<C::Rx> {
+        loop {
+            match &mut self.connection {
+                Connection::Open {
+                    client,
+                    restored: true,
+                } => {
+                    if let Some(msg) = client.recv().await {
+                        return Received::Msg(msg);
+                    }
+                    log::warn!("WS connection is lost, reconnect");
+                    self.connection = Connection::lost(RECONNECT_DELAY);
+                }
+                Connection::Open {
+                    restored: false, ..
+                } => return Received::Reconnected,
+                Connection::Lost { delay, retry_at } => {
+                    let delay = *delay;
+                    sleep_until(*retry_at).await;
+                    self.connection = match self.connector.connect().await {
+                        Ok(client) => Connection::Open {
+                            client,
+                            restored: false,
+                        },
+                        Err(err) => {
+                            log::error!("Can't reconnect to WS server: {err:#}");
+                            Connection::lost((delay * 2).min(MAX_RECONNECT_DELAY))
+                        }
+                    };
+                }
+            }
+        }
+    }
+
+    /// State of new connection is restored, `recv` gives its messages from now on
+    pub fn restored(&mut self) {
+        if let Connection::Open { restored, .. } = &mut self.connection {
+            *restored = true;
+        }
+    }
+
+    /// Closes connection if it's established
+    pub async fn close(self) {
+        if let Connection::Open { client, .. } = self.connection {
+            client.close().await;
+        }
+    }
+}